@@ -0,0 +1,406 @@
+// Module for reading activity files packed inside zip, gzip and tar.gz archives.
+//
+// Members of an archive are addressed by a virtual path made of the archive
+// path, a separator and the member name, e.g. `export.zip!/123456.fit`.
+use crate::source::is_activity_name;
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type TarMembers = Arc<HashMap<String, Vec<u8>>>;
+// Members of one archive, unpacked by the first reader that needs them.
+type TarSlot = Arc<OnceLock<Result<TarMembers, String>>>;
+// An archive by its path and modification time, so a replaced archive is
+// read again.
+type ArchiveKey = (PathBuf, Option<SystemTime>);
+// An open zip archive with its central directory read, shared by the
+// readers of its members.
+type ZipSlot = Arc<Mutex<zip::ZipArchive<File>>>;
+
+// Separates the archive path from the member name in a virtual path.
+pub const ARCHIVE_SEPARATOR: &str = "!/";
+
+// Number of tar.gz archives kept unpacked in memory.
+const TAR_CACHE_SIZE: usize = 4;
+
+// The most recently read tar.gz archives, least recent first. Tar has no
+// index, so the activity members are decompressed once and served from
+// memory while a range spanning a few exports is being read.
+static TAR_CACHE: Mutex<Vec<(ArchiveKey, TarSlot)>> = Mutex::new(Vec::new());
+
+// Number of zip archives kept open.
+const ZIP_CACHE_SIZE: usize = 4;
+
+// The most recently read zip archives, least recent first, so the central
+// directory is read once rather than for every member.
+static ZIP_CACHE: Mutex<Vec<(ArchiveKey, ZipSlot)>> = Mutex::new(Vec::new());
+
+fn archive_key(archive: &Path) -> ArchiveKey {
+    let modified = std::fs::metadata(archive).and_then(|m| m.modified()).ok();
+    (archive.to_path_buf(), modified)
+}
+
+// Lower case file name of a path, or an empty string.
+fn lower_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn is_tar_gz_name(name: &str) -> bool {
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+// True if the path is an archive that may contain several activity files.
+pub fn is_archive(path: &Path) -> bool {
+    let name = lower_name(path);
+    name.ends_with(".zip") || is_tar_gz_name(&name)
+}
+
+// Build the virtual path addressing a member of an archive.
+pub fn virtual_path(archive: &Path, member: &str) -> PathBuf {
+    PathBuf::from(format!(
+        "{}{}{}",
+        archive.display(),
+        ARCHIVE_SEPARATOR,
+        member
+    ))
+}
+
+// Split a virtual path into the archive path and the member name.
+pub fn split_virtual_path(path: &Path) -> Option<(PathBuf, String)> {
+    let path_str = path.to_str()?;
+    let (archive, member) = path_str.split_once(ARCHIVE_SEPARATOR)?;
+    Some((PathBuf::from(archive), member.to_string()))
+}
+
+// Size and modification time (unix seconds) of the file holding an
+// activity; for archive members, those of the archive.
+pub fn file_meta(path: &Path) -> Option<(i64, i64)> {
+    let on_disk = match split_virtual_path(path) {
+        Some((archive, _)) => archive,
+        None => path.to_path_buf(),
+    };
+    let meta = std::fs::metadata(on_disk).ok()?;
+    let modified = meta
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs() as i64;
+    Some((meta.len() as i64, modified))
+}
+
+// Decompress the member bytes if the member itself is gzipped (e.g. *.fit.gz).
+fn maybe_gunzip(name: &str, bytes: Vec<u8>) -> Result<Vec<u8>, BoxError> {
+    if !name.to_lowercase().ends_with(".gz") {
+        return Ok(bytes);
+    }
+    let mut out = Vec::new();
+    GzDecoder::new(&bytes[..]).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+// Visit every activity member of a zip or tar.gz archive, passing the virtual
+// path and the (decompressed) file bytes to the callback.
+pub fn for_each_entry<F>(archive: &Path, mut f: F) -> Result<(), BoxError>
+where
+    F: FnMut(PathBuf, &[u8]),
+{
+    let name = lower_name(archive);
+    if is_tar_gz_name(&name) {
+        let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let member = entry.path()?.to_string_lossy().to_string();
+            if !entry.header().entry_type().is_file() || !is_activity_name(&member) {
+                continue;
+            }
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            if let Ok(bytes) = maybe_gunzip(&member, bytes) {
+                f(virtual_path(archive, &member), &bytes);
+            }
+        }
+    } else {
+        let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            let member = entry.name().to_string();
+            if !entry.is_file() || !is_activity_name(&member) {
+                continue;
+            }
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            if let Ok(bytes) = maybe_gunzip(&member, bytes) {
+                f(virtual_path(archive, &member), &bytes);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn unpack_tar(archive: &Path) -> Result<TarMembers, BoxError> {
+    let mut members = HashMap::new();
+    for_each_entry(archive, |vpath, bytes| {
+        if let Some((_, member)) = split_virtual_path(&vpath) {
+            members.insert(member, bytes.to_vec());
+        }
+    })?;
+    Ok(Arc::new(members))
+}
+
+// Load all activity members of a tar.gz archive, reusing the cached copy when
+// the archive was read recently.
+fn tar_members(archive: &Path) -> Result<TarMembers, BoxError> {
+    let key = archive_key(archive);
+    // Only find or add the slot under the lock: readers of the same archive
+    // wait on its slot for a single pass, other archives unpack in parallel.
+    let slot = {
+        let mut cache = TAR_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        let slot = match cache.iter().position(|(k, _)| k.0 == key.0) {
+            Some(i) if cache[i].0 == key => cache.remove(i).1,
+            Some(i) => {
+                cache.remove(i);
+                TarSlot::default()
+            }
+            None => TarSlot::default(),
+        };
+        cache.push((key, Arc::clone(&slot)));
+        if cache.len() > TAR_CACHE_SIZE {
+            cache.remove(0);
+        }
+        slot
+    };
+    match slot.get_or_init(|| unpack_tar(archive).map_err(|e| e.to_string())) {
+        Ok(members) => Ok(Arc::clone(members)),
+        Err(e) => {
+            // Try again next time, the archive may be readable by then.
+            let mut cache = TAR_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+            cache.retain(|(_, s)| !Arc::ptr_eq(s, &slot));
+            Err(e.clone().into())
+        }
+    }
+}
+
+// The open zip archive, reusing the cached one when the archive was read
+// recently and has not changed since.
+fn zip_archive(archive: &Path) -> Result<ZipSlot, BoxError> {
+    let key = archive_key(archive);
+    {
+        let mut cache = ZIP_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = cache.iter().position(|(k, _)| *k == key) {
+            let entry = cache.remove(i);
+            let slot = Arc::clone(&entry.1);
+            cache.push(entry);
+            return Ok(slot);
+        }
+    }
+    // Read the central directory outside the lock, so other archives are
+    // opened in parallel. Failures are not kept.
+    let slot: ZipSlot = Arc::new(Mutex::new(zip::ZipArchive::new(File::open(archive)?)?));
+    let mut cache = ZIP_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.retain(|(k, _)| k.0 != key.0);
+    cache.push((key, Arc::clone(&slot)));
+    if cache.len() > ZIP_CACHE_SIZE {
+        cache.remove(0);
+    }
+    Ok(slot)
+}
+
+// Read a single member of an archive.
+fn read_member(archive: &Path, member: &str) -> Result<Vec<u8>, BoxError> {
+    if is_tar_gz_name(&lower_name(archive)) {
+        let members = tar_members(archive)?;
+        return members
+            .get(member)
+            .cloned()
+            .ok_or_else(|| format!("{} not found in archive", member).into());
+    }
+    let bytes = {
+        let slot = zip_archive(archive)?;
+        let mut zip = slot.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entry = zip.by_name(member)?;
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        bytes
+    };
+    maybe_gunzip(member, bytes)
+}
+
+// Open an activity for reading, whether it is a plain file, a gzipped file
+// or a member of an archive addressed by a virtual path.
+pub fn open_activity(path: &Path) -> Result<Box<dyn Read + Send>, BoxError> {
+    if let Some((archive, member)) = split_virtual_path(path) {
+        return Ok(Box::new(Cursor::new(read_member(&archive, &member)?)));
+    }
+    let file = File::open(path)?;
+    if lower_name(path).ends_with(".gz") {
+        return Ok(Box::new(GzDecoder::new(file)));
+    }
+    Ok(Box::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use std::time::Duration;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("curro-all-archive-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn write_zip(path: &Path, members: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, bytes) in members {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn write_tar_gz(path: &Path, members: &[(&str, &[u8])]) {
+        let encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, bytes) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, *bytes).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    // Give a rewritten archive a new modification time, whatever the
+    // resolution of the file system clock.
+    fn touch(path: &Path, seconds: u64) {
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    fn read(path: &Path) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        open_activity(path)
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        bytes
+    }
+
+    fn members(archive: &Path) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for_each_entry(archive, |path, _| paths.push(path)).unwrap();
+        paths
+    }
+
+    #[test]
+    fn virtual_paths_split_at_the_first_separator() {
+        let path = virtual_path(Path::new("/data/export.zip"), "runs/1.fit");
+        assert_eq!(path, PathBuf::from("/data/export.zip!/runs/1.fit"));
+        assert_eq!(
+            split_virtual_path(&path),
+            Some((PathBuf::from("/data/export.zip"), "runs/1.fit".to_string()))
+        );
+        assert_eq!(split_virtual_path(Path::new("/data/1.fit")), None);
+    }
+
+    #[test]
+    fn gzipped_files_are_decompressed() {
+        let dir = temp_dir("gz");
+        let path = dir.join("run.fit.gz");
+        std::fs::write(&path, gzip(b"fit bytes")).unwrap();
+        assert_eq!(read(&path), b"fit bytes");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn zip_members_are_read_from_the_current_archive() {
+        let dir = temp_dir("zip");
+        let archive = dir.join("export.zip");
+        let gpx = gzip(b"gpx bytes");
+        write_zip(
+            &archive,
+            &[("a.fit", b"first"), ("notes.txt", b"x"), ("b.gpx.gz", &gpx)],
+        );
+        touch(&archive, 1_000);
+        assert_eq!(
+            members(&archive),
+            vec![
+                virtual_path(&archive, "a.fit"),
+                virtual_path(&archive, "b.gpx.gz")
+            ]
+        );
+        assert_eq!(read(&virtual_path(&archive, "a.fit")), b"first");
+        assert_eq!(read(&virtual_path(&archive, "b.gpx.gz")), b"gpx bytes");
+        assert!(open_activity(&virtual_path(&archive, "c.fit")).is_err());
+
+        write_zip(&archive, &[("a.fit", b"second")]);
+        touch(&archive, 2_000);
+        assert_eq!(read(&virtual_path(&archive, "a.fit")), b"second");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn tar_members_are_read_from_the_current_archive() {
+        let dir = temp_dir("tar");
+        let archive = dir.join("export.tar.gz");
+        let tcx = gzip(b"tcx bytes");
+        write_tar_gz(
+            &archive,
+            &[("a.fit", b"first"), ("notes.txt", b"x"), ("b.tcx.gz", &tcx)],
+        );
+        touch(&archive, 1_000);
+        assert_eq!(
+            members(&archive),
+            vec![
+                virtual_path(&archive, "a.fit"),
+                virtual_path(&archive, "b.tcx.gz")
+            ]
+        );
+        assert_eq!(read(&virtual_path(&archive, "a.fit")), b"first");
+        assert_eq!(read(&virtual_path(&archive, "b.tcx.gz")), b"tcx bytes");
+        assert!(open_activity(&virtual_path(&archive, "c.fit")).is_err());
+
+        write_tar_gz(&archive, &[("a.fit", b"second")]);
+        touch(&archive, 2_000);
+        assert_eq!(read(&virtual_path(&archive, "a.fit")), b"second");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn archive_members_have_the_meta_of_the_archive() {
+        let dir = temp_dir("meta");
+        let archive = dir.join("export.zip");
+        write_zip(&archive, &[("a.fit", b"first")]);
+        touch(&archive, 1_000);
+        let size = std::fs::metadata(&archive).unwrap().len() as i64;
+        assert_eq!(file_meta(&archive), Some((size, 1_000)));
+        assert_eq!(
+            file_meta(&virtual_path(&archive, "a.fit")),
+            Some((size, 1_000))
+        );
+        assert_eq!(file_meta(&dir.join("missing.fit")), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,267 @@
+// Command line access to the Curro-All activity database.
+//
+// Usage:
+//   curro-all-cli <database> index <directory>
+//   curro-all-cli <database> query [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--sport S]
+//                                  [--device D] [--tag T] [--min-distance METERS]
+//   curro-all-cli <database> stats [query options] [--units metric|us]
+//                                  [--weight distance|duration]
+//   curro-all-cli <database> volume [query options] [--units metric|us]
+//                                  [--period week|month] [--max-increase PERCENT]
+//   curro-all-cli <database> sql "<statement>"
+//
+// Given --library <directory>, query, stats and volume also read the sidecar
+// of that library: its corrections replace the recorded values, its manual
+// activities are included and --tag picks the activities it tags.
+use chrono::{NaiveDate, TimeZone, Utc};
+use curro_all_core::data::{Units, convert_all_stats, process_fit_directory};
+use curro_all_core::metrics::METRICS;
+use curro_all_core::sidecar::{Sidecar, library_sidecar, manual_path};
+use curro_all_core::statistics::{Weighting, range_statistics};
+use curro_all_core::store::{ActivityQuery, ActivityRecord, Store};
+use curro_all_core::volume::{Period, period_volumes, write_volume_csv};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: curro-all-cli <database> index <directory>
+       curro-all-cli <database> query [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--sport S] [--device D] [--tag T] [--min-distance METERS]
+       curro-all-cli <database> stats [query options] [--units metric|us] [--weight distance|duration]
+       curro-all-cli <database> volume [query options] [--units metric|us] [--period week|month] [--max-increase PERCENT]
+       curro-all-cli <database> sql \"<statement>\"
+query, stats and volume take --library <directory> to apply its corrections, manual activities and tags;
+--tag needs it";
+
+fn parse_date(text: &str, end_of_day: bool) -> Result<chrono::DateTime<Utc>, Box<dyn Error>> {
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(Utc.from_utc_datetime(&time.ok_or("invalid date")?))
+}
+
+fn parse_query(args: &[String]) -> Result<ActivityQuery, Box<dyn Error>> {
+    let mut query = ActivityQuery::default();
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let value = iter.next().ok_or(format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--from" => query.start = Some(parse_date(value, false)?),
+            "--to" => query.end = Some(parse_date(value, true)?),
+            "--sport" => query = query.sport(value),
+            "--device" => query = query.device(value),
+            "--min-distance" => query = query.min_distance(value.parse()?),
+            _ => return Err(format!("unknown option {}", flag).into()),
+        }
+    }
+    Ok(query)
+}
+
+// Take the value of an option out of the arguments.
+fn take_option(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == flag)?;
+    let value = args.get(i + 1).cloned();
+    args.drain(i..(i + 2).min(args.len()));
+    value
+}
+
+fn take_units(args: &mut Vec<String>) -> Units {
+    match take_option(args, "--units").as_deref() {
+        Some("us") => Units::US,
+        _ => Units::Metric,
+    }
+}
+
+// Whether a manual activity passes the query; it has no device.
+fn matches_manual(record: &ActivityRecord, query: &ActivityQuery) -> bool {
+    query.start.is_none_or(|start| record.timestamp >= start)
+        && query.end.is_none_or(|end| record.timestamp <= end)
+        && query
+            .sport
+            .as_ref()
+            .is_none_or(|sport| record.info.sport.eq_ignore_ascii_case(sport))
+        && query.device.is_none()
+        && query
+            .min_distance
+            .is_none_or(|distance| record.stats.distance >= distance)
+}
+
+// An activity with its tags from the sidecar.
+type TaggedRecord = (ActivityRecord, Vec<String>);
+
+// The stored activities matching the query, with their tags, the
+// corrections and the manual activities of the library's sidecar when one
+// is given. Tags are kept in the sidecar only, so a tag needs the library.
+fn library_records(
+    store: &Store,
+    query: &ActivityQuery,
+    tag: Option<&str>,
+    library: Option<&Path>,
+) -> Result<Vec<TaggedRecord>, Box<dyn Error>> {
+    let mut records = store.query(query)?;
+    let Some(library) = library else {
+        if tag.is_some() {
+            return Err("--tag needs --library: tags are kept in the library sidecar".into());
+        }
+        return Ok(records.into_iter().map(|r| (r, Vec::new())).collect());
+    };
+    let sidecar_file = library_sidecar(library);
+    let sidecar = Sidecar::load(&sidecar_file).map_err(|e| e.to_string())?;
+    for record in &mut records {
+        if let Some(correction) = sidecar.correction(&record.timestamp) {
+            correction.apply(&mut record.stats);
+            correction.apply_info(&mut record.info);
+        }
+    }
+    for activity in &sidecar.manual {
+        let mut record = ActivityRecord {
+            path: manual_path(&sidecar_file, &activity.start),
+            timestamp: activity.start,
+            info: activity.info(),
+            stats: activity.stats(),
+        };
+        if let Some(correction) = sidecar.correction(&activity.start) {
+            correction.apply(&mut record.stats);
+            correction.apply_info(&mut record.info);
+        }
+        if matches_manual(&record, query) {
+            records.push(record);
+        }
+    }
+    let mut records: Vec<TaggedRecord> = records
+        .into_iter()
+        .filter(|r| tag.is_none_or(|tag| sidecar.has_tag(&r.timestamp, tag)))
+        .map(|r| {
+            let tags = sidecar.meta_or_default(&r.timestamp).tags;
+            (r, tags)
+        })
+        .collect();
+    records.sort_by_key(|(r, _)| r.timestamp);
+    Ok(records)
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args.len() < 2 {
+        return Err(USAGE.into());
+    }
+    let mut store = Store::open(Path::new(&args[0]))?;
+    let mut rest = args[2..].to_vec();
+    let library = take_option(&mut rest, "--library").map(PathBuf::from);
+    let library = library.as_deref();
+    let tag = take_option(&mut rest, "--tag");
+    let tag = tag.as_deref();
+    match (args[1].as_str(), &rest[..]) {
+        ("index", [dir]) => {
+            let index = process_fit_directory(Path::new(dir));
+            let parsed = store.sync_index(&index)?;
+            let pruned = store.prune_missing()?;
+            println!(
+                "{} activities found, {} parsed, {} removed",
+                index.len(),
+                parsed,
+                pruned
+            );
+        }
+        ("query", rest) => {
+            let records = library_records(&store, &parse_query(rest)?, tag, library)?;
+            println!(
+                "date\tsport\tdevice\tdistance_m\tduration_s\tmoving_s\tcalories\tascent_m\tdescent_m\ttags\tpath"
+            );
+            for (record, tags) in records {
+                println!(
+                    "{}\t{}\t{}\t{:.1}\t{:.0}\t{:.0}\t{}\t{}\t{}\t{}\t{}",
+                    record.timestamp.format("%Y-%m-%d %H:%M"),
+                    record.info.sport,
+                    record.info.device,
+                    record.stats.distance,
+                    record.stats.duration,
+                    record.stats.moving_time,
+                    record.stats.calories,
+                    record.stats.ascent,
+                    record.stats.descent,
+                    tags.join(","),
+                    record.path.display()
+                );
+            }
+        }
+        ("stats", rest) => {
+            let mut rest = rest.to_vec();
+            let units = take_units(&mut rest);
+            let weighting =
+                Weighting::from_name(&take_option(&mut rest, "--weight").unwrap_or_default());
+            let records = library_records(&store, &parse_query(&rest)?, tag, library)?;
+            let raw: Vec<_> = records.iter().map(|(r, _)| r.plottable()).collect();
+            let statistics = range_statistics(&convert_all_stats(&raw, &units), weighting);
+            println!("activities\t{}", statistics.count);
+            println!("metric\tcount\ttotal\tmean\tmedian\tstd_dev\tp10\tp90\tmin\tmax");
+            for (metric, s) in METRICS.iter().zip(&statistics.metrics) {
+                if let Some(s) = s {
+                    let total = if metric.additive {
+                        format!("{:.2}", s.total)
+                    } else {
+                        String::new()
+                    };
+                    println!(
+                        "{}\t{}\t{}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}",
+                        metric.name,
+                        s.count,
+                        total,
+                        s.mean,
+                        s.median,
+                        s.std_dev,
+                        s.p10,
+                        s.p90,
+                        s.min,
+                        s.max
+                    );
+                }
+            }
+        }
+        ("volume", rest) => {
+            let mut rest = rest.to_vec();
+            let units = take_units(&mut rest);
+            let period = match take_option(&mut rest, "--period").as_deref() {
+                Some("month") => Period::Month,
+                _ => Period::Week,
+            };
+            let max_increase = match take_option(&mut rest, "--max-increase") {
+                Some(value) => value.parse()?,
+                None => 10.0,
+            };
+            let query = parse_query(&rest)?;
+            let raw: Vec<_> = library_records(&store, &query, tag, library)?
+                .iter()
+                .map(|(r, _)| r.plottable())
+                .collect();
+            let (Some(first), Some(last)) = (raw.first(), raw.last()) else {
+                return Ok(());
+            };
+            let start = query.start.unwrap_or(first.timestamp).date_naive();
+            let end = query.end.unwrap_or(last.timestamp).date_naive();
+            let volumes = period_volumes(&convert_all_stats(&raw, &units), period, start, end);
+            write_volume_csv(&volumes, max_increase, &mut std::io::stdout())?;
+        }
+        ("sql", [statement]) => {
+            let (columns, rows) = store.execute_sql(statement)?;
+            println!("{}", columns.join("\t"));
+            for row in rows {
+                println!("{}", row.join("\t"));
+            }
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// Module for the calendar heatmap: daily training totals laid out like a
+// contribution calendar, one column per week and one row per weekday.
+use crate::data::PlottableData;
+use crate::zones::{ActivityZones, ZoneTimes};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use std::collections::BTreeMap;
+
+// Room (pixels) for the caption and month labels above the cells, and for
+// the weekday labels to their left.
+pub const CALENDAR_TOP: f64 = 50.0;
+pub const CALENDAR_LEFT: f64 = 40.0;
+const CALENDAR_MARGIN: f64 = 10.0;
+// Cells never grow past this size (pixels), so short ranges stay compact.
+const MAX_CELL_SIZE: f64 = 40.0;
+// Share of a cell left blank between neighbours.
+const CELL_GAP: f64 = 0.15;
+// The calendar never covers more than a year.
+const MAX_WEEKS: i64 = 53;
+// Columns start on Sunday, like the weekly ranges of get_time_range.
+pub const WEEK_START: Weekday = Weekday::Sun;
+
+// What colors a calendar cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarMetric {
+    Distance,
+    Duration,
+    Load,
+}
+
+// Totals of all activities of one day, in the units of the stats they were
+// summed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DayTotals {
+    pub count: usize,
+    pub distance: f64,
+    pub duration: f64,
+    pub load: f64,
+}
+
+impl DayTotals {
+    pub fn value(&self, metric: CalendarMetric) -> f64 {
+        match metric {
+            CalendarMetric::Distance => self.distance,
+            CalendarMetric::Duration => self.duration,
+            CalendarMetric::Load => self.load,
+        }
+    }
+}
+
+// Training load of an activity from its time in heart rate zone (Edwards
+// TRIMP): minutes in each zone weighted by the zone number.
+pub fn zone_load(times: &ZoneTimes) -> f64 {
+    times
+        .iter()
+        .enumerate()
+        .map(|(i, t)| t / 60.0 * (i + 1) as f64)
+        .sum()
+}
+
+// Sum the stats and heart rate load of the activities per day.
+pub fn daily_totals(
+    stats: &[PlottableData],
+    zones: &[(DateTime<Utc>, ActivityZones)],
+) -> BTreeMap<NaiveDate, DayTotals> {
+    let mut days: BTreeMap<NaiveDate, DayTotals> = BTreeMap::new();
+    for item in stats {
+        let day = days.entry(item.timestamp.date_naive()).or_default();
+        day.count += 1;
+        day.distance += item.stats.distance;
+        day.duration += item.stats.duration;
+    }
+    for (timestamp, z) in zones {
+        if let Some(hr) = &z.hr {
+            days.entry(timestamp.date_naive()).or_default().load += zone_load(hr);
+        }
+    }
+    days
+}
+
+// Position of the cells of a date range in a drawing area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalendarLayout {
+    // First day of the first column, always a week start.
+    pub first_column: NaiveDate,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub weeks: usize,
+    // Width and height of a cell, gap included (pixels).
+    pub cell: f64,
+}
+
+impl CalendarLayout {
+    // Fit the weeks from start to end into a width x height area. Ranges
+    // longer than a year keep their last year.
+    pub fn new(
+        start: NaiveDate,
+        end: NaiveDate,
+        week_start: Weekday,
+        width: f64,
+        height: f64,
+    ) -> Self {
+        let start = start.max(end - Duration::weeks(MAX_WEEKS) + Duration::days(1));
+        let first_column = start.week(week_start).first_day();
+        let weeks = ((end - first_column).num_days() / 7 + 1).max(1) as usize;
+        let cell = ((width - CALENDAR_LEFT - CALENDAR_MARGIN) / weeks as f64)
+            .min((height - CALENDAR_TOP - CALENDAR_MARGIN) / 7.0)
+            .clamp(1.0, MAX_CELL_SIZE);
+        CalendarLayout {
+            first_column,
+            start,
+            end,
+            weeks,
+            cell,
+        }
+    }
+
+    // Every day shown, in order.
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.start.iter_days().take_while(|d| *d <= self.end)
+    }
+
+    // Top-left corner and side (pixels) of the cell of a date.
+    pub fn cell_rect(&self, date: NaiveDate) -> Option<(f64, f64, f64)> {
+        if date < self.start || date > self.end {
+            return None;
+        }
+        let offset = (date - self.first_column).num_days();
+        let x = CALENDAR_LEFT + (offset / 7) as f64 * self.cell;
+        let y = CALENDAR_TOP + (offset % 7) as f64 * self.cell;
+        Some((x, y, self.cell * (1.0 - CELL_GAP)))
+    }
+
+    // Vertical position (pixels) of the middle of a weekday row, 0 being
+    // the week start.
+    pub fn row_center(&self, row: usize) -> f64 {
+        CALENDAR_TOP + (row as f64 + 0.5) * self.cell
+    }
+
+    // The date whose cell is under a point, if any.
+    pub fn date_at(&self, x: f64, y: f64) -> Option<NaiveDate> {
+        let (column, row) = (
+            (x - CALENDAR_LEFT) / self.cell,
+            (y - CALENDAR_TOP) / self.cell,
+        );
+        if !(0.0..self.weeks as f64).contains(&column) || !(0.0..7.0).contains(&row) {
+            return None;
+        }
+        let date = self.first_column + Duration::days(column as i64 * 7 + row as i64);
+        self.cell_rect(date)
+            .filter(|(cx, cy, side)| x <= cx + side && y <= cy + side)
+            .map(|_| date)
+    }
+
+    // First column of each month (its first day), for the month labels.
+    pub fn month_starts(&self) -> Vec<(usize, NaiveDate)> {
+        self.days()
+            .filter(|d| d.day() == 1 || (*d == self.start && d.day() < 20))
+            .map(|d| (((d - self.first_column).num_days() / 7) as usize, d))
+            .collect()
+    }
+}
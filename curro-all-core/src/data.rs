@@ -1,22 +1,36 @@
-use crate::config::Units;
+use crate::archive::{for_each_entry, is_archive, open_activity};
 use crate::i18n::tr;
+use crate::laps::classify_laps;
+use crate::records::fill_elapsed;
+use crate::sidecar::{is_manual_path, read_manual_activity};
+use crate::source::{ActivityInfo, BoxError, ParsedActivity, is_activity_name, source_for};
 use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
 use dashmap::DashMap;
 use rayon::prelude::*;
-use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use walkdir::WalkDir;
 
+//Unit of measure system.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Units {
+    Metric,
+    US,
+    None,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct SessionStats {
     pub distance: f64,
     pub calories: u16,
+    // Elapsed time, stops included.
     pub duration: f64,
     pub enhanced_speed: f64,
     pub ascent: u16,
     pub descent: u16,
+    // Time spent moving (the timer time of FIT files), stops excluded.
+    pub moving_time: f64,
 }
 
 impl Default for SessionStats {
@@ -28,15 +42,118 @@ impl Default for SessionStats {
             enhanced_speed: 0.0,
             ascent: 0,
             descent: 0,
+            moving_time: 0.0,
         }
     }
 }
 
+// Read the session totals of an activity file of any supported format, or
+// of a manual activity.
 pub fn extract_session_data(
     path: &Path,
 ) -> Result<SessionStats, Box<dyn std::error::Error + Send + Sync>> {
-    let mut file = File::open(path)?;
-    let messages = fitparser::from_reader(&mut file)?;
+    if is_manual_path(path) {
+        return Ok(read_manual_activity(path)?.stats());
+    }
+    let source = source_for(path).ok_or("Unsupported activity format")?;
+    let mut reader = open_activity(path)?;
+    source.session_stats(&mut reader)
+}
+
+// Read the sport and recording device of an activity file of any supported
+// format, or the sport of a manual activity.
+pub fn extract_activity_info(
+    path: &Path,
+) -> Result<ActivityInfo, Box<dyn std::error::Error + Send + Sync>> {
+    if is_manual_path(path) {
+        return Ok(read_manual_activity(path)?.info());
+    }
+    let source = source_for(path).ok_or("Unsupported activity format")?;
+    let mut reader = open_activity(path)?;
+    source.activity_info(&mut reader)
+}
+
+// Read everything about an activity file of any supported format from a
+// single pass over it, or the totals and sport of a manual activity.
+pub fn read_activity(path: &Path) -> Result<ParsedActivity, BoxError> {
+    if is_manual_path(path) {
+        let manual = read_manual_activity(path)?;
+        return Ok(ParsedActivity {
+            stats: manual.stats(),
+            info: manual.info(),
+            ..Default::default()
+        });
+    }
+    let source = source_for(path).ok_or("Unsupported activity format")?;
+    let mut reader = open_activity(path)?;
+    let mut activity = source.parse(&mut reader)?;
+    classify_laps(&mut activity.laps);
+    fill_elapsed(&mut activity.records);
+    Ok(activity)
+}
+
+// Read the sport (Session message) and device (FileId message) of a FIT stream.
+pub fn fit_activity_info(
+    mut reader: &mut dyn Read,
+) -> Result<ActivityInfo, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(fit_activity_info_of(&fitparser::from_reader(&mut reader)?))
+}
+
+// Same as fit_activity_info, for messages already decoded.
+pub fn fit_activity_info_of(messages: &[fitparser::FitDataRecord]) -> ActivityInfo {
+    let mut info = ActivityInfo::default();
+    let (mut manufacturer, mut product) = (String::new(), String::new());
+    for message in messages {
+        match message.kind() {
+            fitparser::profile::field_types::MesgNum::FileId => {
+                for field in message.fields() {
+                    match field.name() {
+                        "manufacturer" => manufacturer = field.value().to_string(),
+                        "product" | "garmin_product" => product = field.value().to_string(),
+                        _ => {}
+                    }
+                }
+            }
+            fitparser::profile::field_types::MesgNum::Session => {
+                if let Some(field) = message.fields().iter().find(|f| f.name() == "sport") {
+                    info.sport = field.value().to_string();
+                }
+            }
+            _ => {}
+        }
+    }
+    info.device = format!("{} {}", manufacturer, product).trim().to_string();
+    info
+}
+
+// Numeric value of a FIT field, whatever its storage type.
+pub fn fit_value_f64(value: &fitparser::Value) -> Option<f64> {
+    match value {
+        fitparser::Value::Float32(v) => Some(*v as f64),
+        fitparser::Value::Float64(v) => Some(*v),
+        fitparser::Value::Byte(v) | fitparser::Value::UInt8(v) | fitparser::Value::UInt8z(v) => {
+            Some(*v as f64)
+        }
+        fitparser::Value::SInt8(v) => Some(*v as f64),
+        fitparser::Value::SInt16(v) => Some(*v as f64),
+        fitparser::Value::UInt16(v) | fitparser::Value::UInt16z(v) => Some(*v as f64),
+        fitparser::Value::SInt32(v) => Some(*v as f64),
+        fitparser::Value::UInt32(v) | fitparser::Value::UInt32z(v) => Some(*v as f64),
+        fitparser::Value::SInt64(v) => Some(*v as f64),
+        fitparser::Value::UInt64(v) | fitparser::Value::UInt64z(v) => Some(*v as f64),
+        _ => None,
+    }
+}
+
+// Read the session totals from the Session message of a FIT stream.
+pub fn fit_session_stats(
+    mut reader: &mut dyn Read,
+) -> Result<SessionStats, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(fit_session_stats_of(&fitparser::from_reader(&mut reader)?))
+}
+
+// Same as fit_session_stats, for messages already decoded.
+pub fn fit_session_stats_of(messages: &[fitparser::FitDataRecord]) -> SessionStats {
     let mut stats = SessionStats::default();
 
     for message in messages {
@@ -62,6 +179,9 @@ pub fn extract_session_data(
                             _ => 0.0,
                         };
                     }
+                    "total_timer_time" => {
+                        stats.moving_time = fit_value_f64(field.value()).unwrap_or_default();
+                    }
                     "enhanced_avg_speed" => {
                         stats.enhanced_speed = match field.value() {
                             fitparser::Value::Float32(v) => *v as f64,
@@ -82,10 +202,14 @@ pub fn extract_session_data(
                     _ => {}
                 }
             }
-            return Ok(stats);
+            // Files without a timer time never stopped it.
+            if stats.moving_time <= 0.0 {
+                stats.moving_time = stats.duration;
+            }
+            return stats;
         }
     }
-    Ok(stats)
+    stats
 }
 
 pub fn get_files_in_range(
@@ -103,24 +227,32 @@ pub fn get_files_in_range(
         .collect()
 }
 
-pub fn process_fit_directory(pathbuf: &PathBuf) -> Arc<DashMap<DateTime<Utc>, PathBuf>> {
+pub fn process_fit_directory(pathbuf: &Path) -> Arc<DashMap<DateTime<Utc>, PathBuf>> {
     let dir = pathbuf.to_str().unwrap();
     let map = Arc::new(DashMap::new());
+    // Bare and gzipped FIT/GPX/TCX files, plus zip/tar.gz archives (e.g. a Garmin Connect export).
     let paths: Vec<PathBuf> = WalkDir::new(dir)
         .into_iter()
         .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
         .filter(|e| {
-            e.path()
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| ext.eq_ignore_ascii_case("fit"))
-                .unwrap_or(false)
+            let name = e.file_name().to_string_lossy();
+            is_activity_name(&name) || is_archive(e.path())
         })
         .map(|e| e.into_path())
         .collect();
 
     paths.into_par_iter().for_each(|path| {
-        if let Ok(ts) = extract_timestamp_fast(&path) {
+        if is_archive(&path) {
+            // Archive members are indexed by virtual path, e.g. "export.zip!/123.fit".
+            let _ = for_each_entry(&path, |vpath, bytes| {
+                if let Ok(ts) = parse_filename_timestamp(&vpath)
+                    .or_else(|_| timestamp_from_bytes(&vpath, bytes))
+                {
+                    map.insert(ts, vpath);
+                }
+            });
+        } else if let Ok(ts) = extract_timestamp_fast(&path) {
             map.insert(ts, path);
         }
     });
@@ -134,14 +266,33 @@ fn extract_timestamp_fast(
     if let Ok(ts) = parse_filename_timestamp(path) {
         return Ok(ts);
     }
-    // 2. Second Pass: Fallback to the original FIT parser logic
-    let file = File::open(path)?;
-    let mut reader = file.take(2048);
-    match fitparser::from_reader(&mut reader) {
+    // 2. Second Pass: Fallback to the file contents
+    let source = source_for(path).ok_or("Unsupported activity format")?;
+    let mut reader = open_activity(path)?;
+    source.start_time(&mut reader)
+}
+
+// Same as the content pass of extract_timestamp_fast, for bytes already in memory.
+fn timestamp_from_bytes(
+    path: &Path,
+    bytes: &[u8],
+) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
+    let source = source_for(path).ok_or("Unsupported activity format")?;
+    source.start_time(&mut &bytes[..])
+}
+
+// Find the creation time in a FIT stream. Only the header is parsed
+// unless the first 2048 bytes are not enough.
+pub fn fit_start_time(
+    reader: &mut dyn Read,
+) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut bytes = Vec::new();
+    (&mut *reader).take(2048).read_to_end(&mut bytes)?;
+    match fitparser::from_reader(&mut &bytes[..]) {
         Ok(messages) => find_ts_in_vec(&messages),
         Err(_) => {
-            let mut full_file = File::open(path)?;
-            let messages = fitparser::from_reader(&mut full_file)?;
+            reader.read_to_end(&mut bytes)?;
+            let messages = fitparser::from_reader(&mut &bytes[..])?;
             find_ts_in_vec(&messages)
         }
     }
@@ -151,10 +302,10 @@ fn find_ts_in_vec(
     messages: &[fitparser::FitDataRecord],
 ) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
     for message in messages {
-        if let Some(field) = message.fields().iter().find(|f| f.name() == "time_created") {
-            if let fitparser::Value::Timestamp(ts) = field.value() {
-                return Ok((*ts).into());
-            }
+        if let Some(field) = message.fields().iter().find(|f| f.name() == "time_created")
+            && let fitparser::Value::Timestamp(ts) = field.value()
+        {
+            return Ok((*ts).into());
         }
     }
     Err("Timestamp not found".into())
@@ -167,6 +318,11 @@ pub fn parse_filename_timestamp(
         .file_stem()
         .and_then(|s| s.to_str())
         .ok_or("Invalid filename")?;
+    // "name.fit.gz" leaves "name.fit" as the stem.
+    let file_stem = Path::new(file_stem)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(file_stem);
     let format = "%Y-%m-%d-%H-%M-%S";
     let naive_dt = chrono::NaiveDateTime::parse_from_str(file_stem, format)?;
     Ok(Utc.from_local_datetime(&naive_dt).unwrap())
@@ -491,51 +647,37 @@ pub fn cvt_pace(speed: f32, units: &Units) -> f32 {
     match units {
         Units::US => {
             if speed < 1.00 {
-                return 26.8224; //avoid divide by zero
+                26.8224 //avoid divide by zero
             } else {
-                return 26.8224 / speed;
+                26.8224 / speed
             }
         }
         Units::Metric => {
             if speed < 1.00 {
-                return 16.666667; //avoid divide by zero
+                16.666667 //avoid divide by zero
             } else {
-                return 16.666667 / speed;
+                16.666667 / speed
             }
         }
-        Units::None => {
-            return speed;
-        }
+        Units::None => speed,
     }
 }
 
 // Convert distance meters to miles, km.
 pub fn cvt_distance(distance: f32, units: &Units) -> f32 {
     match units {
-        Units::US => {
-            return distance * 0.00062137119;
-        }
-        Units::Metric => {
-            return distance * 0.001;
-        }
-        Units::None => {
-            return distance;
-        }
+        Units::US => distance * 0.000_621_371_2,
+        Units::Metric => distance * 0.001,
+        Units::None => distance,
     }
 }
 
 // Convert altitude meters to feet, m.
 pub fn cvt_altitude(altitude: f32, units: &Units) -> f32 {
     match units {
-        Units::US => {
-            return altitude * 3.2808399;
-        }
-        Units::Metric => {
-            return altitude * 1.0;
-        }
-        Units::None => {
-            return altitude;
-        }
+        Units::US => altitude * 3.280_84,
+        Units::Metric => altitude * 1.0,
+        Units::None => altitude,
     }
 }
 // Create a wrapper struct for the data we actually need
@@ -545,6 +687,47 @@ pub struct PlottableData {
     pub stats: SessionStats,
 }
 
+// Extract the session totals of every file, and of the manual activities
+// indexed with them, in parallel.
+pub fn collect_all_stats(results: &[(DateTime<Utc>, PathBuf)]) -> Vec<PlottableData> {
+    results
+        .into_par_iter()
+        .map(|(ts, path)| PlottableData {
+            timestamp: *ts,
+            stats: extract_session_data(path).unwrap_or_default(),
+        })
+        .collect()
+}
+
+// Convert the session totals to the selected unit system.
+pub fn convert_all_stats(
+    raw_stats: &[PlottableData],
+    selected_units: &Units,
+) -> Vec<PlottableData> {
+    raw_stats
+        .into_par_iter()
+        .map(|plottable_data| PlottableData {
+            timestamp: plottable_data.timestamp,
+            stats: convert_session_data(&plottable_data.stats, selected_units).unwrap_or_default(),
+        })
+        .collect()
+}
+
+// Convert the above structure to plottable vectors
+pub fn get_metric_vec(
+    all_data: &[PlottableData],
+    value_extractor: fn(&SessionStats) -> f64,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let mut data: Vec<(DateTime<Utc>, f64)> = all_data
+        .iter()
+        .map(|item| (item.timestamp, value_extractor(&item.stats)))
+        .collect();
+
+    // Ensure chronological order for the LineSeries
+    data.sort_by_key(|(ts, _)| *ts);
+    data
+}
+
 pub fn convert_session_data(
     stats: &SessionStats,
     selected_units: &Units,
@@ -561,7 +744,8 @@ pub fn convert_session_data(
     converted_stats.descent = cvt_altitude(stats.descent as f32, selected_units) as u16;
 
     // Convert Duration (seconds to minutes)
-    converted_stats.duration = stats.duration / 60.0 as f64;
+    converted_stats.duration = stats.duration / 60.0_f64;
+    converted_stats.moving_time = stats.moving_time / 60.0_f64;
 
     converted_stats.enhanced_speed = cvt_pace(stats.enhanced_speed as f32, selected_units) as f64;
 
@@ -0,0 +1,310 @@
+// Module for elevation correction from a local digital elevation model
+// (DEM): SRTM .hgt tiles in a directory. GPS-only watches derive altitude
+// from the satellites and their ascent and descent are unreliable; looking
+// the track up in the DEM and smoothing the profile gives totals that
+// compare from one activity to the next.
+use crate::records::{Record, extract_records};
+use crate::source::{ascent_descent, haversine};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// Height of a void (no data) SRTM sample.
+const VOID: i16 = -32768;
+
+// Meters between the profile samples; the SRTM grid is 30 or 90 m, so
+// closer samples only repeat the interpolation.
+const SAMPLE_SPACING: f64 = 20.0;
+
+// Samples averaged on each side of a profile sample.
+const SMOOTHING_RADIUS: usize = 2;
+
+// Share of the positions the DEM must cover for the correction to be used.
+const MIN_COVERAGE: f64 = 0.5;
+
+// Where the ascent and descent of the charts and the summary come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ElevationSource {
+    // The totals recorded by the device.
+    #[default]
+    Device,
+    // Recomputed from the DEM, where it covers the track.
+    Dem,
+}
+
+impl ElevationSource {
+    // Parse a settings name; anything else is Device.
+    pub fn from_name(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "dem" => ElevationSource::Dem,
+            _ => ElevationSource::Device,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ElevationSource::Device => "device",
+            ElevationSource::Dem => "dem",
+        }
+    }
+}
+
+// One 1x1 degree tile: square rows of big-endian heights, north first.
+struct Tile {
+    size: usize,
+    heights: Vec<i16>,
+}
+
+impl Tile {
+    fn read(path: &Path) -> Option<Tile> {
+        let bytes = std::fs::read(path).ok()?;
+        let size = ((bytes.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != bytes.len() {
+            return None;
+        }
+        let heights = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        Some(Tile { size, heights })
+    }
+
+    fn height(&self, row: usize, col: usize) -> Option<f64> {
+        let h = *self.heights.get(row * self.size + col)?;
+        (h != VOID).then_some(h as f64)
+    }
+
+    // Bilinear interpolation at a position inside the tile, given from its
+    // north-west corner in fractions of the tile; voids are skipped.
+    fn interpolate(&self, down: f64, right: f64) -> Option<f64> {
+        let last = (self.size - 1) as f64;
+        let (y, x) = (down.clamp(0.0, 1.0) * last, right.clamp(0.0, 1.0) * last);
+        let (row, col) = (y.floor() as usize, x.floor() as usize);
+        let (row2, col2) = ((row + 1).min(self.size - 1), (col + 1).min(self.size - 1));
+        let (fy, fx) = (y - row as f64, x - col as f64);
+        let (mut sum, mut weight) = (0.0, 0.0);
+        for (r, c, w) in [
+            (row, col, (1.0 - fy) * (1.0 - fx)),
+            (row, col2, (1.0 - fy) * fx),
+            (row2, col, fy * (1.0 - fx)),
+            (row2, col2, fy * fx),
+        ] {
+            if let Some(h) = self.height(r, c) {
+                sum += h * w;
+                weight += w;
+            }
+        }
+        (weight > 0.0).then(|| sum / weight)
+    }
+}
+
+// SRTM name of the tile whose south-west corner is at a whole degree,
+// e.g. N37W123.hgt.
+fn tile_name(lat: i32, lon: i32) -> String {
+    format!(
+        "{}{:02}{}{:03}.hgt",
+        if lat >= 0 { 'N' } else { 'S' },
+        lat.abs(),
+        if lon >= 0 { 'E' } else { 'W' },
+        lon.abs()
+    )
+}
+
+// Tiles by the whole degrees of their south-west corner; None when the
+// directory has no tile there.
+type TileCache = Mutex<HashMap<(i32, i32), Option<Arc<Tile>>>>;
+
+// Ascent and descent in meters.
+pub type AscentDescent = (u16, u16);
+
+// The tiles of a directory, read when first needed and shared between
+// threads.
+pub struct Dem {
+    dir: PathBuf,
+    tiles: TileCache,
+}
+
+impl Dem {
+    pub fn new(dir: &Path) -> Self {
+        Dem {
+            dir: dir.to_path_buf(),
+            tiles: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn tile(&self, lat: i32, lon: i32) -> Option<Arc<Tile>> {
+        if let Some(tile) = self.tiles.lock().ok()?.get(&(lat, lon)) {
+            return tile.clone();
+        }
+        let name = tile_name(lat, lon);
+        let tile = [name.clone(), name.to_lowercase()]
+            .iter()
+            .find_map(|n| Tile::read(&self.dir.join(n)))
+            .map(Arc::new);
+        self.tiles.lock().ok()?.insert((lat, lon), tile.clone());
+        tile
+    }
+
+    // Height in meters at a position in degrees, None outside the tiles.
+    pub fn elevation(&self, lat: f64, lon: f64) -> Option<f64> {
+        let (south, west) = (lat.floor(), lon.floor());
+        let tile = self.tile(south as i32, west as i32)?;
+        tile.interpolate(south + 1.0 - lat, lon - west)
+    }
+}
+
+// DEM heights along a track, one every SAMPLE_SPACING meters, smoothed
+// with a moving average. None when the DEM covers too little of it.
+pub fn dem_profile(dem: &Dem, records: &[Record]) -> Option<Vec<f64>> {
+    let positions: Vec<(f64, f64)> = records
+        .iter()
+        .filter_map(|r| Some((r.lat?, r.lon?)))
+        .collect();
+    let mut samples = Vec::new();
+    let (mut looked_up, mut last): (usize, Option<(f64, f64)>) = (0, None);
+    for &(lat, lon) in &positions {
+        if last.is_some_and(|(a, b)| haversine(a, b, lat, lon) < SAMPLE_SPACING) {
+            continue;
+        }
+        last = Some((lat, lon));
+        looked_up += 1;
+        if let Some(height) = dem.elevation(lat, lon) {
+            samples.push(height);
+        }
+    }
+    if samples.len() < 2 || (samples.len() as f64) < MIN_COVERAGE * looked_up as f64 {
+        return None;
+    }
+    Some(
+        (0..samples.len())
+            .map(|i| {
+                let window = &samples[i.saturating_sub(SMOOTHING_RADIUS)
+                    ..(i + SMOOTHING_RADIUS + 1).min(samples.len())];
+                window.iter().sum::<f64>() / window.len() as f64
+            })
+            .collect(),
+    )
+}
+
+// Ascent and descent (meters) of an activity from the DEM.
+pub fn dem_ascent_descent(dem: &Dem, path: &Path) -> Option<AscentDescent> {
+    dem_ascent_descent_of(dem, &extract_records(path).ok()?)
+}
+
+// Ascent and descent (meters) from the DEM along records already read.
+pub fn dem_ascent_descent_of(dem: &Dem, records: &[Record]) -> Option<AscentDescent> {
+    let profile = dem_profile(dem, records)?;
+    let (ascent, descent) = ascent_descent(profile.into_iter());
+    let clamp = |m: f64| m.round().min(u16::MAX as f64) as u16;
+    Some((clamp(ascent), clamp(descent)))
+}
+
+// Read the DEM ascent and descent of every activity in parallel.
+pub fn collect_dem_elevation(
+    results: &[(DateTime<Utc>, PathBuf)],
+    dem: &Dem,
+) -> Vec<(DateTime<Utc>, Option<AscentDescent>)> {
+    results
+        .par_iter()
+        .map(|(timestamp, path)| (*timestamp, dem_ascent_descent(dem, path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temporary directory.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("curro-all-dem-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Write a size x size tile whose rows fall from north_height at the
+    // north edge to 0 at the south edge.
+    fn write_tile(dir: &Path, name: &str, size: usize, north_height: i16) {
+        let mut bytes = Vec::new();
+        for row in 0..size {
+            let h = north_height as f64 * (size - 1 - row) as f64 / (size - 1) as f64;
+            for _ in 0..size {
+                bytes.extend_from_slice(&(h as i16).to_be_bytes());
+            }
+        }
+        std::fs::write(dir.join(name), bytes).unwrap();
+    }
+
+    #[test]
+    fn tile_names_by_hemisphere() {
+        assert_eq!(tile_name(37, -123), "N37W123.hgt");
+        assert_eq!(tile_name(-34, -71), "S34W071.hgt");
+        assert_eq!(tile_name(-1, 6), "S01E006.hgt");
+        assert_eq!(tile_name(0, 0), "N00E000.hgt");
+    }
+
+    #[test]
+    fn southern_and_western_positions_find_their_tile() {
+        let dir = temp_dir("sw");
+        write_tile(&dir, "S34W071.hgt", 11, 1000);
+        let dem = Dem::new(&dir);
+        // Halfway down the tile, whose south-west corner is -34, -71.
+        let height = dem.elevation(-33.5, -70.5).unwrap();
+        assert!((height - 500.0).abs() < 1e-6);
+        assert!(dem.elevation(-32.5, -70.5).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn truncated_tiles_are_rejected() {
+        let dir = temp_dir("truncated");
+        write_tile(&dir, "N45E006.hgt", 11, 1000);
+        let path = dir.join("N45E006.hgt");
+        assert!(Tile::read(&path).is_some());
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        assert!(Tile::read(&path).is_none());
+        std::fs::write(&path, &bytes[..2]).unwrap();
+        assert!(Tile::read(&path).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn voids_are_skipped() {
+        let tile = Tile {
+            size: 2,
+            heights: vec![100, VOID, 100, 100],
+        };
+        assert_eq!(tile.interpolate(0.0, 0.5), Some(100.0));
+        let empty = Tile {
+            size: 2,
+            heights: vec![VOID; 4],
+        };
+        assert_eq!(empty.interpolate(0.5, 0.5), None);
+    }
+
+    #[test]
+    fn profile_needs_coverage() {
+        let dir = temp_dir("coverage");
+        write_tile(&dir, "N45E006.hgt", 11, 1000);
+        let dem = Dem::new(&dir);
+        let track = |lat: f64| Record {
+            lat: Some(lat),
+            lon: Some(6.5),
+            ..Default::default()
+        };
+        // About 1.1 km north inside the tile.
+        let inside: Vec<Record> = (0..100).map(|i| track(45.1 + i as f64 * 0.0001)).collect();
+        let profile = dem_profile(&dem, &inside).unwrap();
+        assert!(profile.windows(2).all(|w| w[1] >= w[0]));
+        // Mostly north of the tile.
+        let outside: Vec<Record> = (0..100)
+            .map(|i| track(45.999 + i as f64 * 0.0001))
+            .collect();
+        assert!(dem_profile(&dem, &outside).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,302 @@
+// Module reading everything the views show about an activity in one pass
+// over its file, and keeping it while the file and the settings it depends
+// on are unchanged, so that switching ranges and unit systems only reads
+// the files not seen yet.
+use crate::archive::file_meta;
+use crate::data::{PlottableData, SessionStats, read_activity};
+use crate::dem::{AscentDescent, Dem, dem_ascent_descent_of};
+use crate::fitness::{ActivityFitness, fitness_of};
+use crate::laps::Lap;
+use crate::map::{Track, track_of};
+use crate::source::{ActivityInfo, BoxError};
+use crate::zones::{ActivityZones, ZoneSettings, zones_of};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// What the views show about one activity.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityDigest {
+    pub stats: SessionStats,
+    pub info: ActivityInfo,
+    pub laps: Vec<Lap>,
+    pub zones: ActivityZones,
+    pub track: Track,
+    // Ascent and descent from the DEM, when one is set and covers the track.
+    pub dem: Option<AscentDescent>,
+    pub fitness: ActivityFitness,
+}
+
+// The settings a digest depends on besides the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestSettings {
+    pub zones: ZoneSettings,
+    pub dem_directory: Option<PathBuf>,
+}
+
+// Read the digest of one activity.
+pub fn activity_digest(
+    path: &Path,
+    settings: &ZoneSettings,
+    dem: Option<&Dem>,
+) -> Result<ActivityDigest, BoxError> {
+    let activity = read_activity(path)?;
+    let records = &activity.records;
+    Ok(ActivityDigest {
+        zones: zones_of(records, activity.device_hr_zones.as_deref(), settings),
+        track: track_of(records),
+        dem: dem.and_then(|dem| dem_ascent_descent_of(dem, records)),
+        fitness: fitness_of(&activity.info.sport, records, activity.vo2max),
+        stats: activity.stats,
+        info: activity.info,
+        laps: activity.laps,
+    })
+}
+
+// How much the cache holds before the least recently shown activities are
+// dropped, counted in GPS positions (16 bytes each) with every activity
+// counting ENTRY_WEIGHT more for its totals, laps and zones: about 40 MB.
+const CACHE_BUDGET: usize = 2_500_000;
+const ENTRY_WEIGHT: usize = 64;
+
+struct CacheEntry {
+    // Size and modification time of the file when it was read.
+    meta: (i64, i64),
+    settings: DigestSettings,
+    digest: Arc<ActivityDigest>,
+    // The collect call that last returned it.
+    used: u64,
+}
+
+impl CacheEntry {
+    fn weight(&self) -> usize {
+        self.digest.track.len() + ENTRY_WEIGHT
+    }
+}
+
+#[derive(Default)]
+struct CacheState {
+    // Number of collect calls so far.
+    clock: u64,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+// Digests by path, shared with the worker threads reading them. Manual
+// activities are cheap to read and not kept, nor are files that could not
+// be read, so they are tried again.
+pub struct DigestCache {
+    state: Mutex<CacheState>,
+    budget: usize,
+}
+
+impl Default for DigestCache {
+    fn default() -> Self {
+        Self::with_budget(CACHE_BUDGET)
+    }
+}
+
+impl DigestCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A cache holding about budget GPS positions; see CACHE_BUDGET.
+    pub fn with_budget(budget: usize) -> Self {
+        DigestCache {
+            state: Mutex::new(CacheState::default()),
+            budget,
+        }
+    }
+
+    fn cached(
+        &self,
+        path: &Path,
+        meta: (i64, i64),
+        settings: &DigestSettings,
+        clock: u64,
+    ) -> Option<Arc<ActivityDigest>> {
+        let mut state = self.state.lock().ok()?;
+        let entry = state.entries.get_mut(path)?;
+        if entry.meta != meta || entry.settings != *settings {
+            return None;
+        }
+        entry.used = clock;
+        Some(entry.digest.clone())
+    }
+
+    // Digest every activity in parallel, reading only the files that are
+    // new, changed or read with other settings. In date order; activities
+    // that cannot be read are left out.
+    pub fn collect(
+        &self,
+        results: &[(DateTime<Utc>, PathBuf)],
+        settings: &DigestSettings,
+    ) -> Vec<(DateTime<Utc>, Arc<ActivityDigest>)> {
+        let clock = match self.state.lock() {
+            Ok(mut state) => {
+                state.clock += 1;
+                state.clock
+            }
+            Err(_) => 0,
+        };
+        let dem = settings.dem_directory.as_deref().map(Dem::new);
+        let mut digests: Vec<(DateTime<Utc>, Arc<ActivityDigest>)> = results
+            .par_iter()
+            .filter_map(|(timestamp, path)| {
+                let meta = file_meta(path);
+                if let Some(digest) = meta.and_then(|meta| self.cached(path, meta, settings, clock))
+                {
+                    return Some((*timestamp, digest));
+                }
+                let digest = Arc::new(activity_digest(path, &settings.zones, dem.as_ref()).ok()?);
+                if let (Some(meta), Ok(mut state)) = (meta, self.state.lock()) {
+                    state.entries.insert(
+                        path.clone(),
+                        CacheEntry {
+                            meta,
+                            settings: settings.clone(),
+                            digest: digest.clone(),
+                            used: clock,
+                        },
+                    );
+                }
+                Some((*timestamp, digest))
+            })
+            .collect();
+        digests.sort_by_key(|(timestamp, _)| *timestamp);
+        self.evict(clock);
+        digests
+    }
+
+    // Drop the least recently used digests over the budget. Those of the
+    // current call are kept whatever their size.
+    fn evict(&self, clock: u64) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let mut total: usize = state.entries.values().map(CacheEntry::weight).sum();
+        if total <= self.budget {
+            return;
+        }
+        let mut older: Vec<(u64, PathBuf, usize)> = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.used < clock)
+            .map(|(path, entry)| (entry.used, path.clone(), entry.weight()))
+            .collect();
+        older.sort_by_key(|(used, _, _)| *used);
+        for (_, path, weight) in older {
+            if total <= self.budget {
+                break;
+            }
+            state.entries.remove(&path);
+            total -= weight;
+        }
+    }
+}
+
+// The session totals of digests, for the charts and the summary.
+pub fn digest_stats(digests: &[(DateTime<Utc>, Arc<ActivityDigest>)]) -> Vec<PlottableData> {
+    digests
+        .iter()
+        .map(|(timestamp, digest)| PlottableData {
+            timestamp: *timestamp,
+            stats: digest.stats,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zones::HrZoneSettings;
+    use chrono::TimeZone;
+
+    const GPX: &str = r#"<gpx><trk><trkseg>
+<trkpt lat="45.0" lon="7.0"><time>2024-05-01T10:00:00Z</time></trkpt>
+<trkpt lat="45.001" lon="7.0"><time>2024-05-01T10:00:30Z</time></trkpt>
+<trkpt lat="45.002" lon="7.0"><time>2024-05-01T10:01:00Z</time></trkpt>
+</trkseg></trk></gpx>"#;
+
+    fn settings(max_hr: f64) -> DigestSettings {
+        DigestSettings {
+            zones: ZoneSettings {
+                hr: HrZoneSettings {
+                    max_hr,
+                    threshold_hr: None,
+                    prefer_device: false,
+                },
+                pace: None,
+                ftp: None,
+            },
+            dem_directory: None,
+        }
+    }
+
+    #[test]
+    fn digests_are_read_once_per_file_and_settings() {
+        let dir = std::env::temp_dir().join(format!("curro-all-digest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("run.gpx");
+        std::fs::write(&path, GPX).unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        let data = vec![(start, path)];
+
+        let cache = DigestCache::new();
+        let first = cache.collect(&data, &settings(190.0));
+        assert_eq!(first[0].1.track.len(), 3);
+        assert!(first[0].1.stats.distance > 200.0);
+        let again = cache.collect(&data, &settings(190.0));
+        assert!(Arc::ptr_eq(&first[0].1, &again[0].1));
+        let other = cache.collect(&data, &settings(180.0));
+        assert!(!Arc::ptr_eq(&first[0].1, &other[0].1));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unreadable_files_are_left_out_and_tried_again() {
+        let dir = std::env::temp_dir().join(format!("curro-all-unreadable-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("run.gpx");
+        std::fs::write(&path, "<gpx><trk>").unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        let data = vec![(start, path.clone())];
+
+        let cache = DigestCache::new();
+        assert!(cache.collect(&data, &settings(190.0)).is_empty());
+        std::fs::write(&path, GPX).unwrap();
+        let digests = cache.collect(&data, &settings(190.0));
+        assert_eq!(digests[0].1.track.len(), 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn least_recently_used_digests_are_dropped_over_the_budget() {
+        let dir = std::env::temp_dir().join(format!("curro-all-evict-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 10, 0, 0).unwrap();
+        let data: Vec<_> = (0..3)
+            .map(|i| {
+                let path = dir.join(format!("run{i}.gpx"));
+                std::fs::write(&path, GPX).unwrap();
+                (start, path)
+            })
+            .collect();
+
+        // Room for two activities of three positions each.
+        let cache = DigestCache::with_budget(2 * (3 + ENTRY_WEIGHT));
+        let first = cache.collect(&data[0..1], &settings(190.0));
+        let second = cache.collect(&data[1..2], &settings(190.0));
+        cache.collect(&data[2..3], &settings(190.0));
+        let again = cache.collect(&data[1..2], &settings(190.0));
+        assert!(Arc::ptr_eq(&second[0].1, &again[0].1));
+        let reread = cache.collect(&data[0..1], &settings(190.0));
+        assert!(!Arc::ptr_eq(&first[0].1, &reread[0].1));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,189 @@
+// Module for the activity table filter. A filter is a list of terms that
+// must all match, separated by commas or "and": comparisons of a metric
+// with a number, such as "distance > 10" or "pace <= 5:30", and anything
+// else as text found in the activity date, title, tags or notes, such as
+// "2026-03" or "race".
+use crate::data::SessionStats;
+use crate::i18n::tr;
+use crate::metrics::{METRICS, Metric};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn holds(&self, a: f64, b: f64) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Equal => (a - b).abs() < 1e-9,
+            Comparison::NotEqual => (a - b).abs() >= 1e-9,
+        }
+    }
+}
+
+// Longer operators first, so "<=" is not read as "<".
+const OPERATORS: [(&str, Comparison); 7] = [
+    ("<=", Comparison::LessOrEqual),
+    (">=", Comparison::GreaterOrEqual),
+    ("!=", Comparison::NotEqual),
+    ("<", Comparison::Less),
+    (">", Comparison::Greater),
+    ("==", Comparison::Equal),
+    ("=", Comparison::Equal),
+];
+
+enum Term {
+    Compare {
+        metric: &'static Metric,
+        comparison: Comparison,
+        value: f64,
+    },
+    Text(String),
+}
+
+#[derive(Default)]
+pub struct ActivityFilter {
+    terms: Vec<Term>,
+}
+
+// A metric by its settings name or its translated chart caption.
+fn metric_named(name: &str) -> Option<&'static Metric> {
+    let name = name.trim().to_lowercase();
+    METRICS
+        .iter()
+        .find(|m| m.name == name || tr(m.caption, None).to_lowercase() == name)
+}
+
+// A number, or a pace or duration written as "m:ss".
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    match text.split_once(':') {
+        Some((mins, secs)) => Some(mins.parse::<f64>().ok()? + secs.parse::<f64>().ok()? / 60.0),
+        None => text.parse().ok(),
+    }
+}
+
+impl ActivityFilter {
+    // Parse a filter, or return the term that could not be understood.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut terms = Vec::new();
+        let normalized = text.replace(" and ", ",").replace(" AND ", ",");
+        for term in normalized
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+        {
+            let operator = OPERATORS
+                .iter()
+                .filter_map(|(op, comparison)| term.find(op).map(|i| (i, *op, *comparison)))
+                .min_by_key(|(i, op, _)| (*i, usize::MAX - op.len()));
+            match operator {
+                Some((i, op, comparison)) => {
+                    let metric = metric_named(&term[..i]).ok_or_else(|| term.to_string())?;
+                    let value =
+                        parse_number(&term[i + op.len()..]).ok_or_else(|| term.to_string())?;
+                    terms.push(Term::Compare {
+                        metric,
+                        comparison,
+                        value,
+                    });
+                }
+                None => terms.push(Term::Text(term.to_lowercase())),
+            }
+        }
+        Ok(ActivityFilter { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    // Whether an activity passes every term. Stats are compared in the
+    // units they are given in, i.e. the ones shown; text terms are also
+    // looked for in the lower case text given (title, tags and notes).
+    pub fn matches(&self, timestamp: &DateTime<Utc>, stats: &SessionStats, text: &str) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Compare {
+                metric,
+                comparison,
+                value,
+            } => comparison.holds((metric.value)(stats), *value),
+            Term::Text(term) => {
+                text.contains(term.as_str())
+                    || timestamp
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                        .contains(term.as_str())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn matches(filter: &str, distance: f64) -> bool {
+        let stats = SessionStats {
+            distance,
+            ..Default::default()
+        };
+        let timestamp = Utc.with_ymd_and_hms(2026, 3, 1, 8, 0, 0).unwrap();
+        ActivityFilter::parse(filter)
+            .unwrap()
+            .matches(&timestamp, &stats, "parkrun race")
+    }
+
+    #[test]
+    fn longer_operators_win() {
+        assert!(matches("distance <= 10", 10.0));
+        assert!(!matches("distance < 10", 10.0));
+        assert!(matches("distance >= 10", 10.0));
+        assert!(!matches("distance > 10", 10.0));
+        assert!(matches("distance != 5", 10.0));
+        assert!(matches("distance == 10", 10.0));
+        assert!(matches("distance = 10", 10.0));
+    }
+
+    #[test]
+    fn terms_must_all_match() {
+        assert!(matches("distance > 5 and race", 10.0));
+        assert!(matches("distance > 5, 2026-03", 10.0));
+        assert!(!matches("distance > 5, 2026-04", 10.0));
+        assert!(!matches("distance > 15 AND race", 10.0));
+    }
+
+    #[test]
+    fn paces_are_read_as_minutes() {
+        let stats = SessionStats {
+            enhanced_speed: 5.25,
+            ..Default::default()
+        };
+        let filter = ActivityFilter::parse("pace < 5:30").unwrap();
+        assert!(filter.matches(&Utc::now(), &stats, ""));
+    }
+
+    #[test]
+    fn parse_errors_name_the_term() {
+        assert_eq!(
+            ActivityFilter::parse("distance > 5, speediness > 3").err(),
+            Some("speediness > 3".to_string())
+        );
+        assert_eq!(
+            ActivityFilter::parse("distance > ten").err(),
+            Some("distance > ten".to_string())
+        );
+        assert!(ActivityFilter::parse(" , ").unwrap().is_empty());
+    }
+}
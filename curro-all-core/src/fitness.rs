@@ -0,0 +1,195 @@
+// Module estimating running fitness from the activity history: the best
+// efforts of each run, the VO2max they imply (Daniels' VDOT) or the one
+// the device recorded, and race time predictions (Riegel's formula).
+use crate::data::{fit_value_f64, read_activity};
+use crate::records::Record;
+use crate::source::BoxError;
+use chrono::{DateTime, Duration, Utc};
+use rayon::prelude::*;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// Distances (meters) of the best efforts searched in each run. Shorter
+// efforts are outside the range the VDOT formula was fitted on.
+const EFFORT_DISTANCES: [f64; 5] = [1609.344, 5000.0, 10000.0, 21097.5, 42195.0];
+
+// Races predicted: translation key of the name and distance in meters.
+pub const RACES: [(&str, f64); 4] = [
+    ("RACE_5K", 5000.0),
+    ("RACE_10K", 10000.0),
+    ("RACE_HALF_MARATHON", 21097.5),
+    ("RACE_MARATHON", 42195.0),
+];
+
+// Fatigue exponent of Riegel's formula.
+const RIEGEL_EXPONENT: f64 = 1.06;
+
+// Efforts older than this no longer reflect current fitness.
+pub const RECENT_DAYS: i64 = 42;
+
+// Milliliters of oxygen per kilogram per minute in one MET.
+const ML_PER_MET: f64 = 3.5;
+
+// A distance covered in a time, e.g. the fastest 5 km of a run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Effort {
+    pub distance: f64,
+    pub seconds: f64,
+}
+
+impl Effort {
+    // Daniels and Gilbert's VDOT: the oxygen cost of the effort's speed
+    // over the share of VO2max sustainable for its duration.
+    pub fn vdot(&self) -> f64 {
+        let minutes = self.seconds / 60.0;
+        let velocity = self.distance / minutes;
+        let cost = -4.60 + 0.182258 * velocity + 0.000104 * velocity * velocity;
+        let share = 0.8
+            + 0.1894393 * (-0.012778 * minutes).exp()
+            + 0.2989558 * (-0.1932605 * minutes).exp();
+        cost / share
+    }
+
+    // Riegel's prediction of the time (seconds) over another distance.
+    pub fn predict(&self, distance: f64) -> f64 {
+        self.seconds * (distance / self.distance).powf(RIEGEL_EXPONENT)
+    }
+}
+
+// Fastest time over each of EFFORT_DISTANCES the records cover.
+pub fn best_efforts(records: &[Record]) -> Vec<Effort> {
+    let samples: Vec<(f64, f64)> = records
+        .iter()
+        .filter(|r| r.time.is_some())
+        .filter_map(|r| Some((r.distance?, r.elapsed)))
+        .collect();
+    let total = samples.last().map_or(0.0, |s| s.0);
+    EFFORT_DISTANCES
+        .iter()
+        .filter(|d| **d <= total)
+        .filter_map(|&distance| {
+            let mut best: Option<f64> = None;
+            let mut start = 0;
+            for end in 0..samples.len() {
+                // Shortest window ending here still covering the distance.
+                while start + 1 < end && samples[end].0 - samples[start + 1].0 >= distance {
+                    start += 1;
+                }
+                let covered = samples[end].0 - samples[start].0;
+                let seconds = samples[end].1 - samples[start].1;
+                if covered >= distance && seconds > 0.0 {
+                    let scaled = seconds * distance / covered;
+                    best = Some(best.map_or(scaled, |b| b.min(scaled)));
+                }
+            }
+            best.map(|seconds| Effort { distance, seconds })
+        })
+        .collect()
+}
+
+// VO2max (ml/kg/min) recorded in a FIT stream, as a vo2max field or as
+// maximum METs.
+pub fn fit_vo2max(mut reader: &mut dyn Read) -> Result<Option<f64>, BoxError> {
+    Ok(fit_vo2max_of(&fitparser::from_reader(&mut reader)?))
+}
+
+// Same as fit_vo2max, for messages already decoded.
+pub fn fit_vo2max_of(messages: &[fitparser::FitDataRecord]) -> Option<f64> {
+    let mut vo2max = None;
+    for message in messages {
+        for field in message.fields() {
+            let value = fit_value_f64(field.value()).filter(|v| *v > 0.0);
+            match field.name() {
+                "vo2max" | "vo2_max" => vo2max = value.or(vo2max),
+                "max_met" => vo2max = value.map(|v| v * ML_PER_MET).or(vo2max),
+                _ => {}
+            }
+        }
+    }
+    vo2max
+}
+
+// What one activity says about fitness.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActivityFitness {
+    // The best effort with the highest VDOT, for runs.
+    pub effort: Option<Effort>,
+    pub device_vo2max: Option<f64>,
+}
+
+impl ActivityFitness {
+    pub fn vdot(&self) -> Option<f64> {
+        self.effort.map(|e| e.vdot())
+    }
+}
+
+fn activity_fitness(path: &Path) -> ActivityFitness {
+    let activity = read_activity(path).unwrap_or_default();
+    fitness_of(&activity.info.sport, &activity.records, activity.vo2max)
+}
+
+// Fitness from the records of an activity and the VO2max its device
+// recorded. Efforts only count for runs; files without a sport (GPX) are
+// assumed to be runs.
+pub fn fitness_of(sport: &str, records: &[Record], device_vo2max: Option<f64>) -> ActivityFitness {
+    let effort = (sport.is_empty() || sport.contains("run"))
+        .then(|| {
+            best_efforts(records)
+                .into_iter()
+                .max_by(|a, b| a.vdot().total_cmp(&b.vdot()))
+        })
+        .flatten();
+    ActivityFitness {
+        effort,
+        device_vo2max,
+    }
+}
+
+// Read the fitness of every activity in parallel, oldest first.
+pub fn collect_fitness(
+    results: &[(DateTime<Utc>, PathBuf)],
+) -> Vec<(DateTime<Utc>, ActivityFitness)> {
+    let mut fitness: Vec<(DateTime<Utc>, ActivityFitness)> = results
+        .par_iter()
+        .map(|(timestamp, path)| (*timestamp, activity_fitness(path)))
+        .collect();
+    fitness.sort_by_key(|(timestamp, _)| *timestamp);
+    fitness
+}
+
+// Estimated fitness at the time of an activity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FitnessPoint {
+    pub timestamp: DateTime<Utc>,
+    // The device's latest VO2max when it recorded one, else the VDOT.
+    pub vo2max: f64,
+    pub from_device: bool,
+    // Predicted time (seconds) of each of RACES.
+    pub predictions: [f64; RACES.len()],
+}
+
+// Fitness after each activity, from the best effort (by VDOT) of the last
+// RECENT_DAYS days, so a hard session lifts the estimate for a while and
+// it falls back once no recent effort supports it.
+pub fn fitness_timeline(fitness: &[(DateTime<Utc>, ActivityFitness)]) -> Vec<FitnessPoint> {
+    let recent = Duration::days(RECENT_DAYS);
+    fitness
+        .iter()
+        .filter_map(|(timestamp, _)| {
+            let window = fitness
+                .iter()
+                .filter(|(t, _)| t <= timestamp && *timestamp - *t < recent);
+            let best = window
+                .clone()
+                .filter_map(|(_, f)| f.effort)
+                .max_by(|a, b| a.vdot().total_cmp(&b.vdot()))?;
+            let device = window.filter_map(|(_, f)| f.device_vo2max).next_back();
+            Some(FitnessPoint {
+                timestamp: *timestamp,
+                vo2max: device.unwrap_or(best.vdot()),
+                from_device: device.is_some(),
+                predictions: RACES.map(|(_, distance)| best.predict(distance)),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,157 @@
+// Module for gear (e.g. shoe) mileage: which activities used each item,
+// assigned by hand or by rules on sport, device and dates, and the distance
+// and time each item has accumulated towards its retirement.
+use crate::data::{SessionStats, read_activity};
+use crate::source::ActivityInfo;
+use chrono::{DateTime, NaiveDate, Utc};
+use rayon::prelude::*;
+use std::path::PathBuf;
+
+// Share of the retirement distance or time from which an item is flagged.
+pub const GEAR_WARNING_SHARE: f64 = 0.9;
+
+// Activities matching every field set are assigned to the item.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GearRule {
+    // Sport, e.g. "running" (case is ignored).
+    pub sport: Option<String>,
+    // Part of the device name, e.g. "fr945" (case is ignored).
+    pub device: Option<String>,
+    // First and last day, included.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl GearRule {
+    pub fn matches(&self, activity: &GearActivity) -> bool {
+        let day = activity.timestamp.date_naive();
+        self.sport
+            .as_ref()
+            .is_none_or(|s| activity.info.sport.eq_ignore_ascii_case(s))
+            && self.device.as_ref().is_none_or(|d| {
+                activity
+                    .info
+                    .device
+                    .to_lowercase()
+                    .contains(&d.to_lowercase())
+            })
+            && self.from.is_none_or(|from| day >= from)
+            && self.to.is_none_or(|to| day <= to)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gear {
+    pub name: String,
+    pub rules: Vec<GearRule>,
+    // Start times of activities assigned by hand, to the minute. An
+    // activity assigned by hand only counts for the items it is assigned to.
+    pub activities: Vec<DateTime<Utc>>,
+    // Meters already covered before the first activity recorded.
+    pub initial_distance: f64,
+    // Meters and seconds after which the item is worn out.
+    pub retire_distance: Option<f64>,
+    pub retire_time: Option<f64>,
+}
+
+fn same_minute(a: &DateTime<Utc>, b: &DateTime<Utc>) -> bool {
+    a.timestamp().div_euclid(60) == b.timestamp().div_euclid(60)
+}
+
+impl Gear {
+    fn assigned(&self, activity: &GearActivity) -> bool {
+        self.activities
+            .iter()
+            .any(|t| same_minute(t, &activity.timestamp))
+    }
+}
+
+// What gear assignment needs to know about an activity. Stats are raw
+// (meters and seconds).
+#[derive(Debug, Clone, Default)]
+pub struct GearActivity {
+    pub timestamp: DateTime<Utc>,
+    pub info: ActivityInfo,
+    pub stats: SessionStats,
+}
+
+// Read the sport, device and totals of every activity in parallel.
+pub fn collect_gear_activities(results: &[(DateTime<Utc>, PathBuf)]) -> Vec<GearActivity> {
+    results
+        .par_iter()
+        .map(|(timestamp, path)| {
+            let activity = read_activity(path).unwrap_or_default();
+            GearActivity {
+                timestamp: *timestamp,
+                info: activity.info,
+                stats: activity.stats,
+            }
+        })
+        .collect()
+}
+
+// The use of one item.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GearUsage {
+    pub name: String,
+    pub count: usize,
+    // Meters, the initial distance included, and seconds moving.
+    pub distance: f64,
+    pub time: f64,
+    // Distance after each activity, oldest first.
+    pub history: Vec<(DateTime<Utc>, f64)>,
+    pub retire_distance: Option<f64>,
+    pub retire_time: Option<f64>,
+}
+
+impl GearUsage {
+    // Share of the retirement distance or time used, whichever is higher.
+    pub fn share_used(&self) -> Option<f64> {
+        let distance = self.retire_distance.map(|r| self.distance / r);
+        let time = self.retire_time.map(|r| self.time / r);
+        match (distance, time) {
+            (Some(d), Some(t)) => Some(d.max(t)),
+            (d, t) => d.or(t),
+        }
+    }
+
+    pub fn needs_attention(&self) -> bool {
+        self.share_used().is_some_and(|s| s >= GEAR_WARNING_SHARE)
+    }
+
+    pub fn is_worn_out(&self) -> bool {
+        self.share_used().is_some_and(|s| s >= 1.0)
+    }
+}
+
+// Add up the activities of each item, in the order the items are given.
+pub fn gear_usage(gear: &[Gear], activities: &[GearActivity]) -> Vec<GearUsage> {
+    let mut activities: Vec<&GearActivity> = activities.iter().collect();
+    activities.sort_by_key(|a| a.timestamp);
+    gear.iter()
+        .map(|item| {
+            let mut usage = GearUsage {
+                name: item.name.clone(),
+                distance: item.initial_distance,
+                retire_distance: item.retire_distance,
+                retire_time: item.retire_time,
+                ..Default::default()
+            };
+            for activity in &activities {
+                let by_hand = gear.iter().any(|g| g.assigned(activity));
+                let used = if by_hand {
+                    item.assigned(activity)
+                } else {
+                    item.rules.iter().any(|r| r.matches(activity))
+                };
+                if used {
+                    usage.count += 1;
+                    usage.distance += activity.stats.distance;
+                    usage.time += activity.stats.moving_time;
+                    usage.history.push((activity.timestamp, usage.distance));
+                }
+            }
+            usage
+        })
+        .collect()
+}
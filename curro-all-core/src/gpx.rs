@@ -0,0 +1,117 @@
+// Module for reading activities stored in the GPS Exchange Format (GPX).
+use crate::data::SessionStats;
+use crate::records::{Record, records_from_track};
+use crate::source::{
+    ActivityInfo, ActivitySource, BoxError, ParsedActivity, TrackPoint, parse_xml_time,
+    stats_from_track,
+};
+use chrono::{DateTime, Utc};
+use std::io::Read;
+
+pub struct GpxSource;
+
+// Parse the track points (trkpt) of all tracks in a GPX document.
+pub fn parse_gpx_track(reader: &mut dyn Read) -> Result<Vec<TrackPoint>, BoxError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let doc = roxmltree::Document::parse(&text)?;
+    Ok(gpx_track_of(&doc))
+}
+
+// The track points of a parsed GPX document.
+fn gpx_track_of(doc: &roxmltree::Document) -> Vec<TrackPoint> {
+    doc.descendants()
+        .filter(|n| n.has_tag_name("trkpt"))
+        .map(|n| {
+            let child_text = |name: &str| {
+                n.children()
+                    .find(|c| c.has_tag_name(name))
+                    .and_then(|c| c.text())
+            };
+            let extension = |name: &str| {
+                n.descendants()
+                    .find(|c| c.has_tag_name(name))
+                    .and_then(|c| c.text())
+                    .and_then(|v| v.trim().parse().ok())
+            };
+            TrackPoint {
+                time: child_text("time").and_then(parse_xml_time),
+                lat: n.attribute("lat").and_then(|v| v.parse().ok()),
+                lon: n.attribute("lon").and_then(|v| v.parse().ok()),
+                elevation: child_text("ele").and_then(|v| v.trim().parse().ok()),
+                distance: None,
+                // Garmin TrackPointExtension values, e.g. <gpxtpx:hr>.
+                heart_rate: extension("hr"),
+                cadence: extension("cad"),
+                power: extension("power"),
+            }
+        })
+        .collect()
+}
+
+// The sport and writing device of a parsed GPX document.
+fn gpx_activity_info_of(doc: &roxmltree::Document) -> ActivityInfo {
+    // The track type is free text; the creator names the writing device or program.
+    let sport = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("trk"))
+        .find_map(|n| n.children().find(|c| c.has_tag_name("type")))
+        .and_then(|n| n.text())
+        .unwrap_or_default();
+    ActivityInfo {
+        sport: sport.trim().to_lowercase(),
+        device: doc
+            .root_element()
+            .attribute("creator")
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+impl ActivitySource for GpxSource {
+    fn start_time(&self, reader: &mut dyn Read) -> Result<DateTime<Utc>, BoxError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        // Prefer the first track point; fall back to the metadata time.
+        doc.descendants()
+            .filter(|n| n.has_tag_name("trkpt"))
+            .chain(doc.descendants().filter(|n| n.has_tag_name("metadata")))
+            .find_map(|n| {
+                n.children()
+                    .find(|c| c.has_tag_name("time"))
+                    .and_then(|c| c.text())
+                    .and_then(parse_xml_time)
+            })
+            .ok_or_else(|| "Timestamp not found".into())
+    }
+
+    fn session_stats(&self, reader: &mut dyn Read) -> Result<SessionStats, BoxError> {
+        // GPX has no calorie information.
+        Ok(stats_from_track(&parse_gpx_track(reader)?))
+    }
+
+    fn records(&self, reader: &mut dyn Read) -> Result<Vec<Record>, BoxError> {
+        Ok(records_from_track(&parse_gpx_track(reader)?))
+    }
+
+    fn activity_info(&self, reader: &mut dyn Read) -> Result<ActivityInfo, BoxError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        Ok(gpx_activity_info_of(&doc))
+    }
+
+    fn parse(&self, reader: &mut dyn Read) -> Result<ParsedActivity, BoxError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        let points = gpx_track_of(&doc);
+        Ok(ParsedActivity {
+            stats: stats_from_track(&points),
+            info: gpx_activity_info_of(&doc),
+            records: records_from_track(&points),
+            ..Default::default()
+        })
+    }
+}
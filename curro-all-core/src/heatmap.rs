@@ -0,0 +1,121 @@
+// Module for route heatmaps: the GPS points of many activities rasterized
+// into density bins and colored with a ramp.
+use crate::map::{Track, Viewport};
+use rayon::prelude::*;
+
+// Segments longer than this (pixels) are recording gaps and are not filled in.
+const MAX_SEGMENT_PIXELS: f64 = 32.0;
+
+// Color ramp from sparse to dense: (position, red, green, blue, alpha).
+const HEAT_RAMP: [(f64, f64, f64, f64, f64); 5] = [
+    (0.0, 40.0, 40.0, 200.0, 110.0),
+    (0.25, 0.0, 170.0, 230.0, 170.0),
+    (0.5, 60.0, 210.0, 60.0, 210.0),
+    (0.75, 250.0, 220.0, 0.0, 235.0),
+    (1.0, 230.0, 30.0, 20.0, 255.0),
+];
+
+// Number of activities passing through each pixel of a view.
+pub struct Heatmap {
+    pub width: usize,
+    pub height: usize,
+    pub bins: Vec<u32>,
+}
+
+impl Heatmap {
+    // Rasterize the tracks in parallel. Each activity counts once per pixel,
+    // and the gaps between consecutive points are filled in so sparse
+    // recordings still draw continuous lines.
+    pub fn from_tracks<'a>(
+        tracks: impl IntoParallelIterator<Item = &'a Track>,
+        viewport: &Viewport,
+    ) -> Heatmap {
+        let (width, height) = (viewport.width as usize, viewport.height as usize);
+        let bins = tracks
+            .into_par_iter()
+            .fold(
+                || vec![0u32; width * height],
+                |mut bins, track| {
+                    let mut visited = vec![false; width * height];
+                    let mut mark = |x: f64, y: f64| {
+                        if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+                            let i = y as usize * width + x as usize;
+                            if !visited[i] {
+                                visited[i] = true;
+                                bins[i] += 1;
+                            }
+                        }
+                    };
+                    let pixels: Vec<(f64, f64)> = track
+                        .iter()
+                        .map(|&(lat, lon)| viewport.to_pixel(lat, lon))
+                        .collect();
+                    if let Some(&(x, y)) = pixels.first() {
+                        mark(x, y);
+                    }
+                    for w in pixels.windows(2) {
+                        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+                        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil();
+                        if steps > MAX_SEGMENT_PIXELS {
+                            mark(x1, y1);
+                            continue;
+                        }
+                        let steps = steps.max(1.0);
+                        for s in 1..=steps as usize {
+                            let t = s as f64 / steps;
+                            mark(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+                        }
+                    }
+                    bins
+                },
+            )
+            .reduce(
+                || vec![0u32; width * height],
+                |mut a, b| {
+                    a.iter_mut().zip(b).for_each(|(x, y)| *x += y);
+                    a
+                },
+            );
+        Heatmap {
+            width,
+            height,
+            bins,
+        }
+    }
+
+    pub fn max(&self) -> u32 {
+        self.bins.iter().copied().max().unwrap_or(0)
+    }
+
+    // Non-premultiplied RGBA pixels, transparent where no activity passed.
+    // Counts are log scaled so a few much-used routes do not wash out the rest.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let scale = (1.0 + self.max() as f64).ln();
+        let mut pixels = vec![0u8; self.bins.len() * 4];
+        for (bin, pixel) in self.bins.iter().zip(pixels.chunks_exact_mut(4)) {
+            if *bin > 0 {
+                let t = if scale > 0.0 {
+                    (1.0 + *bin as f64).ln() / scale
+                } else {
+                    1.0
+                };
+                pixel.copy_from_slice(&heat_color(t));
+            }
+        }
+        pixels
+    }
+}
+
+// Color of a density in 0..1 on the heat ramp.
+pub fn heat_color(t: f64) -> [u8; 4] {
+    let t = t.clamp(0.0, 1.0);
+    let upper = HEAT_RAMP
+        .iter()
+        .position(|stop| stop.0 >= t)
+        .unwrap_or(HEAT_RAMP.len() - 1)
+        .max(1);
+    let (a, b) = (HEAT_RAMP[upper - 1], HEAT_RAMP[upper]);
+    let f = (t - a.0) / (b.0 - a.0);
+    let mix = |x: f64, y: f64| (x + (y - x) * f).round() as u8;
+    [mix(a.1, b.1), mix(a.2, b.2), mix(a.3, b.3), mix(a.4, b.4)]
+}
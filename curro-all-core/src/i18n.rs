@@ -13,9 +13,9 @@ thread_local! {
 
     // Load the appropriate file based on the detected language
     let ftl_content = match lang_id.language.as_str() {
-        "es" => include_str!("../i18n/es/gui_curro-all.ftl"),
-        "fr" => include_str!("../i18n/fr/gui_curro-all.ftl"),
-        _ => include_str!("../i18n/en-US/gui_curro-all.ftl"),
+        "es" => include_str!("../../i18n/es/gui_curro-all.ftl"),
+        "fr" => include_str!("../../i18n/fr/gui_curro-all.ftl"),
+        _ => include_str!("../../i18n/en-US/gui_curro-all.ftl"),
     };
 
     let resource = FluentResource::try_new(ftl_content.to_string())
@@ -0,0 +1,235 @@
+// Module for lap (split) analysis: reading Lap messages, telling work
+// intervals from recoveries and summarizing them over a range.
+use crate::archive::open_activity;
+use crate::data::fit_value_f64;
+use crate::source::{BoxError, source_for};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// Laps whose speeds are all within this ratio of each other are treated as
+// a steady effort (e.g. auto-laps) rather than intervals.
+const STEADY_SPEED_RATIO: f64 = 1.15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LapKind {
+    Work,
+    Recovery,
+    WarmUp,
+    CoolDown,
+    Steady,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lap {
+    pub index: usize,
+    pub start_time: Option<DateTime<Utc>>,
+    // meters
+    pub distance: f64,
+    // seconds, including pauses
+    pub elapsed_time: f64,
+    // seconds, excluding pauses
+    pub timer_time: f64,
+    // m/s
+    pub avg_speed: f64,
+    pub avg_heart_rate: Option<u8>,
+    pub max_heart_rate: Option<u8>,
+    pub ascent: Option<u16>,
+    pub descent: Option<u16>,
+    // As recorded by the device, e.g. "active", "rest", "warmup", "cooldown".
+    pub intensity: String,
+    pub kind: LapKind,
+}
+
+impl Default for Lap {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            start_time: None,
+            distance: 0.0,
+            elapsed_time: 0.0,
+            timer_time: 0.0,
+            avg_speed: 0.0,
+            avg_heart_rate: None,
+            max_heart_rate: None,
+            ascent: None,
+            descent: None,
+            intensity: String::new(),
+            kind: LapKind::Steady,
+        }
+    }
+}
+
+// Read the laps of an activity file of any supported format.
+pub fn extract_laps(path: &Path) -> Result<Vec<Lap>, BoxError> {
+    let source = source_for(path).ok_or("Unsupported activity format")?;
+    let mut reader = open_activity(path)?;
+    let mut laps = source.laps(&mut reader)?;
+    classify_laps(&mut laps);
+    Ok(laps)
+}
+
+// Read the Lap messages of a FIT stream.
+pub fn fit_laps(mut reader: &mut dyn Read) -> Result<Vec<Lap>, BoxError> {
+    Ok(fit_laps_of(&fitparser::from_reader(&mut reader)?))
+}
+
+// Same as fit_laps, for messages already decoded.
+pub fn fit_laps_of(messages: &[fitparser::FitDataRecord]) -> Vec<Lap> {
+    let mut laps = Vec::new();
+    for message in messages {
+        if message.kind() != fitparser::profile::field_types::MesgNum::Lap {
+            continue;
+        }
+        let mut lap = Lap {
+            index: laps.len(),
+            ..Default::default()
+        };
+        let mut speed = None;
+        let mut enhanced_speed = None;
+        for field in message.fields() {
+            let value = field.value();
+            match field.name() {
+                "start_time" => {
+                    if let fitparser::Value::Timestamp(ts) = value {
+                        lap.start_time = Some((*ts).into());
+                    }
+                }
+                "total_distance" => lap.distance = fit_value_f64(value).unwrap_or(0.0),
+                "total_elapsed_time" => lap.elapsed_time = fit_value_f64(value).unwrap_or(0.0),
+                "total_timer_time" => lap.timer_time = fit_value_f64(value).unwrap_or(0.0),
+                "avg_speed" => speed = fit_value_f64(value),
+                "enhanced_avg_speed" => enhanced_speed = fit_value_f64(value),
+                "avg_heart_rate" => lap.avg_heart_rate = fit_value_f64(value).map(|v| v as u8),
+                "max_heart_rate" => lap.max_heart_rate = fit_value_f64(value).map(|v| v as u8),
+                "total_ascent" => lap.ascent = fit_value_f64(value).map(|v| v as u16),
+                "total_descent" => lap.descent = fit_value_f64(value).map(|v| v as u16),
+                "intensity" => lap.intensity = value.to_string(),
+                _ => {}
+            }
+        }
+        lap.avg_speed = enhanced_speed.or(speed).unwrap_or(0.0);
+        if lap.avg_speed == 0.0 && lap.timer_time > 0.0 {
+            lap.avg_speed = lap.distance / lap.timer_time;
+        }
+        laps.push(lap);
+    }
+    laps
+}
+
+// Label each lap. The device's intensity wins for warm-up, cool-down and
+// rest laps; the remaining laps are split into work and recovery by speed
+// when they differ enough to be intervals, and are steady otherwise.
+pub fn classify_laps(laps: &mut [Lap]) {
+    let mut candidates = Vec::new();
+    for (i, lap) in laps.iter_mut().enumerate() {
+        lap.kind = match lap.intensity.to_lowercase().as_str() {
+            "warmup" => LapKind::WarmUp,
+            "cooldown" => LapKind::CoolDown,
+            "rest" | "recovery" | "resting" => LapKind::Recovery,
+            _ => {
+                if lap.avg_speed > 0.0 {
+                    candidates.push(i);
+                }
+                LapKind::Steady
+            }
+        };
+    }
+
+    let speeds: Vec<f64> = candidates.iter().map(|&i| laps[i].avg_speed).collect();
+    let fastest = speeds.iter().cloned().fold(f64::MIN, f64::max);
+    let slowest = speeds.iter().cloned().fold(f64::MAX, f64::min);
+    let explicit_rest = laps.iter().any(|l| l.kind == LapKind::Recovery);
+    if speeds.len() < 2 || (fastest / slowest < STEADY_SPEED_RATIO && !explicit_rest) {
+        return;
+    }
+
+    // Two-means clustering of the lap speeds, seeded with the extremes.
+    let (mut fast, mut slow) = (fastest, slowest);
+    for _ in 0..10 {
+        let threshold = (fast + slow) / 2.0;
+        let (f, s): (Vec<f64>, Vec<f64>) = speeds.iter().partition(|&&v| v >= threshold);
+        if f.is_empty() || s.is_empty() {
+            break;
+        }
+        fast = f.iter().sum::<f64>() / f.len() as f64;
+        slow = s.iter().sum::<f64>() / s.len() as f64;
+    }
+    let threshold = (fast + slow) / 2.0;
+    for i in candidates {
+        laps[i].kind = if laps[i].avg_speed >= threshold || fastest / slowest < STEADY_SPEED_RATIO {
+            LapKind::Work
+        } else {
+            LapKind::Recovery
+        };
+    }
+}
+
+// Totals of the work and recovery laps over a set of activities.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LapSummary {
+    pub work_count: usize,
+    pub work_distance: f64,
+    pub work_time: f64,
+    pub recovery_count: usize,
+    pub recovery_distance: f64,
+    pub recovery_time: f64,
+}
+
+impl LapSummary {
+    // Average speed (m/s) of all work intervals: total distance over total time.
+    pub fn work_speed(&self) -> f64 {
+        if self.work_time > 0.0 {
+            self.work_distance / self.work_time
+        } else {
+            0.0
+        }
+    }
+
+    pub fn recovery_speed(&self) -> f64 {
+        if self.recovery_time > 0.0 {
+            self.recovery_distance / self.recovery_time
+        } else {
+            0.0
+        }
+    }
+}
+
+// Lap time used for pace: the timer time, or the elapsed time if absent.
+pub fn lap_time(lap: &Lap) -> f64 {
+    if lap.timer_time > 0.0 {
+        lap.timer_time
+    } else {
+        lap.elapsed_time
+    }
+}
+
+pub fn summarize_laps<'a>(laps: impl IntoIterator<Item = &'a Lap>) -> LapSummary {
+    let mut summary = LapSummary::default();
+    for lap in laps {
+        match lap.kind {
+            LapKind::Work => {
+                summary.work_count += 1;
+                summary.work_distance += lap.distance;
+                summary.work_time += lap_time(lap);
+            }
+            LapKind::Recovery => {
+                summary.recovery_count += 1;
+                summary.recovery_distance += lap.distance;
+                summary.recovery_time += lap_time(lap);
+            }
+            _ => {}
+        }
+    }
+    summary
+}
+
+// Read the laps of every file in parallel and summarize them.
+pub fn collect_lap_summary(results: &[(DateTime<Utc>, PathBuf)]) -> LapSummary {
+    let laps: Vec<Lap> = results
+        .par_iter()
+        .flat_map(|(_, path)| extract_laps(path).unwrap_or_default())
+        .collect();
+    summarize_laps(&laps)
+}
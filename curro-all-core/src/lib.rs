@@ -0,0 +1,34 @@
+// Core library for Curro-All: indexing of activity files, time range
+// computation, session statistics extraction, unit conversion and
+// aggregate computation. It has no GUI dependency so it may be reused by
+// other tools; the GTK front-end is a thin client on top of it.
+pub mod archive;
+pub mod calendar;
+pub mod data;
+pub mod dem;
+pub mod digest;
+pub mod filter;
+pub mod fitness;
+pub mod gear;
+pub mod gpx;
+pub mod heatmap;
+pub mod i18n;
+pub mod laps;
+pub mod map;
+pub mod metrics;
+pub mod privacy;
+pub mod records;
+pub mod sidecar;
+pub mod source;
+pub mod statistics;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod tcx;
+pub mod trend;
+pub mod volume;
+pub mod zones;
+
+pub use data::{
+    PlottableData, SessionStats, TimeBucket, Units, collect_all_stats, convert_all_stats,
+    extract_session_data, get_files_in_range, get_time_range, process_fit_directory,
+};
@@ -0,0 +1,208 @@
+// Module for offline maps: GPS tracks of activities projected to Web
+// Mercator, and map tiles read from a tile directory or an MBTiles file.
+use crate::records::{Record, extract_records};
+use crate::source::{BoxError, EARTH_RADIUS};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+
+// Edge of a map tile in pixels.
+pub const TILE_SIZE: f64 = 256.0;
+pub const MAX_ZOOM: u32 = 18;
+
+// Web Mercator cannot represent the poles; latitudes are clamped to this.
+const MAX_LATITUDE: f64 = 85.051_128_78;
+
+// GPS positions (latitude, longitude) in degrees.
+pub type Track = Vec<(f64, f64)>;
+
+// Project a position to Web Mercator world coordinates, both in 0..1 with
+// y increasing southwards (the tile layout).
+pub fn project(lat: f64, lon: f64) -> (f64, f64) {
+    let x = (lon + 180.0) / 360.0;
+    let phi = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let y = (1.0 - (phi.tan() + 1.0 / phi.cos()).ln() / PI) / 2.0;
+    (x, y)
+}
+
+// Read the GPS track of an activity file; empty for indoor activities.
+pub fn extract_track(path: &Path) -> Result<Track, BoxError> {
+    Ok(track_of(&extract_records(path)?))
+}
+
+// The GPS positions of records already read.
+pub fn track_of(records: &[Record]) -> Track {
+    records
+        .iter()
+        .filter_map(|r| Some((r.lat?, r.lon?)))
+        .collect()
+}
+
+// Read the tracks of every file in parallel, skipping files without GPS.
+pub fn collect_tracks(results: &[(DateTime<Utc>, PathBuf)]) -> Vec<(DateTime<Utc>, Track)> {
+    let mut tracks: Vec<(DateTime<Utc>, Track)> = results
+        .par_iter()
+        .filter_map(|(timestamp, path)| {
+            let track = extract_track(path).ok()?;
+            (!track.is_empty()).then_some((*timestamp, track))
+        })
+        .collect();
+    tracks.sort_by_key(|(timestamp, _)| *timestamp);
+    tracks
+}
+
+// Bounding box in world coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    pub fn of_tracks<'a>(tracks: impl IntoIterator<Item = &'a Track>) -> Option<Bounds> {
+        let mut bounds: Option<Bounds> = None;
+        for &(lat, lon) in tracks.into_iter().flatten() {
+            let (x, y) = project(lat, lon);
+            let b = bounds.get_or_insert(Bounds {
+                min_x: x,
+                min_y: y,
+                max_x: x,
+                max_y: y,
+            });
+            b.min_x = b.min_x.min(x);
+            b.min_y = b.min_y.min(y);
+            b.max_x = b.max_x.max(x);
+            b.max_y = b.max_y.max(y);
+        }
+        bounds
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min_x + self.max_x) / 2.0,
+            (self.min_y + self.max_y) / 2.0,
+        )
+    }
+
+    // Largest zoom level at which the box fits in a view of the given size.
+    pub fn zoom_to_fit(&self, width: f64, height: f64) -> u32 {
+        let span_x = (self.max_x - self.min_x).max(f64::EPSILON);
+        let span_y = (self.max_y - self.min_y).max(f64::EPSILON);
+        let scale = (width / span_x).min(height / span_y) / TILE_SIZE;
+        (scale.log2().floor().max(0.0) as u32).min(MAX_ZOOM)
+    }
+}
+
+// A view of the map: world coordinates centred in a pixel area at a zoom level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub zoom: u32,
+    pub center_x: f64,
+    pub center_y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Viewport {
+    // Centre the bounds in the area, at the largest zoom leaving the given
+    // share of the area empty on each side.
+    pub fn fit(bounds: &Bounds, width: f64, height: f64, padding: f64) -> Viewport {
+        let (center_x, center_y) = bounds.center();
+        Viewport {
+            zoom: bounds.zoom_to_fit(
+                width * (1.0 - 2.0 * padding),
+                height * (1.0 - 2.0 * padding),
+            ),
+            center_x,
+            center_y,
+            width,
+            height,
+        }
+    }
+
+    // Pixels per world unit.
+    pub fn scale(&self) -> f64 {
+        TILE_SIZE * f64::from(1u32 << self.zoom)
+    }
+
+    // Pixel position of a point in world coordinates.
+    pub fn world_to_pixel(&self, x: f64, y: f64) -> (f64, f64) {
+        let scale = self.scale();
+        (
+            (x - self.center_x) * scale + self.width / 2.0,
+            (y - self.center_y) * scale + self.height / 2.0,
+        )
+    }
+
+    // Pixel position of a GPS position.
+    pub fn to_pixel(&self, lat: f64, lon: f64) -> (f64, f64) {
+        let (x, y) = project(lat, lon);
+        self.world_to_pixel(x, y)
+    }
+
+    // Length in pixels of a distance on the ground at a given latitude.
+    pub fn meters_to_pixels(&self, meters: f64, lat: f64) -> f64 {
+        let world_meters = 2.0 * PI * EARTH_RADIUS * lat.to_radians().cos();
+        meters / world_meters * self.scale()
+    }
+}
+
+// Where map tiles come from. Tiles are PNG or JPEG images in the
+// slippy-map layout (zoom/x/y, y counted from the north).
+pub enum TileSource {
+    // A directory of {z}/{x}/{y}.png (or .jpg) files.
+    Directory(PathBuf),
+    // An MBTiles database; its rows are counted from the south (TMS).
+    #[cfg(feature = "sqlite")]
+    MbTiles(rusqlite::Connection),
+}
+
+impl TileSource {
+    // Open a tile directory or an .mbtiles file. None if it does not exist
+    // or the format is not supported.
+    pub fn open(path: &Path) -> Option<TileSource> {
+        if path.is_dir() {
+            return Some(TileSource::Directory(path.to_path_buf()));
+        }
+        #[cfg(feature = "sqlite")]
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("mbtiles"))
+        {
+            let flags = rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY;
+            return rusqlite::Connection::open_with_flags(path, flags)
+                .ok()
+                .map(TileSource::MbTiles);
+        }
+        None
+    }
+
+    // Return the encoded image of a tile, if the source has it.
+    pub fn tile(&self, zoom: u32, x: u32, y: u32) -> Option<Vec<u8>> {
+        match self {
+            TileSource::Directory(dir) => ["png", "jpg", "jpeg"].iter().find_map(|ext| {
+                std::fs::read(
+                    dir.join(zoom.to_string())
+                        .join(x.to_string())
+                        .join(format!("{}.{}", y, ext)),
+                )
+                .ok()
+            }),
+            #[cfg(feature = "sqlite")]
+            TileSource::MbTiles(conn) => {
+                let row = (1u32 << zoom) - 1 - y;
+                conn.query_row(
+                    "SELECT tile_data FROM tiles
+                     WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                    (zoom, x, row),
+                    |r| r.get(0),
+                )
+                .ok()
+            }
+        }
+    }
+}
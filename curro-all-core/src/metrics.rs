@@ -0,0 +1,133 @@
+// Module listing the session metrics that can be charted. A field added to
+// SessionStats becomes available to the chart layout with an entry here.
+use crate::data::{SessionStats, Units};
+use crate::i18n::tr;
+use chrono::{DateTime, Utc};
+
+pub struct Metric {
+    // Name used in the settings file.
+    pub name: &'static str,
+    // Translation key of the chart caption.
+    pub caption: &'static str,
+    // Unit label of the converted value.
+    pub unit: fn(&Units) -> String,
+    // Value of a session already converted to the unit system shown.
+    pub value: fn(&SessionStats) -> f64,
+    // Whether values add up over activities (distance does, pace does not).
+    pub additive: bool,
+    // Whether a falling value is an improvement (pace).
+    pub lower_is_better: bool,
+    // Default chart color, red, green and blue.
+    pub color: (u8, u8, u8),
+}
+
+fn distance_unit(units: &Units) -> String {
+    match units {
+        Units::US => tr("UNIT_MILES", None),
+        _ => tr("UNIT_KM", None),
+    }
+}
+
+fn pace_unit(units: &Units) -> String {
+    match units {
+        Units::US => tr("UNIT_PACE_US", None),
+        _ => tr("UNIT_PACE_METRIC", None),
+    }
+}
+
+fn altitude_unit(units: &Units) -> String {
+    match units {
+        Units::US => tr("UNIT_FEET", None),
+        _ => tr("UNIT_METERS", None),
+    }
+}
+
+pub const METRICS: [Metric; 7] = [
+    Metric {
+        name: "distance",
+        caption: "GRAPH_CAPTION_DISTANCE",
+        unit: distance_unit,
+        value: |s| s.distance,
+        additive: true,
+        lower_is_better: false,
+        color: (0, 255, 0),
+    },
+    Metric {
+        name: "calories",
+        caption: "GRAPH_CAPTION_CALORIES",
+        unit: |_| "kcal".to_string(),
+        value: |s| s.calories as f64,
+        additive: true,
+        lower_is_better: false,
+        color: (0, 0, 255),
+    },
+    Metric {
+        name: "pace",
+        caption: "GRAPH_CAPTION_PACE",
+        unit: pace_unit,
+        value: |s| s.enhanced_speed,
+        additive: false,
+        lower_is_better: true,
+        color: (121, 85, 72),
+    },
+    Metric {
+        name: "duration",
+        caption: "GRAPH_CAPTION_DURATION",
+        unit: |_| tr("UNIT_MINUTES", None),
+        value: |s| s.duration,
+        additive: true,
+        lower_is_better: false,
+        color: (255, 0, 0),
+    },
+    Metric {
+        name: "moving_time",
+        caption: "GRAPH_CAPTION_MOVING_TIME",
+        unit: |_| tr("UNIT_MINUTES", None),
+        value: |s| s.moving_time,
+        additive: true,
+        lower_is_better: false,
+        color: (156, 39, 176),
+    },
+    Metric {
+        name: "ascent",
+        caption: "GRAPH_CAPTION_ASCENT",
+        unit: altitude_unit,
+        value: |s| s.ascent as f64,
+        additive: true,
+        lower_is_better: false,
+        color: (0, 188, 212),
+    },
+    Metric {
+        name: "descent",
+        caption: "GRAPH_CAPTION_DESCENT",
+        unit: altitude_unit,
+        value: |s| s.descent as f64,
+        additive: true,
+        lower_is_better: false,
+        color: (255, 255, 0),
+    },
+];
+
+pub fn find_metric(name: &str) -> Option<&'static Metric> {
+    METRICS
+        .iter()
+        .find(|m| m.name.eq_ignore_ascii_case(name.trim()))
+}
+
+// Running total of a metric over activities in date order, or the running
+// average for metrics that do not add up.
+pub fn cumulative(values: &[(DateTime<Utc>, f64)], additive: bool) -> Vec<f64> {
+    let mut total = 0.0;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, (_, v))| {
+            total += v;
+            if additive {
+                total
+            } else {
+                total / (i + 1) as f64
+            }
+        })
+        .collect()
+}
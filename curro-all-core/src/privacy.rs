@@ -0,0 +1,62 @@
+// Module for privacy zones: circles (e.g. around home or work) inside which
+// GPS positions are never drawn or exported.
+use crate::map::Track;
+use crate::source::haversine;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrivacyZone {
+    // Centre, degrees.
+    pub lat: f64,
+    pub lon: f64,
+    // meters
+    pub radius: f64,
+}
+
+impl PrivacyZone {
+    // A zone hiding every position, for when the configured zones are not
+    // known.
+    pub fn everywhere() -> Self {
+        PrivacyZone {
+            lat: 0.0,
+            lon: 0.0,
+            radius: f64::INFINITY,
+        }
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        haversine(self.lat, self.lon, lat, lon) <= self.radius
+    }
+}
+
+pub fn is_private(zones: &[PrivacyZone], lat: f64, lon: f64) -> bool {
+    zones.iter().any(|zone| zone.contains(lat, lon))
+}
+
+// Split a track into the runs of positions outside every zone. A track
+// crossing a zone becomes two parts with nothing drawn in between.
+pub fn clip_track(track: &Track, zones: &[PrivacyZone]) -> Vec<Track> {
+    let mut parts = Vec::new();
+    let mut current = Track::new();
+    for &(lat, lon) in track {
+        if is_private(zones, lat, lon) {
+            if !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+        } else {
+            current.push((lat, lon));
+        }
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+// The positions of a track that a privacy zone hides.
+pub fn hidden_points(track: &Track, zones: &[PrivacyZone]) -> Track {
+    track
+        .iter()
+        .copied()
+        .filter(|&(lat, lon)| is_private(zones, lat, lon))
+        .collect()
+}
@@ -0,0 +1,143 @@
+// Module for the per-record (per-second) samples of an activity: FIT Record
+// messages or GPX/TCX track points.
+use crate::archive::open_activity;
+use crate::data::fit_value_f64;
+use crate::source::{BoxError, TrackPoint, haversine, source_for};
+use chrono::{DateTime, Utc};
+use std::io::Read;
+use std::path::Path;
+
+// Degrees per FIT semicircle (2^31 semicircles = 180 degrees).
+const DEGREES_PER_SEMICIRCLE: f64 = 180.0 / 2_147_483_648.0;
+
+// One sample of an activity. Fields the device did not record are None.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Record {
+    pub time: Option<DateTime<Utc>>,
+    // seconds since the first record
+    pub elapsed: f64,
+    // cumulative meters
+    pub distance: Option<f64>,
+    // m/s
+    pub speed: Option<f64>,
+    // bpm
+    pub heart_rate: Option<f64>,
+    // meters
+    pub altitude: Option<f64>,
+    // rpm (steps per minute per foot when running)
+    pub cadence: Option<f64>,
+    // watts
+    pub power: Option<f64>,
+    // degrees
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+}
+
+// Read the records of an activity file of any supported format.
+pub fn extract_records(path: &Path) -> Result<Vec<Record>, BoxError> {
+    let source = source_for(path).ok_or("Unsupported activity format")?;
+    let mut reader = open_activity(path)?;
+    let mut records = source.records(&mut reader)?;
+    fill_elapsed(&mut records);
+    Ok(records)
+}
+
+// Set the elapsed time of each record from its timestamp.
+pub(crate) fn fill_elapsed(records: &mut [Record]) {
+    let Some(start) = records.iter().find_map(|r| r.time) else {
+        return;
+    };
+    for record in records.iter_mut() {
+        if let Some(time) = record.time {
+            record.elapsed = (time - start).num_milliseconds() as f64 / 1000.0;
+        }
+    }
+}
+
+// Read the Record messages of a FIT stream.
+pub fn fit_records(mut reader: &mut dyn Read) -> Result<Vec<Record>, BoxError> {
+    Ok(fit_records_of(&fitparser::from_reader(&mut reader)?))
+}
+
+// Same as fit_records, for messages already decoded.
+pub fn fit_records_of(messages: &[fitparser::FitDataRecord]) -> Vec<Record> {
+    let mut records = Vec::new();
+    for message in messages {
+        if message.kind() != fitparser::profile::field_types::MesgNum::Record {
+            continue;
+        }
+        let mut record = Record::default();
+        let (mut speed, mut enhanced_speed) = (None, None);
+        let (mut altitude, mut enhanced_altitude) = (None, None);
+        for field in message.fields() {
+            let value = field.value();
+            match field.name() {
+                "timestamp" => {
+                    if let fitparser::Value::Timestamp(ts) = value {
+                        record.time = Some((*ts).into());
+                    }
+                }
+                "distance" => record.distance = fit_value_f64(value),
+                "speed" => speed = fit_value_f64(value),
+                "enhanced_speed" => enhanced_speed = fit_value_f64(value),
+                "altitude" => altitude = fit_value_f64(value),
+                "enhanced_altitude" => enhanced_altitude = fit_value_f64(value),
+                "heart_rate" => record.heart_rate = fit_value_f64(value),
+                "cadence" => record.cadence = fit_value_f64(value),
+                "power" => record.power = fit_value_f64(value),
+                "position_lat" => {
+                    record.lat = fit_value_f64(value).map(|v| v * DEGREES_PER_SEMICIRCLE)
+                }
+                "position_long" => {
+                    record.lon = fit_value_f64(value).map(|v| v * DEGREES_PER_SEMICIRCLE)
+                }
+                _ => {}
+            }
+        }
+        record.speed = enhanced_speed.or(speed);
+        record.altitude = enhanced_altitude.or(altitude);
+        records.push(record);
+    }
+    records
+}
+
+// Turn GPX/TCX track points into records. Distance prefers the recorded
+// cumulative distance and falls back to the sum of GPS segments; speed is
+// derived from consecutive points.
+pub fn records_from_track(points: &[TrackPoint]) -> Vec<Record> {
+    let recorded = points.iter().any(|p| p.distance.is_some());
+    let mut records: Vec<Record> = Vec::with_capacity(points.len());
+    let mut total = 0.0;
+    let mut last_position: Option<(f64, f64)> = None;
+    for point in points {
+        let distance = if recorded {
+            point.distance
+        } else {
+            if let (Some(lat), Some(lon)) = (point.lat, point.lon) {
+                if let Some((prev_lat, prev_lon)) = last_position {
+                    total += haversine(prev_lat, prev_lon, lat, lon);
+                }
+                last_position = Some((lat, lon));
+            }
+            Some(total)
+        };
+        let speed = records.last().and_then(|prev| {
+            let seconds = (point.time? - prev.time?).num_milliseconds() as f64 / 1000.0;
+            let meters = distance? - prev.distance?;
+            (seconds > 0.0).then(|| meters / seconds)
+        });
+        records.push(Record {
+            time: point.time,
+            elapsed: 0.0,
+            distance,
+            speed,
+            heart_rate: point.heart_rate,
+            altitude: point.elevation,
+            cadence: point.cadence,
+            power: point.power,
+            lat: point.lat,
+            lon: point.lon,
+        });
+    }
+    records
+}
@@ -0,0 +1,448 @@
+// Module for the library sidecar: what the user knows about activities that
+// their files do not say (a title, notes, tags such as "race" or
+// "treadmill", perceived exertion), kept in a TOML file of its own so the
+// activity files are never modified. Activities are keyed by start time,
+// which survives moving or re-archiving the files. Each library directory
+// has its own sidecar, next to its files.
+//
+// Corrections of recorded values (a GPS distance that drifted, a treadmill
+// out of calibration, bogus elevation) are kept there too and applied on top
+// of the totals read from the files, so the files stay as recorded and a
+// correction can always be reverted.
+//
+// The sidecar also holds manual activities, entered by hand for workouts
+// without a file. They are indexed like archive members, by a virtual path
+// made of the sidecar path, a separator and the start time (unix seconds),
+// e.g. `curro-all-library.toml#manual/1772352000`, so they flow through the
+// same code as recorded activities.
+use crate::data::{PlottableData, SessionStats};
+use crate::source::{ActivityInfo, BoxError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// File name of the sidecar of a library directory.
+pub const SIDECAR_NAME: &str = "curro-all-library.toml";
+
+// Separates the sidecar path from the start time in a manual activity path.
+pub const MANUAL_SEPARATOR: &str = "#manual/";
+
+// Highest rating of perceived exertion (Borg CR10 scale).
+pub const MAX_RPE: u8 = 10;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActivityMeta {
+    pub start: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    // Rating of perceived exertion, 1 to MAX_RPE.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpe: Option<u8>,
+}
+
+impl ActivityMeta {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        ActivityMeta {
+            start,
+            ..Default::default()
+        }
+    }
+
+    // Whether nothing was added to the activity.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_empty() && self.notes.is_empty() && self.tags.is_empty() && self.rpe.is_none()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    // Title, tags and notes, lower case, for text searches.
+    pub fn search_text(&self) -> String {
+        format!("{} {} {}", self.title, self.tags.join(" "), self.notes).to_lowercase()
+    }
+}
+
+// A workout entered by hand, in meters and seconds. Its title, tags and
+// notes are kept with those of recorded activities.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ManualActivity {
+    pub start: DateTime<Utc>,
+    #[serde(default)]
+    pub sport: String,
+    #[serde(default)]
+    pub distance: f64,
+    #[serde(default)]
+    pub duration: f64,
+    #[serde(default)]
+    pub ascent: u16,
+    #[serde(default)]
+    pub calories: u16,
+}
+
+impl ManualActivity {
+    // Session totals; the whole duration counts as moving.
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            distance: self.distance,
+            calories: self.calories,
+            duration: self.duration,
+            enhanced_speed: if self.duration > 0.0 {
+                self.distance / self.duration
+            } else {
+                0.0
+            },
+            ascent: self.ascent,
+            descent: 0,
+            moving_time: self.duration,
+        }
+    }
+
+    pub fn info(&self) -> ActivityInfo {
+        ActivityInfo {
+            sport: self.sport.trim().to_lowercase(),
+            device: String::new(),
+        }
+    }
+}
+
+// Values of a recorded activity replaced by the user, in meters and
+// seconds; None keeps the recorded value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Correction {
+    pub start: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ascent: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descent: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sport: Option<String>,
+}
+
+impl Correction {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Correction {
+            start,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distance.is_none()
+            && self.duration.is_none()
+            && self.ascent.is_none()
+            && self.descent.is_none()
+            && self.sport.is_none()
+    }
+
+    // Replace the corrected totals. Moving time never exceeds a corrected
+    // duration, and pace follows the distance and moving time.
+    pub fn apply(&self, stats: &mut SessionStats) {
+        if let Some(distance) = self.distance {
+            stats.distance = distance;
+        }
+        if let Some(duration) = self.duration {
+            stats.duration = duration;
+            if stats.moving_time <= 0.0 || stats.moving_time > duration {
+                stats.moving_time = duration;
+            }
+        }
+        if let Some(ascent) = self.ascent {
+            stats.ascent = ascent;
+        }
+        if let Some(descent) = self.descent {
+            stats.descent = descent;
+        }
+        if (self.distance.is_some() || self.duration.is_some()) && stats.moving_time > 0.0 {
+            stats.enhanced_speed = stats.distance / stats.moving_time;
+        }
+    }
+
+    pub fn apply_info(&self, info: &mut ActivityInfo) {
+        if let Some(sport) = &self.sport {
+            info.sport = sport.trim().to_lowercase();
+        }
+    }
+}
+
+// The sidecar of a library directory.
+pub fn library_sidecar(library: &Path) -> PathBuf {
+    library.join(SIDECAR_NAME)
+}
+
+// Build the virtual path of a manual activity of a sidecar.
+pub fn manual_path(sidecar: &Path, start: &DateTime<Utc>) -> PathBuf {
+    PathBuf::from(format!(
+        "{}{}{}",
+        sidecar.display(),
+        MANUAL_SEPARATOR,
+        start.timestamp()
+    ))
+}
+
+// Split a manual activity path into the sidecar path and the start time.
+pub fn split_manual_path(path: &Path) -> Option<(PathBuf, DateTime<Utc>)> {
+    let path_str = path.to_str()?;
+    let (sidecar, start) = path_str.split_once(MANUAL_SEPARATOR)?;
+    let start = DateTime::from_timestamp(start.parse().ok()?, 0)?;
+    Some((PathBuf::from(sidecar), start))
+}
+
+pub fn is_manual_path(path: &Path) -> bool {
+    split_manual_path(path).is_some()
+}
+
+// Read the manual activity a virtual path addresses.
+pub fn read_manual_activity(path: &Path) -> Result<ManualActivity, BoxError> {
+    let (sidecar, start) = split_manual_path(path).ok_or("Not a manual activity")?;
+    Sidecar::load(&sidecar)?
+        .manual(&start)
+        .cloned()
+        .ok_or_else(|| "Manual activity not found".into())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sidecar {
+    #[serde(default, rename = "activity")]
+    pub activities: Vec<ActivityMeta>,
+    #[serde(default, rename = "manual")]
+    pub manual: Vec<ManualActivity>,
+    #[serde(default, rename = "correction")]
+    pub corrections: Vec<Correction>,
+}
+
+impl Sidecar {
+    // Read a sidecar file; a missing file is an empty sidecar.
+    pub fn load(path: &Path) -> Result<Self, BoxError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Sidecar::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Write the sidecar, through a temporary file so an interrupted save
+    // never leaves it half written.
+    pub fn save(&self, path: &Path) -> Result<(), BoxError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, toml::to_string_pretty(self)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    pub fn meta(&self, start: &DateTime<Utc>) -> Option<&ActivityMeta> {
+        self.activities.iter().find(|m| m.start == *start)
+    }
+
+    // What was added to an activity, empty when nothing was.
+    pub fn meta_or_default(&self, start: &DateTime<Utc>) -> ActivityMeta {
+        self.meta(start)
+            .cloned()
+            .unwrap_or_else(|| ActivityMeta::new(*start))
+    }
+
+    // Replace what was added to an activity; an empty entry is dropped.
+    pub fn set_meta(&mut self, meta: ActivityMeta) {
+        self.activities.retain(|m| m.start != meta.start);
+        if !meta.is_empty() {
+            self.activities.push(meta);
+            self.activities.sort_by_key(|m| m.start);
+        }
+    }
+
+    // Every tag in use, sorted, each once.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.activities.iter().flat_map(|m| &m.tags) {
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
+            }
+        }
+        tags.sort_by_key(|t| t.to_lowercase());
+        tags
+    }
+
+    pub fn has_tag(&self, start: &DateTime<Utc>, tag: &str) -> bool {
+        self.meta(start).is_some_and(|m| m.has_tag(tag))
+    }
+
+    pub fn manual(&self, start: &DateTime<Utc>) -> Option<&ManualActivity> {
+        self.manual.iter().find(|m| m.start == *start)
+    }
+
+    // Add a manual activity, or replace the one starting at the same time.
+    pub fn set_manual(&mut self, activity: ManualActivity) {
+        self.manual.retain(|m| m.start != activity.start);
+        self.manual.push(activity);
+        self.manual.sort_by_key(|m| m.start);
+    }
+
+    // Remove a manual activity and what was added to it.
+    pub fn remove_manual(&mut self, start: &DateTime<Utc>) {
+        self.manual.retain(|m| m.start != *start);
+        self.activities.retain(|m| m.start != *start);
+        self.corrections.retain(|c| c.start != *start);
+    }
+
+    pub fn correction(&self, start: &DateTime<Utc>) -> Option<&Correction> {
+        self.corrections.iter().find(|c| c.start == *start)
+    }
+
+    pub fn is_corrected(&self, start: &DateTime<Utc>) -> bool {
+        self.correction(start).is_some()
+    }
+
+    // Replace the correction of an activity; an empty one reverts it.
+    pub fn set_correction(&mut self, correction: Correction) {
+        self.revert(&correction.start);
+        if !correction.is_empty() {
+            self.corrections.push(correction);
+            self.corrections.sort_by_key(|c| c.start);
+        }
+    }
+
+    // Go back to the values recorded in the file.
+    pub fn revert(&mut self, start: &DateTime<Utc>) {
+        self.corrections.retain(|c| c.start != *start);
+    }
+
+    // Raw session totals with the corrections applied.
+    pub fn corrected(&self, mut stats: Vec<PlottableData>) -> Vec<PlottableData> {
+        for item in &mut stats {
+            if let Some(correction) = self.correction(&item.timestamp) {
+                correction.apply(&mut item.stats);
+            }
+        }
+        stats
+    }
+
+    // Start times and virtual paths of the manual activities, to index them
+    // with the activity files; sidecar is the path this sidecar is kept at.
+    pub fn manual_index(&self, sidecar: &Path) -> Vec<(DateTime<Utc>, PathBuf)> {
+        self.manual
+            .iter()
+            .map(|m| (m.start, manual_path(sidecar, &m.start)))
+            .collect()
+    }
+}
+
+// Tags written separated by commas, trimmed, each once.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn recorded() -> SessionStats {
+        SessionStats {
+            distance: 10_000.0,
+            duration: 3_600.0,
+            moving_time: 3_000.0,
+            enhanced_speed: 10_000.0 / 3_000.0,
+            ascent: 120,
+            descent: 110,
+            calories: 700,
+        }
+    }
+
+    fn start(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn distance_corrections_keep_the_times_and_set_the_pace() {
+        let mut stats = recorded();
+        Correction {
+            distance: Some(10_500.0),
+            ..Correction::new(start(8))
+        }
+        .apply(&mut stats);
+        assert_eq!(stats.distance, 10_500.0);
+        assert_eq!(stats.duration, 3_600.0);
+        assert_eq!(stats.moving_time, 3_000.0);
+        assert_eq!(stats.enhanced_speed, 3.5);
+        assert_eq!((stats.ascent, stats.descent), (120, 110));
+    }
+
+    #[test]
+    fn duration_corrections_bound_the_moving_time() {
+        let mut shorter = recorded();
+        Correction {
+            duration: Some(2_500.0),
+            ..Correction::new(start(8))
+        }
+        .apply(&mut shorter);
+        assert_eq!(shorter.distance, 10_000.0);
+        assert_eq!(shorter.duration, 2_500.0);
+        assert_eq!(shorter.moving_time, 2_500.0);
+        assert_eq!(shorter.enhanced_speed, 4.0);
+
+        let mut longer = recorded();
+        Correction {
+            duration: Some(4_000.0),
+            ..Correction::new(start(8))
+        }
+        .apply(&mut longer);
+        assert_eq!(longer.duration, 4_000.0);
+        assert_eq!(longer.moving_time, 3_000.0);
+    }
+
+    #[test]
+    fn corrections_are_replaced_and_reverted() {
+        let data = vec![
+            PlottableData {
+                timestamp: start(8),
+                stats: recorded(),
+            },
+            PlottableData {
+                timestamp: start(18),
+                stats: recorded(),
+            },
+        ];
+        let mut sidecar = Sidecar::default();
+        sidecar.set_correction(Correction {
+            distance: Some(9_000.0),
+            ..Correction::new(start(8))
+        });
+        sidecar.set_correction(Correction {
+            distance: Some(10_500.0),
+            ..Correction::new(start(8))
+        });
+        assert_eq!(sidecar.corrections.len(), 1);
+        let corrected = sidecar.corrected(data.clone());
+        assert_eq!(corrected[0].stats.distance, 10_500.0);
+        assert_eq!(corrected[1].stats.distance, 10_000.0);
+
+        // Clearing every value reverts, like revert does.
+        sidecar.set_correction(Correction::new(start(8)));
+        assert!(!sidecar.is_corrected(&start(8)));
+        sidecar.set_correction(Correction {
+            ascent: Some(80),
+            ..Correction::new(start(8))
+        });
+        sidecar.revert(&start(8));
+        assert!(sidecar.corrections.is_empty());
+        assert_eq!(sidecar.corrected(data)[0].stats.ascent, 120);
+    }
+}
@@ -0,0 +1,260 @@
+// Module providing a common interface over the supported activity file
+// formats (FIT, GPX and TCX).
+use crate::data::{
+    SessionStats, fit_activity_info, fit_activity_info_of, fit_session_stats, fit_session_stats_of,
+    fit_start_time,
+};
+use crate::fitness::{fit_vo2max, fit_vo2max_of};
+use crate::gpx::GpxSource;
+use crate::laps::{Lap, fit_laps, fit_laps_of};
+use crate::records::{Record, fit_records, fit_records_of};
+use crate::tcx::TcxSource;
+use crate::zones::{fit_time_in_hr_zone, fit_time_in_hr_zone_of};
+use chrono::{DateTime, Utc};
+use std::io::Read;
+use std::path::Path;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Mean earth radius in meters.
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+// Elevation changes smaller than this (meters) are treated as GPS noise
+// when summing ascent and descent from track points.
+const ELEVATION_THRESHOLD: f64 = 2.0;
+
+// Descriptive information about an activity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActivityInfo {
+    // Sport in lower case, e.g. "running".
+    pub sport: String,
+    // Recording device, e.g. "garmin fr945".
+    pub device: String,
+}
+
+// Everything the views read from one activity file.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedActivity {
+    pub stats: SessionStats,
+    pub info: ActivityInfo,
+    pub laps: Vec<Lap>,
+    pub records: Vec<Record>,
+    // Seconds in each heart rate zone as computed by the device, if recorded.
+    pub device_hr_zones: Option<Vec<f64>>,
+    pub vo2max: Option<f64>,
+}
+
+// A reader for one activity file format.
+pub trait ActivitySource: Sync {
+    // Return the start time of the activity.
+    fn start_time(&self, reader: &mut dyn Read) -> Result<DateTime<Utc>, BoxError>;
+    // Return the session totals of the activity.
+    fn session_stats(&self, reader: &mut dyn Read) -> Result<SessionStats, BoxError>;
+    // Return the sport and recording device of the activity.
+    fn activity_info(&self, reader: &mut dyn Read) -> Result<ActivityInfo, BoxError>;
+    // Return the laps of the activity, in order. Formats without laps have none.
+    fn laps(&self, _reader: &mut dyn Read) -> Result<Vec<Lap>, BoxError> {
+        Ok(Vec::new())
+    }
+    // Return the recorded samples of the activity, in order.
+    fn records(&self, reader: &mut dyn Read) -> Result<Vec<Record>, BoxError>;
+    // Return the seconds in each heart rate zone as computed by the device, if recorded.
+    fn time_in_hr_zone(&self, _reader: &mut dyn Read) -> Result<Option<Vec<f64>>, BoxError> {
+        Ok(None)
+    }
+    // Return the VO2max estimated by the device, if recorded.
+    fn vo2max(&self, _reader: &mut dyn Read) -> Result<Option<f64>, BoxError> {
+        Ok(None)
+    }
+    // Return everything above at once. The file is read once and each part
+    // taken from the bytes in memory; formats that can decode once override it.
+    // Only missing session totals are an error.
+    fn parse(&self, reader: &mut dyn Read) -> Result<ParsedActivity, BoxError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(ParsedActivity {
+            stats: self.session_stats(&mut &bytes[..])?,
+            info: self.activity_info(&mut &bytes[..]).unwrap_or_default(),
+            laps: self.laps(&mut &bytes[..]).unwrap_or_default(),
+            records: self.records(&mut &bytes[..]).unwrap_or_default(),
+            device_hr_zones: self.time_in_hr_zone(&mut &bytes[..]).ok().flatten(),
+            vo2max: self.vo2max(&mut &bytes[..]).ok().flatten(),
+        })
+    }
+}
+
+pub struct FitSource;
+
+impl ActivitySource for FitSource {
+    fn start_time(&self, reader: &mut dyn Read) -> Result<DateTime<Utc>, BoxError> {
+        fit_start_time(reader)
+    }
+    fn session_stats(&self, reader: &mut dyn Read) -> Result<SessionStats, BoxError> {
+        fit_session_stats(reader)
+    }
+    fn activity_info(&self, reader: &mut dyn Read) -> Result<ActivityInfo, BoxError> {
+        fit_activity_info(reader)
+    }
+    fn laps(&self, reader: &mut dyn Read) -> Result<Vec<Lap>, BoxError> {
+        fit_laps(reader)
+    }
+    fn records(&self, reader: &mut dyn Read) -> Result<Vec<Record>, BoxError> {
+        fit_records(reader)
+    }
+    fn time_in_hr_zone(&self, reader: &mut dyn Read) -> Result<Option<Vec<f64>>, BoxError> {
+        fit_time_in_hr_zone(reader)
+    }
+    fn vo2max(&self, reader: &mut dyn Read) -> Result<Option<f64>, BoxError> {
+        fit_vo2max(reader)
+    }
+    fn parse(&self, mut reader: &mut dyn Read) -> Result<ParsedActivity, BoxError> {
+        let messages = fitparser::from_reader(&mut reader)?;
+        Ok(ParsedActivity {
+            stats: fit_session_stats_of(&messages),
+            info: fit_activity_info_of(&messages),
+            laps: fit_laps_of(&messages),
+            records: fit_records_of(&messages),
+            device_hr_zones: fit_time_in_hr_zone_of(&messages),
+            vo2max: fit_vo2max_of(&messages),
+        })
+    }
+}
+
+// Extension of an activity file name, ignoring a trailing ".gz".
+fn activity_extension(name: &str) -> String {
+    let name = name.to_lowercase();
+    let name = name.strip_suffix(".gz").unwrap_or(&name);
+    match name.rsplit_once('.') {
+        Some((_, ext)) => ext.to_string(),
+        None => String::new(),
+    }
+}
+
+// True for bare or gzip-compressed FIT, GPX and TCX file names.
+pub fn is_activity_name(name: &str) -> bool {
+    matches!(activity_extension(name).as_str(), "fit" | "gpx" | "tcx")
+}
+
+// Pick the reader for a (possibly virtual) activity path from its extension.
+pub fn source_for(path: &Path) -> Option<&'static dyn ActivitySource> {
+    let name = path.file_name()?.to_str()?;
+    match activity_extension(name).as_str() {
+        "fit" => Some(&FitSource),
+        "gpx" => Some(&GpxSource),
+        "tcx" => Some(&TcxSource),
+        _ => None,
+    }
+}
+
+// A single point of a GPX or TCX track.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrackPoint {
+    pub time: Option<DateTime<Utc>>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub elevation: Option<f64>,
+    // Cumulative distance in meters, when the file records it (TCX).
+    pub distance: Option<f64>,
+    pub heart_rate: Option<f64>,
+    pub cadence: Option<f64>,
+    pub power: Option<f64>,
+}
+
+// Great circle distance in meters between two points given in degrees.
+pub fn haversine(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_phi = (lat2 - lat1).to_radians();
+    let d_lambda = (lon2 - lon1).to_radians();
+    let a = (d_phi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (d_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+// Parse an XML (RFC 3339) timestamp.
+pub fn parse_xml_time(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text.trim())
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+// Slowest speed (m/s) still counted as moving rather than stopped.
+const MIN_MOVING_SPEED: f64 = 0.5;
+
+// Seconds between consecutive timed points covered at least at
+// MIN_MOVING_SPEED, i.e. the elapsed time less the stops.
+fn moving_time(points: &[TrackPoint]) -> f64 {
+    let timed: Vec<&TrackPoint> = points.iter().filter(|p| p.time.is_some()).collect();
+    timed
+        .windows(2)
+        .filter_map(|w| {
+            let (a, b) = (w[0], w[1]);
+            let seconds = (b.time? - a.time?).num_milliseconds() as f64 / 1000.0;
+            let meters = match (a.distance, b.distance, a.lat, a.lon, b.lat, b.lon) {
+                (Some(da), Some(db), ..) => db - da,
+                (_, _, Some(lat1), Some(lon1), Some(lat2), Some(lon2)) => {
+                    haversine(lat1, lon1, lat2, lon2)
+                }
+                _ => return None,
+            };
+            (seconds > 0.0 && meters / seconds >= MIN_MOVING_SPEED).then_some(seconds)
+        })
+        .sum()
+}
+
+// Derive session totals from track points. Distance prefers the recorded
+// cumulative distance and falls back to the sum of GPS segments.
+pub fn stats_from_track(points: &[TrackPoint]) -> SessionStats {
+    let mut stats = SessionStats::default();
+
+    let recorded = points.iter().filter_map(|p| p.distance).fold(0.0, f64::max);
+    if recorded > 0.0 {
+        stats.distance = recorded;
+    } else {
+        let positions: Vec<(f64, f64)> = points
+            .iter()
+            .filter_map(|p| Some((p.lat?, p.lon?)))
+            .collect();
+        stats.distance = positions
+            .windows(2)
+            .map(|w| haversine(w[0].0, w[0].1, w[1].0, w[1].1))
+            .sum();
+    }
+
+    let first = points.iter().find_map(|p| p.time);
+    let last = points.iter().rev().find_map(|p| p.time);
+    if let (Some(first), Some(last)) = (first, last) {
+        stats.duration = (last - first).num_milliseconds() as f64 / 1000.0;
+    }
+    stats.moving_time = moving_time(points);
+    if stats.moving_time > 0.0 {
+        stats.enhanced_speed = stats.distance / stats.moving_time;
+    }
+
+    let (ascent, descent) = ascent_descent(points.iter().filter_map(|p| p.elevation));
+    stats.ascent = ascent.round().min(u16::MAX as f64) as u16;
+    stats.descent = descent.round().min(u16::MAX as f64) as u16;
+    stats
+}
+
+// Total climb and drop (meters) of an elevation profile. A climb or drop
+// only counts once it exceeds the noise threshold.
+pub fn ascent_descent(elevations: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (mut ascent, mut descent) = (0.0, 0.0);
+    let mut reference: Option<f64> = None;
+    for elevation in elevations {
+        match reference {
+            None => reference = Some(elevation),
+            Some(r) => {
+                let delta = elevation - r;
+                if delta.abs() >= ELEVATION_THRESHOLD {
+                    if delta > 0.0 {
+                        ascent += delta;
+                    } else {
+                        descent -= delta;
+                    }
+                    reference = Some(elevation);
+                }
+            }
+        }
+    }
+    (ascent, descent)
+}
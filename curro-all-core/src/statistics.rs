@@ -0,0 +1,232 @@
+// Module computing the statistics of each session metric over the
+// activities of a range: count, total, mean, median, standard deviation,
+// 10th and 90th percentiles, minimum and maximum. The summary table and
+// the exports share it so they always agree.
+use crate::data::{PlottableData, SessionStats};
+use crate::metrics::{METRICS, Metric};
+
+// What the mean and standard deviation of a metric are weighted by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Weighting {
+    // Every activity counts the same.
+    #[default]
+    None,
+    Distance,
+    Duration,
+}
+
+impl Weighting {
+    // Parse a settings or command line name; anything else is None.
+    pub fn from_name(name: &str) -> Self {
+        match name.trim().to_lowercase().as_str() {
+            "distance" => Weighting::Distance,
+            "duration" => Weighting::Duration,
+            _ => Weighting::None,
+        }
+    }
+
+    fn weight(&self, stats: &SessionStats) -> f64 {
+        match self {
+            Weighting::None => 1.0,
+            Weighting::Distance => stats.distance,
+            Weighting::Duration => stats.duration,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Statistics {
+    pub count: usize,
+    pub total: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+    pub p10: f64,
+    pub p90: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+// Value at a share (0 to 1) of sorted values, interpolating between the
+// two nearest.
+fn percentile(sorted: &[f64], share: f64) -> f64 {
+    let position = share * (sorted.len() - 1) as f64;
+    let (low, high) = (position.floor() as usize, position.ceil() as usize);
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+// Weighted sample standard deviation of (value, weight) pairs around a
+// mean, zero for a single value.
+fn std_dev(values: &[(f64, f64)], mean: f64) -> f64 {
+    let weight: f64 = values.iter().map(|(_, w)| w).sum();
+    if values.len() < 2 || weight <= 0.0 {
+        return 0.0;
+    }
+    let squares: f64 = values.iter().map(|(v, w)| w * (v - mean).powi(2)).sum();
+    let n = values.len() as f64;
+    (squares / weight * n / (n - 1.0)).sqrt()
+}
+
+impl Statistics {
+    // Statistics of (value, weight) pairs; the weights only apply to the
+    // mean and the standard deviation. None without any value or weight.
+    pub fn weighted(values: &[(f64, f64)]) -> Option<Self> {
+        let weight: f64 = values.iter().map(|(_, w)| w).sum();
+        if values.is_empty() || weight <= 0.0 {
+            return None;
+        }
+        let mut sorted: Vec<f64> = values.iter().map(|(v, _)| *v).collect();
+        sorted.sort_by(f64::total_cmp);
+        let mean = values.iter().map(|(v, w)| v * w).sum::<f64>() / weight;
+        Some(Statistics {
+            count: values.len(),
+            total: sorted.iter().sum(),
+            mean,
+            median: percentile(&sorted, 0.5),
+            std_dev: std_dev(values, mean),
+            p10: percentile(&sorted, 0.1),
+            p90: percentile(&sorted, 0.9),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+        })
+    }
+
+    pub fn of(values: &[f64]) -> Option<Self> {
+        Self::weighted(&values.iter().map(|v| (*v, 1.0)).collect::<Vec<_>>())
+    }
+
+    // The best value: the lowest for metrics where lower is better (pace).
+    pub fn best(&self, metric: &Metric) -> f64 {
+        if metric.lower_is_better {
+            self.min
+        } else {
+            self.max
+        }
+    }
+
+    pub fn worst(&self, metric: &Metric) -> f64 {
+        if metric.lower_is_better {
+            self.max
+        } else {
+            self.min
+        }
+    }
+}
+
+// Statistics of every metric of METRICS over the activities of a range.
+#[derive(Debug, Clone, Default)]
+pub struct RangeStatistics {
+    // Number of activities.
+    pub count: usize,
+    // In METRICS order; None when no activity has the metric.
+    pub metrics: Vec<Option<Statistics>>,
+}
+
+impl RangeStatistics {
+    pub fn get(&self, name: &str) -> Option<&Statistics> {
+        let index = METRICS.iter().position(|m| m.name == name)?;
+        self.metrics.get(index)?.as_ref()
+    }
+}
+
+// Compute the statistics of sessions already converted to the unit system
+// shown. Pace is only defined for activities with a distance, and its mean
+// is the total moving time over the total distance rather than the mean of
+// paces, so long runs weigh more than short ones. Its standard deviation is
+// weighted by distance too, around that mean.
+pub fn range_statistics(stats: &[PlottableData], weighting: Weighting) -> RangeStatistics {
+    let metrics = METRICS
+        .iter()
+        .map(|metric| {
+            let pace = metric.name == "pace";
+            let values: Vec<(f64, f64)> = stats
+                .iter()
+                .filter(|item| !pace || item.stats.distance > 0.0)
+                .map(|item| ((metric.value)(&item.stats), weighting.weight(&item.stats)))
+                .collect();
+            let mut statistics = Statistics::weighted(&values)
+                .or_else(|| Statistics::of(&values.iter().map(|(v, _)| *v).collect::<Vec<_>>()))?;
+            if pace {
+                let (time, distance) = stats
+                    .iter()
+                    .filter(|item| item.stats.distance > 0.0)
+                    .fold((0.0, 0.0), |(t, d), item| {
+                        (t + item.stats.moving_time, d + item.stats.distance)
+                    });
+                if distance > 0.0 {
+                    statistics.mean = time / distance;
+                    let by_distance: Vec<(f64, f64)> = stats
+                        .iter()
+                        .filter(|item| item.stats.distance > 0.0)
+                        .map(|item| ((metric.value)(&item.stats), item.stats.distance))
+                        .collect();
+                    statistics.std_dev = std_dev(&by_distance, statistics.mean);
+                }
+            }
+            Some(statistics)
+        })
+        .collect();
+    RangeStatistics {
+        count: stats.len(),
+        metrics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn run(distance: f64, moving_time: f64) -> PlottableData {
+        PlottableData {
+            timestamp: Utc.with_ymd_and_hms(2026, 3, 1, 8, 0, 0).unwrap(),
+            stats: SessionStats {
+                distance,
+                duration: moving_time,
+                moving_time,
+                enhanced_speed: moving_time / distance,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn percentiles_interpolate_between_values() {
+        let stats = Statistics::of(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+        assert_eq!(stats.median, 2.5);
+        assert!((stats.p10 - 1.3).abs() < 1e-9);
+        assert!((stats.p90 - 3.7).abs() < 1e-9);
+        assert_eq!((stats.min, stats.max, stats.total), (1.0, 4.0, 10.0));
+    }
+
+    #[test]
+    fn single_value_has_no_deviation() {
+        let stats = Statistics::of(&[7.0]).unwrap();
+        assert_eq!((stats.median, stats.p10, stats.p90), (7.0, 7.0, 7.0));
+        assert_eq!(stats.std_dev, 0.0);
+        assert!(Statistics::of(&[]).is_none());
+    }
+
+    #[test]
+    fn pace_mean_is_time_over_distance() {
+        // 10 km at 5:00 and 5 km at 6:00.
+        let stats = [run(10.0, 50.0), run(5.0, 30.0)];
+        let range = range_statistics(&stats, Weighting::None);
+        let pace = range.get("pace").unwrap();
+        assert!((pace.mean - 80.0 / 15.0).abs() < 1e-9);
+        // The deviation is taken around that mean, weighted by distance.
+        let expected =
+            ((10.0 * (5.0 - pace.mean).powi(2) + 5.0 * (6.0 - pace.mean).powi(2)) / 15.0 * 2.0)
+                .sqrt();
+        assert!((pace.std_dev - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pace_ignores_activities_without_distance() {
+        let stats = [run(10.0, 50.0), run(0.0, 40.0)];
+        let range = range_statistics(&stats, Weighting::None);
+        assert_eq!(range.count, 2);
+        assert_eq!(range.get("pace").unwrap().count, 1);
+        assert_eq!(range.get("duration").unwrap().count, 2);
+    }
+}
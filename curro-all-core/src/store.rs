@@ -0,0 +1,382 @@
+// Module providing an optional SQLite-backed store of activities, their
+// session totals, laps and per-file metadata, so the index survives
+// between sessions. Enabled with the "sqlite" cargo feature.
+use crate::archive::file_meta;
+use crate::data::{PlottableData, SessionStats, read_activity};
+use crate::source::ActivityInfo;
+use chrono::{DateTime, TimeZone, Utc};
+use dashmap::DashMap;
+use rayon::prelude::*;
+use rusqlite::types::{ToSql, ValueRef};
+use rusqlite::{Connection, params};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// Schema migrations, applied in order. The number of applied migrations is
+// kept in the database's user_version pragma; append, never edit.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema.
+    "CREATE TABLE files (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL UNIQUE,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        indexed_at INTEGER NOT NULL
+    );
+    CREATE TABLE activities (
+        id INTEGER PRIMARY KEY,
+        file_id INTEGER NOT NULL UNIQUE REFERENCES files(id) ON DELETE CASCADE,
+        start_time INTEGER NOT NULL,
+        sport TEXT NOT NULL DEFAULT '',
+        device TEXT NOT NULL DEFAULT ''
+    );
+    CREATE INDEX activities_start_time ON activities(start_time);
+    CREATE TABLE sessions (
+        activity_id INTEGER PRIMARY KEY REFERENCES activities(id) ON DELETE CASCADE,
+        distance REAL NOT NULL,
+        calories INTEGER NOT NULL,
+        duration REAL NOT NULL,
+        enhanced_speed REAL NOT NULL,
+        ascent INTEGER NOT NULL,
+        descent INTEGER NOT NULL
+    );
+    CREATE TABLE laps (
+        activity_id INTEGER NOT NULL REFERENCES activities(id) ON DELETE CASCADE,
+        lap_index INTEGER NOT NULL,
+        start_time INTEGER NOT NULL,
+        distance REAL NOT NULL,
+        elapsed_time REAL NOT NULL,
+        timer_time REAL NOT NULL,
+        avg_speed REAL NOT NULL,
+        avg_heart_rate INTEGER,
+        max_heart_rate INTEGER,
+        ascent INTEGER,
+        descent INTEGER,
+        intensity TEXT NOT NULL DEFAULT '',
+        PRIMARY KEY (activity_id, lap_index)
+    );
+    CREATE TABLE tags (
+        activity_id INTEGER NOT NULL REFERENCES activities(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (activity_id, tag)
+    );",
+    // 2: moving time. Clearing the modification times makes the next
+    // sync_index parse the files indexed before it was read again.
+    "ALTER TABLE sessions ADD COLUMN moving_time REAL NOT NULL DEFAULT 0;
+    UPDATE files SET modified = -1;",
+    // 3: tags are kept in the library sidecar only; the copy here was lost
+    // whenever a file was parsed again.
+    "DROP TABLE tags;",
+];
+
+// Filter for Store::query. Unset fields do not restrict the result.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityQuery {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    // Exact sport, case-insensitive, e.g. "running".
+    pub sport: Option<String>,
+    // Substring of the device name, case-insensitive.
+    pub device: Option<String>,
+    // Minimum distance in meters.
+    pub min_distance: Option<f64>,
+}
+
+impl ActivityQuery {
+    pub fn range(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self.end = Some(end);
+        self
+    }
+    pub fn sport(mut self, sport: &str) -> Self {
+        self.sport = Some(sport.to_string());
+        self
+    }
+    pub fn device(mut self, device: &str) -> Self {
+        self.device = Some(device.to_string());
+        self
+    }
+    pub fn min_distance(mut self, meters: f64) -> Self {
+        self.min_distance = Some(meters);
+        self
+    }
+}
+
+// One activity returned by Store::query.
+#[derive(Debug, Clone)]
+pub struct ActivityRecord {
+    pub path: PathBuf,
+    pub timestamp: DateTime<Utc>,
+    pub info: ActivityInfo,
+    pub stats: SessionStats,
+}
+
+impl ActivityRecord {
+    pub fn plottable(&self) -> PlottableData {
+        PlottableData {
+            timestamp: self.timestamp,
+            stats: self.stats,
+        }
+    }
+}
+
+// Column names and rows (rendered as text) of an ad-hoc SQL statement.
+pub type SqlTable = (Vec<String>, Vec<Vec<String>>);
+
+pub struct Store {
+    conn: Connection,
+}
+
+fn from_unix(secs: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(secs, 0).single().unwrap_or_default()
+}
+
+impl Store {
+    // Open (or create) the database at path and bring its schema up to date.
+    pub fn open(path: &Path) -> rusqlite::Result<Store> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> rusqlite::Result<Store> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> rusqlite::Result<Store> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let tx = conn.transaction()?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+        }
+        tx.commit()?;
+        Ok(Store { conn })
+    }
+
+    // Size and modification time of every stored file when it was indexed.
+    fn known_files(&self) -> rusqlite::Result<HashMap<String, (i64, i64)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, size, modified FROM files")?;
+        stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+            .collect()
+    }
+
+    // The stored activities of the given files. Files not indexed yet, or
+    // changed since, are left out for the caller to read.
+    pub fn current_records(&self, paths: &[PathBuf]) -> rusqlite::Result<Vec<ActivityRecord>> {
+        let known = self.known_files()?;
+        let current: HashSet<&PathBuf> = paths
+            .iter()
+            .filter(|path| {
+                let meta = file_meta(path);
+                meta.is_some() && known.get(path.to_string_lossy().as_ref()) == meta.as_ref()
+            })
+            .collect();
+        Ok(self
+            .query(&ActivityQuery::default())?
+            .into_iter()
+            .filter(|record| current.contains(&record.path))
+            .collect())
+    }
+
+    // Add new or changed files of the index to the store. Files whose size
+    // and modification time are unchanged are not parsed again. Returns the
+    // number of activities (re)parsed.
+    pub fn sync_index(
+        &mut self,
+        index: &DashMap<DateTime<Utc>, PathBuf>,
+    ) -> rusqlite::Result<usize> {
+        let known = self.known_files()?;
+        let pending: Vec<(DateTime<Utc>, PathBuf, (i64, i64))> = index
+            .iter()
+            .filter_map(|entry| {
+                let meta = file_meta(entry.value())?;
+                let path_str = entry.value().to_string_lossy();
+                (known.get(path_str.as_ref()) != Some(&meta))
+                    .then(|| (*entry.key(), entry.value().clone(), meta))
+            })
+            .collect();
+
+        // Parsing is the slow part; do it in parallel before writing, each
+        // file once. Files that cannot be read (e.g. still being copied) are
+        // left out, so the next sync tries them again.
+        let parsed: Vec<_> = pending
+            .into_par_iter()
+            .filter_map(|(ts, path, meta)| {
+                let activity = read_activity(&path).ok()?;
+                Some((ts, path, meta, activity.stats, activity.info, activity.laps))
+            })
+            .collect();
+
+        let now = Utc::now().timestamp();
+        let tx = self.conn.transaction()?;
+        for (ts, path, (size, modified), stats, info, laps) in &parsed {
+            let path_str = path.to_string_lossy();
+            tx.execute("DELETE FROM files WHERE path = ?1", params![path_str])?;
+            tx.execute(
+                "INSERT INTO files (path, size, modified, indexed_at) VALUES (?1, ?2, ?3, ?4)",
+                params![path_str, size, modified, now],
+            )?;
+            let file_id = tx.last_insert_rowid();
+            tx.execute(
+                "INSERT INTO activities (file_id, start_time, sport, device) VALUES (?1, ?2, ?3, ?4)",
+                params![file_id, ts.timestamp(), info.sport, info.device],
+            )?;
+            let activity_id = tx.last_insert_rowid();
+            tx.execute(
+                "INSERT INTO sessions (activity_id, distance, calories, duration, enhanced_speed, ascent, descent,
+                                       moving_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    activity_id,
+                    stats.distance,
+                    stats.calories,
+                    stats.duration,
+                    stats.enhanced_speed,
+                    stats.ascent,
+                    stats.descent,
+                    stats.moving_time
+                ],
+            )?;
+            for lap in laps {
+                tx.execute(
+                    "INSERT INTO laps (activity_id, lap_index, start_time, distance, elapsed_time, timer_time,
+                                       avg_speed, avg_heart_rate, max_heart_rate, ascent, descent, intensity)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                    params![
+                        activity_id,
+                        lap.index,
+                        lap.start_time.map(|t| t.timestamp()).unwrap_or_default(),
+                        lap.distance,
+                        lap.elapsed_time,
+                        lap.timer_time,
+                        lap.avg_speed,
+                        lap.avg_heart_rate,
+                        lap.max_heart_rate,
+                        lap.ascent,
+                        lap.descent,
+                        lap.intensity
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(parsed.len())
+    }
+
+    // Remove files (and their activities) that no longer exist on disk.
+    pub fn prune_missing(&mut self) -> rusqlite::Result<usize> {
+        let missing: Vec<String> = {
+            let mut stmt = self.conn.prepare("SELECT path FROM files")?;
+            stmt.query_map([], |row| row.get::<_, String>(0))?
+                .filter_map(|p| p.ok())
+                .filter(|p| file_meta(Path::new(p)).is_none())
+                .collect()
+        };
+        let tx = self.conn.transaction()?;
+        for path in &missing {
+            tx.execute("DELETE FROM files WHERE path = ?1", params![path])?;
+        }
+        tx.commit()?;
+        Ok(missing.len())
+    }
+
+    // Rebuild the timestamp to file index held in memory by the GUI, for
+    // the files of one library directory.
+    pub fn load_index(&self, library: &Path) -> rusqlite::Result<DashMap<DateTime<Utc>, PathBuf>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT a.start_time, f.path FROM activities a JOIN files f ON f.id = a.file_id",
+        )?;
+        let map = DashMap::new();
+        for row in stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })? {
+            let (ts, path) = row?;
+            let path = PathBuf::from(path);
+            if path.starts_with(library) {
+                map.insert(from_unix(ts), path);
+            }
+        }
+        Ok(map)
+    }
+
+    // Return the activities matching the query, oldest first.
+    pub fn query(&self, query: &ActivityQuery) -> rusqlite::Result<Vec<ActivityRecord>> {
+        let mut sql = String::from(
+            "SELECT f.path, a.start_time, a.sport, a.device,
+                    s.distance, s.calories, s.duration, s.enhanced_speed, s.ascent, s.descent,
+                    CASE WHEN s.moving_time > 0 THEN s.moving_time ELSE s.duration END
+             FROM activities a
+             JOIN files f ON f.id = a.file_id
+             JOIN sessions s ON s.activity_id = a.id
+             WHERE 1 = 1",
+        );
+        let mut args: Vec<Box<dyn ToSql>> = Vec::new();
+        if let Some(start) = query.start {
+            sql.push_str(" AND a.start_time >= ?");
+            args.push(Box::new(start.timestamp()));
+        }
+        if let Some(end) = query.end {
+            sql.push_str(" AND a.start_time <= ?");
+            args.push(Box::new(end.timestamp()));
+        }
+        if let Some(sport) = &query.sport {
+            sql.push_str(" AND lower(a.sport) = lower(?)");
+            args.push(Box::new(sport.clone()));
+        }
+        if let Some(device) = &query.device {
+            sql.push_str(" AND a.device LIKE '%' || ? || '%'");
+            args.push(Box::new(device.clone()));
+        }
+        if let Some(min_distance) = query.min_distance {
+            sql.push_str(" AND s.distance >= ?");
+            args.push(Box::new(min_distance));
+        }
+        sql.push_str(" ORDER BY a.start_time");
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args.iter()), |row| {
+            Ok(ActivityRecord {
+                path: PathBuf::from(row.get::<_, String>(0)?),
+                timestamp: from_unix(row.get(1)?),
+                info: ActivityInfo {
+                    sport: row.get(2)?,
+                    device: row.get(3)?,
+                },
+                stats: SessionStats {
+                    distance: row.get(4)?,
+                    calories: row.get(5)?,
+                    duration: row.get(6)?,
+                    enhanced_speed: row.get(7)?,
+                    ascent: row.get(8)?,
+                    descent: row.get(9)?,
+                    moving_time: row.get(10)?,
+                },
+            })
+        })?;
+        rows.collect()
+    }
+
+    // Run an arbitrary SQL statement for power users and return its result
+    // rendered as text.
+    pub fn execute_sql(&self, sql: &str) -> rusqlite::Result<SqlTable> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt.query([])?;
+        let mut table = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut cells = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                cells.push(match row.get_ref(i)? {
+                    ValueRef::Null => String::new(),
+                    ValueRef::Integer(v) => v.to_string(),
+                    ValueRef::Real(v) => v.to_string(),
+                    ValueRef::Text(v) | ValueRef::Blob(v) => String::from_utf8_lossy(v).to_string(),
+                });
+            }
+            table.push(cells);
+        }
+        Ok((columns, table))
+    }
+}
@@ -0,0 +1,207 @@
+// Module for reading activities stored in the Garmin Training Center format (TCX).
+use crate::data::SessionStats;
+use crate::laps::Lap;
+use crate::records::{Record, records_from_track};
+use crate::source::{
+    ActivityInfo, ActivitySource, BoxError, ParsedActivity, TrackPoint, parse_xml_time,
+    stats_from_track,
+};
+use chrono::{DateTime, Utc};
+use roxmltree::Node;
+use std::io::Read;
+
+pub struct TcxSource;
+
+// Text of the first direct child element with the given name.
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|c| c.has_tag_name(name))
+        .and_then(|c| c.text())
+}
+
+fn child_f64(node: Node, name: &str) -> Option<f64> {
+    child_text(node, name).and_then(|v| v.trim().parse().ok())
+}
+
+fn read_document(reader: &mut dyn Read) -> Result<String, BoxError> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+// Parse the track points of all laps in a TCX document.
+fn track_points(doc: &roxmltree::Document) -> Vec<TrackPoint> {
+    track_points_under(doc.root())
+}
+
+fn track_points_under(node: Node) -> Vec<TrackPoint> {
+    node.descendants()
+        .filter(|n| n.has_tag_name("Trackpoint"))
+        .map(|n| {
+            let position = n.children().find(|c| c.has_tag_name("Position"));
+            let extension = |name: &str| {
+                n.descendants()
+                    .find(|c| c.has_tag_name(name))
+                    .and_then(|c| c.text())
+                    .and_then(|v| v.trim().parse().ok())
+            };
+            TrackPoint {
+                time: child_text(n, "Time").and_then(parse_xml_time),
+                lat: position.and_then(|p| child_f64(p, "LatitudeDegrees")),
+                lon: position.and_then(|p| child_f64(p, "LongitudeDegrees")),
+                elevation: child_f64(n, "AltitudeMeters"),
+                distance: child_f64(n, "DistanceMeters"),
+                heart_rate: n
+                    .children()
+                    .find(|c| c.has_tag_name("HeartRateBpm"))
+                    .and_then(|c| child_f64(c, "Value")),
+                cadence: child_f64(n, "Cadence").or_else(|| extension("RunCadence")),
+                // Power lives in the ActivityExtension (TPX) element.
+                power: extension("Watts"),
+            }
+        })
+        .collect()
+}
+
+// The session totals of a parsed TCX document with the given track points.
+fn tcx_session_stats_of(doc: &roxmltree::Document, points: &[TrackPoint]) -> SessionStats {
+    let mut stats = stats_from_track(points);
+
+    // Lap totals, when present, are what the device reported.
+    let laps: Vec<Node> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("Lap"))
+        .collect();
+    let lap_distance: f64 = laps
+        .iter()
+        .filter_map(|l| child_f64(*l, "DistanceMeters"))
+        .sum();
+    let lap_time: f64 = laps
+        .iter()
+        .filter_map(|l| child_f64(*l, "TotalTimeSeconds"))
+        .sum();
+    let lap_calories: f64 = laps.iter().filter_map(|l| child_f64(*l, "Calories")).sum();
+    if lap_distance > 0.0 {
+        stats.distance = lap_distance;
+    }
+    // The lap times are timer times, stops excluded.
+    if lap_time > 0.0 {
+        stats.moving_time = lap_time;
+    }
+    if stats.duration < stats.moving_time {
+        stats.duration = stats.moving_time;
+    }
+    if stats.moving_time > 0.0 {
+        stats.enhanced_speed = stats.distance / stats.moving_time;
+    }
+    stats.calories = lap_calories.round().min(u16::MAX as f64) as u16;
+    stats
+}
+
+// The laps of a parsed TCX document, in order.
+fn tcx_laps_of(doc: &roxmltree::Document) -> Vec<Lap> {
+    let heart_rate = |lap: Node, name: &str| {
+        lap.children()
+            .find(|c| c.has_tag_name(name))
+            .and_then(|c| child_f64(c, "Value"))
+            .map(|v| v as u8)
+    };
+    doc.descendants()
+        .filter(|n| n.has_tag_name("Lap"))
+        .enumerate()
+        .map(|(index, lap)| {
+            // Elevation changes come from the lap's own track points.
+            let points: Vec<TrackPoint> = track_points_under(lap);
+            let track = stats_from_track(&points);
+            let time = child_f64(lap, "TotalTimeSeconds").unwrap_or(track.duration);
+            let distance = child_f64(lap, "DistanceMeters").unwrap_or(track.distance);
+            Lap {
+                index,
+                start_time: lap.attribute("StartTime").and_then(parse_xml_time),
+                distance,
+                elapsed_time: time,
+                timer_time: time,
+                avg_speed: if time > 0.0 { distance / time } else { 0.0 },
+                avg_heart_rate: heart_rate(lap, "AverageHeartRateBpm"),
+                max_heart_rate: heart_rate(lap, "MaximumHeartRateBpm"),
+                ascent: Some(track.ascent),
+                descent: Some(track.descent),
+                intensity: child_text(lap, "Intensity")
+                    .unwrap_or_default()
+                    .to_lowercase(),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+// The sport and recording device of a parsed TCX document.
+fn tcx_activity_info_of(doc: &roxmltree::Document) -> ActivityInfo {
+    let activity = doc.descendants().find(|n| n.has_tag_name("Activity"));
+    let device = activity
+        .and_then(|n| n.children().find(|c| c.has_tag_name("Creator")))
+        .and_then(|n| child_text(n, "Name"))
+        .unwrap_or_default();
+    ActivityInfo {
+        sport: activity
+            .and_then(|n| n.attribute("Sport"))
+            .unwrap_or_default()
+            .to_lowercase(),
+        device: device.trim().to_string(),
+    }
+}
+
+impl ActivitySource for TcxSource {
+    fn start_time(&self, reader: &mut dyn Read) -> Result<DateTime<Utc>, BoxError> {
+        let text = read_document(reader)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        // The activity Id is its start time; laps carry a StartTime attribute.
+        doc.descendants()
+            .find(|n| n.has_tag_name("Activity"))
+            .and_then(|n| child_text(n, "Id"))
+            .and_then(parse_xml_time)
+            .or_else(|| {
+                doc.descendants()
+                    .filter(|n| n.has_tag_name("Lap"))
+                    .find_map(|n| n.attribute("StartTime").and_then(parse_xml_time))
+            })
+            .ok_or_else(|| "Timestamp not found".into())
+    }
+
+    fn session_stats(&self, reader: &mut dyn Read) -> Result<SessionStats, BoxError> {
+        let text = read_document(reader)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        Ok(tcx_session_stats_of(&doc, &track_points(&doc)))
+    }
+
+    fn laps(&self, reader: &mut dyn Read) -> Result<Vec<Lap>, BoxError> {
+        let text = read_document(reader)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        Ok(tcx_laps_of(&doc))
+    }
+
+    fn records(&self, reader: &mut dyn Read) -> Result<Vec<Record>, BoxError> {
+        let text = read_document(reader)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        Ok(records_from_track(&track_points(&doc)))
+    }
+
+    fn activity_info(&self, reader: &mut dyn Read) -> Result<ActivityInfo, BoxError> {
+        let text = read_document(reader)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        Ok(tcx_activity_info_of(&doc))
+    }
+
+    fn parse(&self, reader: &mut dyn Read) -> Result<ParsedActivity, BoxError> {
+        let text = read_document(reader)?;
+        let doc = roxmltree::Document::parse(&text)?;
+        let points = track_points(&doc);
+        Ok(ParsedActivity {
+            stats: tcx_session_stats_of(&doc, &points),
+            info: tcx_activity_info_of(&doc),
+            laps: tcx_laps_of(&doc),
+            records: records_from_track(&points),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,201 @@
+// Module for chart overlays: moving averages and trend lines of a metric
+// over the activities of a range.
+use chrono::{DateTime, Duration, Utc};
+
+// Average length of a month in days, for slopes per month.
+pub const DAYS_PER_MONTH: f64 = 30.44;
+
+// Share of the points each LOESS fit uses.
+const LOESS_SPAN: f64 = 0.5;
+
+// Days from the first activity, the x axis of the fits.
+fn days_since(first: DateTime<Utc>, t: DateTime<Utc>) -> f64 {
+    (t - first).num_seconds() as f64 / 86400.0
+}
+
+// Mean of each activity and the n - 1 activities before it.
+pub fn moving_average_by_count(
+    values: &[(DateTime<Utc>, f64)],
+    n: usize,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let n = n.max(1);
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, (t, _))| {
+            let window = &values[(i + 1).saturating_sub(n)..=i];
+            let mean = window.iter().map(|(_, v)| v).sum::<f64>() / window.len() as f64;
+            (*t, mean)
+        })
+        .collect()
+}
+
+// Mean of the activities of the n days up to and including each activity.
+pub fn moving_average_by_days(
+    values: &[(DateTime<Utc>, f64)],
+    days: u32,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let span = Duration::days(days.max(1) as i64);
+    values
+        .iter()
+        .map(|(t, _)| {
+            let window: Vec<f64> = values
+                .iter()
+                .filter(|(u, _)| *u <= *t && *t - *u < span)
+                .map(|(_, v)| *v)
+                .collect();
+            (*t, window.iter().sum::<f64>() / window.len() as f64)
+        })
+        .collect()
+}
+
+// Least squares line through the values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearTrend {
+    pub first: DateTime<Utc>,
+    pub intercept: f64,
+    // Change per day.
+    pub slope: f64,
+}
+
+impl LinearTrend {
+    pub fn value_at(&self, t: DateTime<Utc>) -> f64 {
+        self.intercept + self.slope * days_since(self.first, t)
+    }
+
+    pub fn slope_per_month(&self) -> f64 {
+        self.slope * DAYS_PER_MONTH
+    }
+}
+
+// None with fewer than two activities or when they all fall on one instant.
+pub fn linear_trend(values: &[(DateTime<Utc>, f64)]) -> Option<LinearTrend> {
+    let first = values.first()?.0;
+    let points: Vec<(f64, f64)> = values
+        .iter()
+        .map(|(t, v)| (days_since(first, *t), *v))
+        .collect();
+    let (intercept, slope) = weighted_fit(&points, &vec![1.0; points.len()])?;
+    Some(LinearTrend {
+        first,
+        intercept,
+        slope,
+    })
+}
+
+// Weighted least squares line, as (intercept, slope).
+fn weighted_fit(points: &[(f64, f64)], weights: &[f64]) -> Option<(f64, f64)> {
+    let total: f64 = weights.iter().sum();
+    if points.len() < 2 || total <= 0.0 {
+        return None;
+    }
+    let mean = |f: fn(&(f64, f64)) -> f64| {
+        points
+            .iter()
+            .zip(weights)
+            .map(|(p, w)| f(p) * w)
+            .sum::<f64>()
+            / total
+    };
+    let (mx, my) = (mean(|p| p.0), mean(|p| p.1));
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for ((x, y), w) in points.iter().zip(weights) {
+        sxy += w * (x - mx) * (y - my);
+        sxx += w * (x - mx) * (x - mx);
+    }
+    if sxx <= f64::EPSILON {
+        return None;
+    }
+    let slope = sxy / sxx;
+    Some((my - slope * mx, slope))
+}
+
+// Locally weighted linear regression (LOESS) evaluated at each activity,
+// with tricube weights over the nearest LOESS_SPAN of the activities.
+pub fn loess(values: &[(DateTime<Utc>, f64)]) -> Vec<(DateTime<Utc>, f64)> {
+    let Some(first) = values.first().map(|(t, _)| *t) else {
+        return Vec::new();
+    };
+    if values.len() < 3 {
+        return values.to_vec();
+    }
+    let points: Vec<(f64, f64)> = values
+        .iter()
+        .map(|(t, v)| (days_since(first, *t), *v))
+        .collect();
+    let neighbours = ((points.len() as f64 * LOESS_SPAN).ceil() as usize).clamp(2, points.len());
+    values
+        .iter()
+        .zip(&points)
+        .map(|((t, v), (x, _))| {
+            let mut distances: Vec<f64> = points.iter().map(|(px, _)| (px - x).abs()).collect();
+            distances.sort_by(f64::total_cmp);
+            let radius = distances[neighbours - 1].max(f64::EPSILON) * 1.0001;
+            let weights: Vec<f64> = points
+                .iter()
+                .map(|(px, _)| {
+                    let d = ((px - x).abs() / radius).min(1.0);
+                    (1.0 - d * d * d).powi(3)
+                })
+                .collect();
+            let fitted = match weighted_fit(&points, &weights) {
+                Some((intercept, slope)) => intercept + slope * x,
+                None => *v,
+            };
+            (*t, fitted)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(n: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 8, 0, 0).unwrap() + Duration::days(n)
+    }
+
+    #[test]
+    fn linear_trend_slope_per_day() {
+        let values = [(day(0), 10.0), (day(1), 12.0), (day(2), 14.0)];
+        let trend = linear_trend(&values).unwrap();
+        assert!((trend.slope - 2.0).abs() < 1e-9);
+        assert!((trend.intercept - 10.0).abs() < 1e-9);
+        assert!((trend.value_at(day(5)) - 20.0).abs() < 1e-9);
+        assert!((trend.slope_per_month() - 2.0 * DAYS_PER_MONTH).abs() < 1e-9);
+    }
+
+    #[test]
+    fn linear_trend_needs_two_instants() {
+        assert!(linear_trend(&[]).is_none());
+        assert!(linear_trend(&[(day(0), 1.0)]).is_none());
+        assert!(linear_trend(&[(day(0), 1.0), (day(0), 3.0)]).is_none());
+    }
+
+    #[test]
+    fn loess_keeps_fewer_than_three_points() {
+        assert!(loess(&[]).is_empty());
+        let values = [(day(0), 1.0), (day(3), 5.0)];
+        assert_eq!(loess(&values), values.to_vec());
+    }
+
+    #[test]
+    fn loess_follows_a_line() {
+        let values: Vec<(DateTime<Utc>, f64)> =
+            (0..8).map(|n| (day(n), 3.0 * n as f64 + 1.0)).collect();
+        for ((_, fitted), (_, value)) in loess(&values).iter().zip(&values) {
+            assert!((fitted - value).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn moving_average_by_count_uses_the_last_n() {
+        let values = [(day(0), 2.0), (day(1), 4.0), (day(2), 9.0)];
+        let average: Vec<f64> = moving_average_by_count(&values, 2)
+            .iter()
+            .map(|(_, v)| *v)
+            .collect();
+        assert_eq!(average, vec![2.0, 3.0, 6.5]);
+    }
+}
@@ -0,0 +1,115 @@
+// Module for the volume report: training totals per week or month of a
+// range and their change from one period to the next, so sudden jumps in
+// load stand out.
+use crate::calendar::WEEK_START;
+use crate::data::PlottableData;
+use chrono::{Datelike, Months, NaiveDate};
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    // Weeks start like the calendar columns and the weekly ranges.
+    Week,
+    Month,
+}
+
+impl Period {
+    // First day of the period holding a date.
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => date.week(WEEK_START).first_day(),
+            Period::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Week => start + chrono::Duration::weeks(1),
+            Period::Month => start + Months::new(1),
+        }
+    }
+}
+
+// Totals of one period, in the units of the stats they were summed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PeriodVolume {
+    pub start: NaiveDate,
+    pub count: usize,
+    pub distance: f64,
+    pub duration: f64,
+    pub ascent: f64,
+    // Distance of the longest activity.
+    pub longest: f64,
+    // Distance change from the previous period in percent; None for the
+    // first period or after one without any distance.
+    pub change: Option<f64>,
+}
+
+impl PeriodVolume {
+    // Whether the distance grew by more than max_increase percent.
+    pub fn is_jump(&self, max_increase: f64) -> bool {
+        self.change.is_some_and(|c| c > max_increase)
+    }
+}
+
+// Sum the activities per period from start to end, empty periods included.
+pub fn period_volumes(
+    stats: &[PlottableData],
+    period: Period,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<PeriodVolume> {
+    let mut volumes = Vec::new();
+    let mut period_start = period.start_of(start);
+    while period_start <= end {
+        volumes.push(PeriodVolume {
+            start: period_start,
+            ..Default::default()
+        });
+        period_start = period.next(period_start);
+    }
+    for item in stats {
+        let first = period.start_of(item.timestamp.date_naive());
+        if let Some(volume) = volumes.iter_mut().find(|v| v.start == first) {
+            volume.count += 1;
+            volume.distance += item.stats.distance;
+            volume.duration += item.stats.duration;
+            volume.ascent += item.stats.ascent as f64;
+            volume.longest = volume.longest.max(item.stats.distance);
+        }
+    }
+    for i in 1..volumes.len() {
+        let previous = volumes[i - 1].distance;
+        if previous > 0.0 {
+            volumes[i].change = Some((volumes[i].distance / previous - 1.0) * 100.0);
+        }
+    }
+    volumes
+}
+
+// Write the report as CSV, one line per period after a header.
+pub fn write_volume_csv(
+    volumes: &[PeriodVolume],
+    max_increase: f64,
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "period_start,activities,distance,duration,ascent,longest,change_percent,jump"
+    )?;
+    for v in volumes {
+        writeln!(
+            out,
+            "{},{},{:.2},{:.1},{:.0},{:.2},{},{}",
+            v.start.format("%Y-%m-%d"),
+            v.count,
+            v.distance,
+            v.duration,
+            v.ascent,
+            v.longest,
+            v.change.map(|c| format!("{:.1}", c)).unwrap_or_default(),
+            v.is_jump(max_increase)
+        )?;
+    }
+    Ok(())
+}
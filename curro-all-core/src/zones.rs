@@ -0,0 +1,380 @@
+// Module for training zones: time spent in each heart rate, pace and power
+// zone, computed from the per-record samples or, for heart rate, taken from
+// the device's own totals.
+use crate::calendar::WEEK_START;
+use crate::data::{Units, cvt_pace, fit_value_f64, read_activity};
+use crate::records::Record;
+use crate::source::BoxError;
+use chrono::{DateTime, NaiveDate, Utc};
+use rayon::prelude::*;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub const ZONE_COUNT: usize = 5;
+
+// Gaps between samples longer than this (seconds) are pauses, not time in a zone.
+const MAX_SAMPLE_GAP: f64 = 10.0;
+
+// Lower bound of zones 2 to 5, as a share of maximum heart rate.
+const MAX_HR_ZONE_SHARES: [f64; ZONE_COUNT - 1] = [0.70, 0.80, 0.87, 0.93];
+// Lower bound of zones 2 to 5, as a share of lactate threshold heart rate.
+const THRESHOLD_HR_ZONE_SHARES: [f64; ZONE_COUNT - 1] = [0.85, 0.90, 0.95, 1.00];
+
+// Lower bound of pace zones 2 to 5, as speed relative to threshold pace
+// (threshold pace / pace): slower than 129%, 114%, 106% and 99% of it.
+const PACE_ZONE_SHARES: [f64; ZONE_COUNT - 1] = [1.0 / 1.29, 1.0 / 1.14, 1.0 / 1.06, 1.0 / 0.99];
+// Lower bound of power zones 2 to 5, as a share of functional threshold
+// power. Zone 5 covers everything above threshold.
+const POWER_ZONE_SHARES: [f64; ZONE_COUNT - 1] = [0.56, 0.76, 0.91, 1.06];
+
+// Seconds spent in each zone, zone 1 first.
+pub type ZoneTimes = [f64; ZONE_COUNT];
+
+// Time in zone of one activity, for each kind of zone with data.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActivityZones {
+    pub hr: Option<ZoneTimes>,
+    pub pace: Option<ZoneTimes>,
+    pub power: Option<ZoneTimes>,
+}
+
+// How all zones are determined. Pace and power zones are skipped when
+// their threshold is not configured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneSettings {
+    pub hr: HrZoneSettings,
+    pub pace: Option<PaceZoneSettings>,
+    // Functional threshold power, watts.
+    pub ftp: Option<f64>,
+}
+
+// Threshold pace in the units the user works in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaceZoneSettings {
+    // Decimal minutes per km or mile, as returned by cvt_pace.
+    pub threshold_pace: f64,
+    pub units: Units,
+}
+
+impl PaceZoneSettings {
+    // Intensity of a speed (m/s) relative to threshold pace; 1.0 at threshold.
+    // Standing still has no pace and no zone.
+    pub fn intensity(&self, speed: f64) -> Option<f64> {
+        if speed < 1.0 || self.threshold_pace <= 0.0 {
+            return None;
+        }
+        Some(self.threshold_pace / cvt_pace(speed as f32, &self.units) as f64)
+    }
+}
+
+// How heart rate zones are determined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HrZoneSettings {
+    // bpm
+    pub max_hr: f64,
+    // Lactate threshold heart rate (bpm); when set it defines the zones
+    // instead of the maximum.
+    pub threshold_hr: Option<f64>,
+    // Use the time in zone the device recorded, when the file has it.
+    pub prefer_device: bool,
+}
+
+impl HrZoneSettings {
+    // Lower bounds (bpm) of zones 2 to 5.
+    pub fn bounds(&self) -> [f64; ZONE_COUNT - 1] {
+        match self.threshold_hr {
+            Some(threshold) => THRESHOLD_HR_ZONE_SHARES.map(|s| s * threshold),
+            None => MAX_HR_ZONE_SHARES.map(|s| s * self.max_hr),
+        }
+    }
+}
+
+// Zone (0-based) of a value given the lower bounds of the zones above the first.
+pub fn zone_of(value: f64, bounds: &[f64]) -> usize {
+    bounds.iter().take_while(|&&b| value >= b).count()
+}
+
+// Sum the time between consecutive records into the zone of the earlier
+// record's value. Records without a value, and pauses, are skipped.
+pub fn time_in_zones(
+    records: &[Record],
+    bounds: &[f64],
+    value: impl Fn(&Record) -> Option<f64>,
+) -> Vec<f64> {
+    let mut times = vec![0.0; bounds.len() + 1];
+    for pair in records.windows(2) {
+        let dt = pair[1].elapsed - pair[0].elapsed;
+        if dt <= 0.0 || dt > MAX_SAMPLE_GAP {
+            continue;
+        }
+        if let Some(v) = value(&pair[0]) {
+            times[zone_of(v, bounds)] += dt;
+        }
+    }
+    times
+}
+
+// Read the session time in heart rate zone recorded by a FIT device, from
+// the Session message or a TimeInZone message referring to the session.
+pub fn fit_time_in_hr_zone(mut reader: &mut dyn Read) -> Result<Option<Vec<f64>>, BoxError> {
+    Ok(fit_time_in_hr_zone_of(&fitparser::from_reader(
+        &mut reader,
+    )?))
+}
+
+// Same as fit_time_in_hr_zone, for messages already decoded.
+pub fn fit_time_in_hr_zone_of(messages: &[fitparser::FitDataRecord]) -> Option<Vec<f64>> {
+    use fitparser::profile::field_types::MesgNum;
+    for message in messages {
+        let is_session = match message.kind() {
+            MesgNum::Session => true,
+            MesgNum::TimeInZone => message
+                .fields()
+                .iter()
+                .any(|f| f.name() == "reference_mesg" && f.value().to_string() == "session"),
+            _ => false,
+        };
+        if !is_session {
+            continue;
+        }
+        for field in message.fields() {
+            if field.name() == "time_in_hr_zone"
+                && let fitparser::Value::Array(values) = field.value()
+            {
+                let times: Vec<f64> = values.iter().filter_map(fit_value_f64).collect();
+                if times.iter().sum::<f64>() > 0.0 {
+                    return Some(times);
+                }
+            }
+        }
+    }
+    None
+}
+
+// Fit device zone times into ZONE_COUNT zones. Devices usually report a
+// zone 0 (below zone 1) first; it is counted as zone 1.
+fn fold_device_zones(times: &[f64]) -> ZoneTimes {
+    let mut zones = [0.0; ZONE_COUNT];
+    let extra = times.len().saturating_sub(ZONE_COUNT);
+    for (i, t) in times.iter().enumerate() {
+        zones[i.saturating_sub(extra).min(ZONE_COUNT - 1)] += t;
+    }
+    zones
+}
+
+// Convert zone times computed over ZONE_COUNT zones, None if all are zero.
+fn zone_times(times: Vec<f64>) -> Option<ZoneTimes> {
+    if times.iter().sum::<f64>() == 0.0 {
+        return None;
+    }
+    let mut zones = [0.0; ZONE_COUNT];
+    zones.copy_from_slice(&times);
+    Some(zones)
+}
+
+// Time in zone of one activity for each kind of zone it has data for.
+pub fn activity_zones(path: &Path, settings: &ZoneSettings) -> ActivityZones {
+    match read_activity(path) {
+        Ok(activity) => zones_of(
+            &activity.records,
+            activity.device_hr_zones.as_deref(),
+            settings,
+        ),
+        Err(_) => ActivityZones::default(),
+    }
+}
+
+// Time in zone from the records of an activity and the heart rate zone
+// times its device recorded, if any.
+pub fn zones_of(
+    records: &[Record],
+    device_hr_zones: Option<&[f64]>,
+    settings: &ZoneSettings,
+) -> ActivityZones {
+    let mut zones = ActivityZones::default();
+    if settings.hr.prefer_device
+        && let Some(times) = device_hr_zones
+    {
+        zones.hr = Some(fold_device_zones(times));
+    }
+    if zones.hr.is_none() {
+        zones.hr = zone_times(time_in_zones(records, &settings.hr.bounds(), |r| {
+            r.heart_rate
+        }));
+    }
+    if let Some(pace) = &settings.pace {
+        zones.pace = zone_times(time_in_zones(records, &PACE_ZONE_SHARES, |r| {
+            pace.intensity(r.speed?)
+        }));
+    }
+    if let Some(ftp) = settings.ftp {
+        let bounds = POWER_ZONE_SHARES.map(|s| s * ftp);
+        zones.power = zone_times(time_in_zones(records, &bounds, |r| r.power));
+    }
+    zones
+}
+
+// Time in zone of every activity, in date order.
+pub fn collect_zones(
+    results: &[(DateTime<Utc>, PathBuf)],
+    settings: &ZoneSettings,
+) -> Vec<(DateTime<Utc>, ActivityZones)> {
+    let mut zones: Vec<(DateTime<Utc>, ActivityZones)> = results
+        .par_iter()
+        .map(|(timestamp, path)| (*timestamp, activity_zones(path, settings)))
+        .collect();
+    zones.sort_by_key(|(timestamp, _)| *timestamp);
+    zones
+}
+
+// One kind of zone times of the activities that have it.
+pub fn zone_series(
+    zones: &[(DateTime<Utc>, ActivityZones)],
+    kind: impl Fn(&ActivityZones) -> Option<ZoneTimes>,
+) -> Vec<(DateTime<Utc>, ZoneTimes)> {
+    zones
+        .iter()
+        .filter_map(|(timestamp, z)| Some((*timestamp, kind(z)?)))
+        .collect()
+}
+
+// Sum zone times per week, weeks starting like the calendar columns and
+// the weekly ranges.
+pub fn zones_by_week<const N: usize>(
+    zones: &[(DateTime<Utc>, [f64; N])],
+) -> Vec<(NaiveDate, [f64; N])> {
+    let mut weeks: Vec<(NaiveDate, [f64; N])> = Vec::new();
+    for (timestamp, times) in zones {
+        let week = timestamp.date_naive().week(WEEK_START).first_day();
+        match weeks.iter_mut().find(|(w, _)| *w == week) {
+            Some((_, total)) => total.iter_mut().zip(times).for_each(|(a, b)| *a += b),
+            None => weeks.push((week, *times)),
+        }
+    }
+    weeks.sort_by_key(|(week, _)| *week);
+    weeks
+}
+
+// Sum zone times over all activities.
+pub fn total_zones<const N: usize>(zones: &[(DateTime<Utc>, [f64; N])]) -> [f64; N] {
+    let mut total = [0.0; N];
+    for (_, times) in zones {
+        total.iter_mut().zip(times).for_each(|(a, b)| *a += b);
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(elapsed: f64, heart_rate: Option<f64>) -> Record {
+        Record {
+            elapsed,
+            heart_rate,
+            ..Default::default()
+        }
+    }
+
+    fn settings(prefer_device: bool) -> ZoneSettings {
+        ZoneSettings {
+            hr: HrZoneSettings {
+                max_hr: 200.0,
+                threshold_hr: None,
+                prefer_device,
+            },
+            pace: None,
+            ftp: None,
+        }
+    }
+
+    #[test]
+    fn time_in_zones_skips_pauses_and_missing_values() {
+        let records = [
+            record(0.0, Some(100.0)),
+            record(5.0, Some(150.0)),
+            record(10.0, None),
+            record(15.0, Some(170.0)),
+            // 20 s later: a pause.
+            record(35.0, Some(120.0)),
+            record(40.0, Some(120.0)),
+        ];
+        let times = time_in_zones(&records, &[130.0, 160.0], |r| r.heart_rate);
+        assert_eq!(times, vec![10.0, 5.0, 0.0]);
+    }
+
+    #[test]
+    fn device_zones_fold_the_zones_below_zone_one() {
+        assert_eq!(
+            fold_device_zones(&[60.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+            [61.0, 2.0, 3.0, 4.0, 5.0]
+        );
+        assert_eq!(
+            fold_device_zones(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]),
+            [6.0, 4.0, 5.0, 6.0, 7.0]
+        );
+        assert_eq!(
+            fold_device_zones(&[1.0, 2.0, 3.0, 4.0, 5.0]),
+            [1.0, 2.0, 3.0, 4.0, 5.0]
+        );
+    }
+
+    #[test]
+    fn device_zones_are_used_only_when_preferred() {
+        let records = [record(0.0, Some(150.0)), record(10.0, Some(150.0))];
+        let device = [0.0, 30.0, 0.0, 0.0, 0.0, 0.0];
+        let zones = zones_of(&records, Some(&device), &settings(true));
+        assert_eq!(zones.hr, Some([30.0, 0.0, 0.0, 0.0, 0.0]));
+        // 150 bpm is 75% of the maximum: zone 2.
+        let zones = zones_of(&records, Some(&device), &settings(false));
+        assert_eq!(zones.hr, Some([0.0, 10.0, 0.0, 0.0, 0.0]));
+        assert_eq!(zones_of(&[], None, &settings(true)).hr, None);
+    }
+
+    #[test]
+    fn zones_add_up_by_week() {
+        // 2026-03-01 is a Sunday, the first day of a calendar week.
+        let day = |d| Utc.with_ymd_and_hms(2026, 3, d, 8, 0, 0).unwrap();
+        let zones = [
+            (day(8), [1.0, 2.0]),
+            (day(1), [10.0, 20.0]),
+            (day(7), [100.0, 200.0]),
+        ];
+        let weeks = zones_by_week(&zones);
+        let week = |d| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        assert_eq!(
+            weeks,
+            vec![(week(1), [110.0, 220.0]), (week(8), [1.0, 2.0])]
+        );
+        assert_eq!(total_zones(&zones), [111.0, 222.0]);
+    }
+
+    #[test]
+    fn pace_intensity_is_relative_to_threshold_pace() {
+        let metric = PaceZoneSettings {
+            threshold_pace: 4.0,
+            units: Units::Metric,
+        };
+        // 4:00 min/km.
+        let at_threshold = metric.intensity(1000.0 / 240.0).unwrap();
+        assert!((at_threshold - 1.0).abs() < 1e-4);
+        // 5:00 min/km.
+        let easy = metric.intensity(1000.0 / 300.0).unwrap();
+        assert!((easy - 0.8).abs() < 1e-4);
+        assert_eq!(metric.intensity(0.5), None);
+        let unset = PaceZoneSettings {
+            threshold_pace: 0.0,
+            units: Units::Metric,
+        };
+        assert_eq!(unset.intensity(4.0), None);
+
+        let us = PaceZoneSettings {
+            threshold_pace: 8.0,
+            units: Units::US,
+        };
+        // 8:00 min/mile.
+        let at_threshold = us.intensity(1609.344 / 480.0).unwrap();
+        assert!((at_threshold - 1.0).abs() < 1e-4);
+    }
+}
@@ -49,6 +49,7 @@ GRAPH_CAPTION_CALORIES = Calories
 GRAPH_CAPTION_ASCENT = Ascent
 GRAPH_CAPTION_DESCENT = Descent
 GRAPH_CAPTION_DURATION = Duration
+GRAPH_CAPTION_MOVING_TIME = Moving time
 
 LABEL_DATE_TIME = DATE AND TIME
 LABEL_DISTANCE_MILES = Distance (mi)
@@ -62,6 +63,7 @@ LABEL_ELEVATION_M = Elevation (m)
 LABEL_TEMP_F = Temperature (°F)
 LABEL_TEMP_C = Temperature (°C)
 LABEL_DURATION = Duration (min)
+LABEL_MOVING_TIME = Moving (min)
 
 UNIT_FEET = feet
 UNIT_METERS = meters
@@ -98,3 +100,151 @@ SUMMARY_TOTAL_ASC_DES = Total Ascent/Descent
 MAXIMUM = Maximum
 MINIMUM = Minimum
 AVERAGE = Average
+ACTIVITY_COUNT = Activities
+TOTAL = Total
+MEDIAN = Median
+STANDARD_DEVIATION = Std. deviation
+PERCENTILE_10 = 10th percentile
+PERCENTILE_90 = 90th percentile
+
+# Activity detail / laps
+DETAIL_TAB_LAPS = Laps
+LABEL_LAP = Lap
+LABEL_LAP_TYPE = Type
+LABEL_LAP_TIME = Time
+LABEL_HR_AVG = Avg HR
+LABEL_HR_MAX = Max HR
+LAP_WORK = Work
+LAP_RECOVERY = Recovery
+LAP_WARMUP = Warm-up
+LAP_COOLDOWN = Cool-down
+LAP_STEADY = Steady
+SUMMARY_WORK_INTERVALS = Work intervals
+SUMMARY_RECOVERY = Recovery
+MESSAGE_NO_LAPS = No laps recorded.
+TOOLTIP_DATE_BUTTON = Show the laps of this activity
+DETAIL_TAB_CHARTS = Charts
+GRAPH_CAPTION_POWER = Power
+UNIT_MINUTES = minutes
+LABEL_X_AXIS_DISTANCE = By distance
+LABEL_X_AXIS_TIME = By time
+MESSAGE_NO_RECORDS = No samples recorded.
+MESSAGE_NO_GPS = No GPS tracks in the selected range.
+SAVE_BUTTON = Save
+HEATMAP_TOGGLE = Heatmap
+HEATMAP_EXPORT = Export heatmap…
+TOOLTIP_HEATMAP_TOGGLE = Show how often each route in the range was used instead of the individual tracks.
+TOOLTIP_HEATMAP_EXPORT = Save the heatmap of the selected range as a PNG image.
+PRIVACY_PREVIEW_TOGGLE = Privacy zones
+TOOLTIP_PRIVACY_PREVIEW_TOGGLE = Preview the privacy zones and the positions they hide. Hidden positions are never exported.
+UNIT_HOURS = hours
+GRAPH_CAPTION_HR_ZONES = Time in heart rate zone per week
+SUMMARY_HR_ZONES = Time in HR zone
+GRAPH_CAPTION_PACE_ZONES = Time in pace zone
+GRAPH_CAPTION_POWER_ZONES = Time in power zone
+LABEL_PACE_ZONES = Pace zones (%)
+LABEL_POWER_ZONES = Power zones (%)
+GRAPH_VIEW_CHARTS = Charts
+GRAPH_VIEW_CALENDAR = Calendar
+TOOLTIP_GRAPH_VIEW = Show the metric charts or a calendar of daily training.
+CALENDAR_METRIC_DISTANCE = Distance
+CALENDAR_METRIC_DURATION = Duration
+CALENDAR_METRIC_LOAD = Load
+TOOLTIP_CALENDAR_METRIC = Color the calendar days by distance, duration or heart rate training load.
+CALENDAR_SHOW_ALL = Show all days
+TOOLTIP_CALENDAR_SHOW_ALL = Show every activity of the range in the summary again.
+GRAPH_CAPTION_CALENDAR = Training calendar
+WEEKDAY_MON = Mon
+WEEKDAY_WED = Wed
+WEEKDAY_FRI = Fri
+MONTH_JAN = Jan
+MONTH_FEB = Feb
+MONTH_MAR = Mar
+MONTH_APR = Apr
+MONTH_MAY = May
+MONTH_JUN = Jun
+MONTH_JUL = Jul
+MONTH_AUG = Aug
+MONTH_SEP = Sep
+MONTH_OCT = Oct
+MONTH_NOV = Nov
+MONTH_DEC = Dec
+OVERLAY_MOVING_AVERAGE = Moving average
+OVERLAY_TREND = Trend line
+TREND_IMPROVING = improving
+TREND_DECLINING = slowing
+TREND_PER_MONTH = per month
+FILTER_PLACEHOLDER = Filter, e.g. distance > 10, pace < 5:30
+TOOLTIP_FILTER = Show only the activities matching every term: a metric compared with a number (distance, calories, pace, duration, ascent, descent), or text found in the date, title, tags or notes
+FILTER_ERROR = Filter not understood
+COLUMNS_BUTTON = Columns
+TOOLTIP_COLUMNS_BUTTON = Choose the columns shown
+SUMMARY_TAB_ACTIVITIES = Activities
+SUMMARY_TAB_VOLUME = Volume
+VOLUME_PERIOD_WEEK = Weekly
+VOLUME_PERIOD_MONTH = Monthly
+TOOLTIP_VOLUME_PERIOD = Sum the activities per week or per month
+VOLUME_EXPORT = Export CSV
+TOOLTIP_VOLUME_EXPORT = Save the volume report as a CSV file
+VOLUME_PERIOD = Starting
+VOLUME_LONGEST = Longest
+VOLUME_CHANGE = Change
+VOLUME_JUMP_WARNING = Distance grew by more than
+GRAPH_VIEW_FITNESS = Fitness
+GRAPH_CAPTION_VO2MAX = VO2max
+FITNESS_FROM_DEVICE = device
+FITNESS_NO_EFFORTS = No runs with recorded distance and time in this range
+RACE_5K = 5 km
+RACE_10K = 10 km
+RACE_HALF_MARATHON = Half marathon
+RACE_MARATHON = Marathon
+SUMMARY_TAB_GEAR = Gear
+GEAR_NONE = No gear configured. Add [[gear]] entries to the settings file to track shoe and bike mileage.
+GEAR_NAME = Gear
+GEAR_RETIRE_AT = Retire at
+GEAR_USED = Used
+GEAR_NEARLY_WORN_OUT = Nearly worn out
+GEAR_WORN_OUT = Worn out, time to replace it
+GEAR_STATUS_WARNING = Gear to replace soon
+GRAPH_CAPTION_GEAR = Gear distance
+LABEL_TITLE = Title
+LABEL_TAGS = Tags
+LABEL_NOTES = Notes
+LABEL_RPE = RPE
+TOOLTIP_RPE = Rating of perceived exertion, from 1 (very easy) to 10 (maximal); 0 for none
+TAGS_PLACEHOLDER = race, treadmill, sick, with group
+EDIT_ACTIVITY_BUTTON = Edit
+TOOLTIP_EDIT_ACTIVITY = Edit the title, tags, notes and exertion of the selected activity (or double-click a row)
+EDIT_ACTIVITY_TITLE = Edit activity
+TAG_ALL = All tags
+TOOLTIP_TAG_FILTER = Show only the activities with this tag in the charts, the map and the summary
+SIDECAR_SAVE_ERROR = Could not save the activity notes
+LABEL_CALORIES = Calories
+LABEL_ASCENT_METERS = Ascent (m)
+LABEL_ASCENT_FEET = Ascent (ft)
+DELETE_BUTTON = Delete
+MANUAL_START = Start (local time)
+MANUAL_SPORT = Sport
+MANUAL_SPORT_PLACEHOLDER = running, strength, cycling
+MANUAL_DURATION = Duration (h:mm:ss)
+MANUAL_ADD_TITLE = Add manual activity
+MANUAL_EDIT_TITLE = Edit manual activity
+MANUAL_MARK = (manual)
+TOOLTIP_MANUAL_ACTIVITY = Entered by hand; click to edit or delete it
+TOOLTIP_MANUAL_ADD = Add a workout without an activity file (gym session, treadmill run, paper log)
+MANUAL_CONFLICT = Another activity already starts at that time
+LABEL_DESCENT_METERS = Descent (m)
+LABEL_DESCENT_FEET = Descent (ft)
+EDITED_MARK = (edited)
+TOOLTIP_EDITED_ACTIVITY = Some recorded values were corrected; show the laps of this activity
+CORRECT_ACTIVITY_BUTTON = Correct
+TOOLTIP_CORRECT_ACTIVITY = Correct the distance, duration, ascent, descent or sport of the selected activity without changing its file
+CORRECTION_TITLE = Correct activity
+CORRECTION_VALUE = Value
+CORRECTION_RECORDED = Recorded
+CORRECTION_REVERT = Revert
+TOOLTIP_CORRECTION_REVERT = Go back to the values recorded in the file
+ELEVATION_DEVICE = Device elevation
+ELEVATION_DEM = Corrected elevation (DEM)
+TOOLTIP_ELEVATION_SOURCE = Ascent and descent as recorded by the device, or recomputed from the elevation tiles of the configured directory
+CONFIG_LOAD_ERROR = Settings file not read, using defaults and hiding GPS positions
@@ -49,6 +49,7 @@ GRAPH_CAPTION_CALORIES = Calorias
 GRAPH_CAPTION_ASCENT = Ascenso
 GRAPH_CAPTION_DESCENT = Descenso
 GRAPH_CAPTION_DURATION = Duración
+GRAPH_CAPTION_MOVING_TIME = Tiempo en movimiento
 
 LABEL_DATE_TIME = Fecha y hora
 LABEL_DISTANCE_MILES = Distancia (millas)
@@ -62,6 +63,7 @@ LABEL_ELEVATION_M = Elevación (m)
 LABEL_TEMP_F = Temperatura (°F)
 LABEL_TEMP_C = Temperatura (°C)
 LABEL_DURATION = Duración (min)
+LABEL_MOVING_TIME = En movimiento (min)
 
 UNIT_FEET = pies
 UNIT_METERS = metros
@@ -98,3 +100,151 @@ SUMMARY_TOTAL_ASC_DES = Ascenso/Descenso Total
 MAXIMUM = Máximo
 MINIMUM = Mínimo
 AVERAGE = Promedio
+ACTIVITY_COUNT = Actividades
+TOTAL = Total
+MEDIAN = Mediana
+STANDARD_DEVIATION = Desv. estándar
+PERCENTILE_10 = Percentil 10
+PERCENTILE_90 = Percentil 90
+
+# Detalle de actividad / vueltas
+DETAIL_TAB_LAPS = Vueltas
+LABEL_LAP = Vuelta
+LABEL_LAP_TYPE = Tipo
+LABEL_LAP_TIME = Tiempo
+LABEL_HR_AVG = FC media
+LABEL_HR_MAX = FC máx.
+LAP_WORK = Trabajo
+LAP_RECOVERY = Recuperación
+LAP_WARMUP = Calentamiento
+LAP_COOLDOWN = Vuelta a la calma
+LAP_STEADY = Constante
+SUMMARY_WORK_INTERVALS = Intervalos de trabajo
+SUMMARY_RECOVERY = Recuperación
+MESSAGE_NO_LAPS = No hay vueltas registradas.
+TOOLTIP_DATE_BUTTON = Mostrar las vueltas de esta actividad
+DETAIL_TAB_CHARTS = Gráficos
+GRAPH_CAPTION_POWER = Potencia
+UNIT_MINUTES = minutos
+LABEL_X_AXIS_DISTANCE = Por distancia
+LABEL_X_AXIS_TIME = Por tiempo
+MESSAGE_NO_RECORDS = No hay muestras registradas.
+MESSAGE_NO_GPS = No hay recorridos GPS en el período seleccionado.
+SAVE_BUTTON = Guardar
+HEATMAP_TOGGLE = Mapa de calor
+HEATMAP_EXPORT = Exportar mapa de calor…
+TOOLTIP_HEATMAP_TOGGLE = Mostrar cuánto se usó cada ruta del período en lugar de los recorridos individuales.
+TOOLTIP_HEATMAP_EXPORT = Guardar el mapa de calor del período seleccionado como imagen PNG.
+PRIVACY_PREVIEW_TOGGLE = Zonas privadas
+TOOLTIP_PRIVACY_PREVIEW_TOGGLE = Previsualizar las zonas privadas y las posiciones que ocultan. Las posiciones ocultas nunca se exportan.
+UNIT_HOURS = horas
+GRAPH_CAPTION_HR_ZONES = Tiempo por zona de frecuencia cardíaca por semana
+SUMMARY_HR_ZONES = Tiempo por zona de FC
+GRAPH_CAPTION_PACE_ZONES = Tiempo por zona de ritmo
+GRAPH_CAPTION_POWER_ZONES = Tiempo por zona de potencia
+LABEL_PACE_ZONES = Zonas de ritmo (%)
+LABEL_POWER_ZONES = Zonas de potencia (%)
+GRAPH_VIEW_CHARTS = Gráficos
+GRAPH_VIEW_CALENDAR = Calendario
+TOOLTIP_GRAPH_VIEW = Muestra los gráficos de métricas o un calendario del entrenamiento diario.
+CALENDAR_METRIC_DISTANCE = Distancia
+CALENDAR_METRIC_DURATION = Duración
+CALENDAR_METRIC_LOAD = Carga
+TOOLTIP_CALENDAR_METRIC = Colorea los días del calendario por distancia, duración o carga de entrenamiento según la frecuencia cardíaca.
+CALENDAR_SHOW_ALL = Mostrar todos los días
+TOOLTIP_CALENDAR_SHOW_ALL = Vuelve a mostrar en el resumen todas las actividades del periodo.
+GRAPH_CAPTION_CALENDAR = Calendario de entrenamiento
+WEEKDAY_MON = lun
+WEEKDAY_WED = mié
+WEEKDAY_FRI = vie
+MONTH_JAN = ene
+MONTH_FEB = feb
+MONTH_MAR = mar
+MONTH_APR = abr
+MONTH_MAY = may
+MONTH_JUN = jun
+MONTH_JUL = jul
+MONTH_AUG = ago
+MONTH_SEP = sep
+MONTH_OCT = oct
+MONTH_NOV = nov
+MONTH_DEC = dic
+OVERLAY_MOVING_AVERAGE = Media móvil
+OVERLAY_TREND = Línea de tendencia
+TREND_IMPROVING = mejorando
+TREND_DECLINING = empeorando
+TREND_PER_MONTH = al mes
+FILTER_PLACEHOLDER = Filtro, p. ej. distance > 10, pace < 5:30
+TOOLTIP_FILTER = Mostrar solo las actividades que cumplen todos los términos: una métrica comparada con un número (distance, calories, pace, duration, ascent, descent), o texto de la fecha, el título, las etiquetas o las notas
+FILTER_ERROR = Filtro no reconocido
+COLUMNS_BUTTON = Columnas
+TOOLTIP_COLUMNS_BUTTON = Elegir las columnas visibles
+SUMMARY_TAB_ACTIVITIES = Actividades
+SUMMARY_TAB_VOLUME = Volumen
+VOLUME_PERIOD_WEEK = Semanal
+VOLUME_PERIOD_MONTH = Mensual
+TOOLTIP_VOLUME_PERIOD = Sumar las actividades por semana o por mes
+VOLUME_EXPORT = Exportar CSV
+TOOLTIP_VOLUME_EXPORT = Guardar el informe de volumen como archivo CSV
+VOLUME_PERIOD = Desde
+VOLUME_LONGEST = Más larga
+VOLUME_CHANGE = Cambio
+VOLUME_JUMP_WARNING = La distancia aumentó más de un
+GRAPH_VIEW_FITNESS = Forma física
+GRAPH_CAPTION_VO2MAX = VO2máx
+FITNESS_FROM_DEVICE = dispositivo
+FITNESS_NO_EFFORTS = No hay carreras con distancia y tiempo registrados en este periodo
+RACE_5K = 5 km
+RACE_10K = 10 km
+RACE_HALF_MARATHON = Media maratón
+RACE_MARATHON = Maratón
+SUMMARY_TAB_GEAR = Equipo
+GEAR_NONE = No hay equipo configurado. Añada entradas [[gear]] al archivo de configuración para seguir el kilometraje de zapatillas y bicicletas.
+GEAR_NAME = Equipo
+GEAR_RETIRE_AT = Retirar a
+GEAR_USED = Usado
+GEAR_NEARLY_WORN_OUT = Casi gastado
+GEAR_WORN_OUT = Gastado, hay que sustituirlo
+GEAR_STATUS_WARNING = Equipo a sustituir pronto
+GRAPH_CAPTION_GEAR = Distancia del equipo
+LABEL_TITLE = Título
+LABEL_TAGS = Etiquetas
+LABEL_NOTES = Notas
+LABEL_RPE = RPE
+TOOLTIP_RPE = Esfuerzo percibido, de 1 (muy fácil) a 10 (máximo); 0 para ninguno
+TAGS_PLACEHOLDER = carrera, cinta, enfermo, en grupo
+EDIT_ACTIVITY_BUTTON = Editar
+TOOLTIP_EDIT_ACTIVITY = Editar el título, las etiquetas, las notas y el esfuerzo de la actividad seleccionada (o doble clic en una fila)
+EDIT_ACTIVITY_TITLE = Editar actividad
+TAG_ALL = Todas las etiquetas
+TOOLTIP_TAG_FILTER = Mostrar solo las actividades con esta etiqueta en los gráficos, el mapa y el resumen
+SIDECAR_SAVE_ERROR = No se pudieron guardar las notas de la actividad
+LABEL_CALORIES = Calorías
+LABEL_ASCENT_METERS = Ascenso (m)
+LABEL_ASCENT_FEET = Ascenso (pies)
+DELETE_BUTTON = Eliminar
+MANUAL_START = Inicio (hora local)
+MANUAL_SPORT = Deporte
+MANUAL_SPORT_PLACEHOLDER = running, strength, cycling
+MANUAL_DURATION = Duración (h:mm:ss)
+MANUAL_ADD_TITLE = Añadir actividad manual
+MANUAL_EDIT_TITLE = Editar actividad manual
+MANUAL_MARK = (manual)
+TOOLTIP_MANUAL_ACTIVITY = Introducida a mano; haga clic para editarla o eliminarla
+TOOLTIP_MANUAL_ADD = Añadir un entrenamiento sin archivo de actividad (gimnasio, cinta, registro en papel)
+MANUAL_CONFLICT = Otra actividad ya empieza a esa hora
+LABEL_DESCENT_METERS = Descenso (m)
+LABEL_DESCENT_FEET = Descenso (pies)
+EDITED_MARK = (editada)
+TOOLTIP_EDITED_ACTIVITY = Se corrigieron valores registrados; mostrar las vueltas de esta actividad
+CORRECT_ACTIVITY_BUTTON = Corregir
+TOOLTIP_CORRECT_ACTIVITY = Corregir la distancia, la duración, el ascenso, el descenso o el deporte de la actividad seleccionada sin modificar su archivo
+CORRECTION_TITLE = Corregir actividad
+CORRECTION_VALUE = Valor
+CORRECTION_RECORDED = Registrado
+CORRECTION_REVERT = Revertir
+TOOLTIP_CORRECTION_REVERT = Volver a los valores registrados en el archivo
+ELEVATION_DEVICE = Altitud del dispositivo
+ELEVATION_DEM = Altitud corregida (MDE)
+TOOLTIP_ELEVATION_SOURCE = Ascenso y descenso registrados por el dispositivo o recalculados con las teselas de elevación del directorio configurado
+CONFIG_LOAD_ERROR = No se pudo leer el archivo de configuración; se usan los valores predeterminados y se ocultan las posiciones GPS
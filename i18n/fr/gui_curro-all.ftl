@@ -48,6 +48,7 @@ GRAPH_CAPTION_CALORIES = Calories
 GRAPH_CAPTION_ASCENT = Ascension
 GRAPH_CAPTION_DESCENT = Descente
 GRAPH_CAPTION_DURATION = Durée
+GRAPH_CAPTION_MOVING_TIME = Temps en mouvement
 
 LABEL_DATE_TIME = Date et heure
 LABEL_DISTANCE_MILES = Distance (milles)
@@ -61,6 +62,7 @@ LABEL_ELEVATION_M = Altitude (m)
 LABEL_TEMP_F = Température (°F)
 LABEL_TEMP_C = Température (°C)
 LABEL_DURATION = Durée (min)
+LABEL_MOVING_TIME = En mouvement (min)
 
 UNIT_FEET = pieds
 UNIT_METERS = mètres
@@ -94,6 +96,154 @@ SUMMARY_DUR_MAX_MIN_AVG = Durée Max/Min/Moy
 SUMMARY_PACE_FAST_SLOW = Allure la plus Rapide/Lente
 SUMMARY_TOTAL_ASC_DES = Dénivelé Positif/Négatif Total
 
-MAXIMUM = Maxium
+MAXIMUM = Maximum
 MINIMUM = Minimum
 AVERAGE = Moyenne 
+ACTIVITY_COUNT = Activités
+TOTAL = Total
+MEDIAN = Médiane
+STANDARD_DEVIATION = Écart type
+PERCENTILE_10 = 10e centile
+PERCENTILE_90 = 90e centile
+
+# Détail de l'activité / tours
+DETAIL_TAB_LAPS = Tours
+LABEL_LAP = Tour
+LABEL_LAP_TYPE = Type
+LABEL_LAP_TIME = Temps
+LABEL_HR_AVG = FC moy.
+LABEL_HR_MAX = FC max.
+LAP_WORK = Effort
+LAP_RECOVERY = Récupération
+LAP_WARMUP = Échauffement
+LAP_COOLDOWN = Retour au calme
+LAP_STEADY = Régulier
+SUMMARY_WORK_INTERVALS = Intervalles d'effort
+SUMMARY_RECOVERY = Récupération
+MESSAGE_NO_LAPS = Aucun tour enregistré.
+TOOLTIP_DATE_BUTTON = Afficher les tours de cette activité
+DETAIL_TAB_CHARTS = Graphiques
+GRAPH_CAPTION_POWER = Puissance
+UNIT_MINUTES = minutes
+LABEL_X_AXIS_DISTANCE = Par distance
+LABEL_X_AXIS_TIME = Par temps
+MESSAGE_NO_RECORDS = Aucun échantillon enregistré.
+MESSAGE_NO_GPS = Aucun tracé GPS dans la période sélectionnée.
+SAVE_BUTTON = Enregistrer
+HEATMAP_TOGGLE = Carte de chaleur
+HEATMAP_EXPORT = Exporter la carte de chaleur…
+TOOLTIP_HEATMAP_TOGGLE = Afficher la fréquentation de chaque parcours de la période au lieu des tracés individuels.
+TOOLTIP_HEATMAP_EXPORT = Enregistrer la carte de chaleur de la période sélectionnée en image PNG.
+PRIVACY_PREVIEW_TOGGLE = Zones privées
+TOOLTIP_PRIVACY_PREVIEW_TOGGLE = Prévisualiser les zones privées et les positions qu'elles masquent. Les positions masquées ne sont jamais exportées.
+UNIT_HOURS = heures
+GRAPH_CAPTION_HR_ZONES = Temps par zone de fréquence cardiaque par semaine
+SUMMARY_HR_ZONES = Temps par zone de FC
+GRAPH_CAPTION_PACE_ZONES = Temps par zone d'allure
+GRAPH_CAPTION_POWER_ZONES = Temps par zone de puissance
+LABEL_PACE_ZONES = Zones d'allure (%)
+LABEL_POWER_ZONES = Zones de puissance (%)
+GRAPH_VIEW_CHARTS = Graphiques
+GRAPH_VIEW_CALENDAR = Calendrier
+TOOLTIP_GRAPH_VIEW = Affiche les graphiques des métriques ou un calendrier de l'entraînement quotidien.
+CALENDAR_METRIC_DISTANCE = Distance
+CALENDAR_METRIC_DURATION = Durée
+CALENDAR_METRIC_LOAD = Charge
+TOOLTIP_CALENDAR_METRIC = Colore les jours du calendrier selon la distance, la durée ou la charge d'entraînement cardiaque.
+CALENDAR_SHOW_ALL = Afficher tous les jours
+TOOLTIP_CALENDAR_SHOW_ALL = Affiche à nouveau toutes les activités de la période dans le résumé.
+GRAPH_CAPTION_CALENDAR = Calendrier d'entraînement
+WEEKDAY_MON = lun
+WEEKDAY_WED = mer
+WEEKDAY_FRI = ven
+MONTH_JAN = janv
+MONTH_FEB = févr
+MONTH_MAR = mars
+MONTH_APR = avr
+MONTH_MAY = mai
+MONTH_JUN = juin
+MONTH_JUL = juil
+MONTH_AUG = août
+MONTH_SEP = sept
+MONTH_OCT = oct
+MONTH_NOV = nov
+MONTH_DEC = déc
+OVERLAY_MOVING_AVERAGE = Moyenne mobile
+OVERLAY_TREND = Ligne de tendance
+TREND_IMPROVING = en amélioration de
+TREND_DECLINING = en baisse de
+TREND_PER_MONTH = par mois
+FILTER_PLACEHOLDER = Filtre, p. ex. distance > 10, pace < 5:30
+TOOLTIP_FILTER = N'afficher que les activités vérifiant tous les termes : une mesure comparée à un nombre (distance, calories, pace, duration, ascent, descent), ou du texte de la date, du titre, des étiquettes ou des notes
+FILTER_ERROR = Filtre non compris
+COLUMNS_BUTTON = Colonnes
+TOOLTIP_COLUMNS_BUTTON = Choisir les colonnes affichées
+SUMMARY_TAB_ACTIVITIES = Activités
+SUMMARY_TAB_VOLUME = Volume
+VOLUME_PERIOD_WEEK = Hebdomadaire
+VOLUME_PERIOD_MONTH = Mensuel
+TOOLTIP_VOLUME_PERIOD = Additionner les activités par semaine ou par mois
+VOLUME_EXPORT = Exporter en CSV
+TOOLTIP_VOLUME_EXPORT = Enregistrer le rapport de volume dans un fichier CSV
+VOLUME_PERIOD = Début
+VOLUME_LONGEST = Plus longue
+VOLUME_CHANGE = Variation
+VOLUME_JUMP_WARNING = La distance a augmenté de plus de
+GRAPH_VIEW_FITNESS = Forme
+GRAPH_CAPTION_VO2MAX = VO2max
+FITNESS_FROM_DEVICE = appareil
+FITNESS_NO_EFFORTS = Aucune course avec distance et temps enregistrés sur cette période
+RACE_5K = 5 km
+RACE_10K = 10 km
+RACE_HALF_MARATHON = Semi-marathon
+RACE_MARATHON = Marathon
+SUMMARY_TAB_GEAR = Équipement
+GEAR_NONE = Aucun équipement configuré. Ajoutez des entrées [[gear]] au fichier de paramètres pour suivre le kilométrage des chaussures et des vélos.
+GEAR_NAME = Équipement
+GEAR_RETIRE_AT = Retrait à
+GEAR_USED = Utilisé
+GEAR_NEARLY_WORN_OUT = Presque usé
+GEAR_WORN_OUT = Usé, à remplacer
+GEAR_STATUS_WARNING = Équipement à remplacer bientôt
+GRAPH_CAPTION_GEAR = Distance de l'équipement
+LABEL_TITLE = Titre
+LABEL_TAGS = Étiquettes
+LABEL_NOTES = Notes
+LABEL_RPE = RPE
+TOOLTIP_RPE = Effort perçu, de 1 (très facile) à 10 (maximal) ; 0 pour aucun
+TAGS_PLACEHOLDER = course, tapis, malade, en groupe
+EDIT_ACTIVITY_BUTTON = Modifier
+TOOLTIP_EDIT_ACTIVITY = Modifier le titre, les étiquettes, les notes et l'effort de l'activité sélectionnée (ou double-cliquer sur une ligne)
+EDIT_ACTIVITY_TITLE = Modifier l'activité
+TAG_ALL = Toutes les étiquettes
+TOOLTIP_TAG_FILTER = N'afficher que les activités portant cette étiquette dans les graphiques, la carte et le résumé
+SIDECAR_SAVE_ERROR = Impossible d'enregistrer les notes de l'activité
+LABEL_CALORIES = Calories
+LABEL_ASCENT_METERS = Dénivelé positif (m)
+LABEL_ASCENT_FEET = Dénivelé positif (pieds)
+DELETE_BUTTON = Supprimer
+MANUAL_START = Début (heure locale)
+MANUAL_SPORT = Sport
+MANUAL_SPORT_PLACEHOLDER = running, strength, cycling
+MANUAL_DURATION = Durée (h:mm:ss)
+MANUAL_ADD_TITLE = Ajouter une activité manuelle
+MANUAL_EDIT_TITLE = Modifier l'activité manuelle
+MANUAL_MARK = (manuelle)
+TOOLTIP_MANUAL_ACTIVITY = Saisie à la main ; cliquer pour la modifier ou la supprimer
+TOOLTIP_MANUAL_ADD = Ajouter un entraînement sans fichier d'activité (salle de sport, tapis, carnet papier)
+MANUAL_CONFLICT = Une autre activité commence déjà à cette heure
+LABEL_DESCENT_METERS = Dénivelé négatif (m)
+LABEL_DESCENT_FEET = Dénivelé négatif (pieds)
+EDITED_MARK = (corrigée)
+TOOLTIP_EDITED_ACTIVITY = Des valeurs enregistrées ont été corrigées ; afficher les tours de cette activité
+CORRECT_ACTIVITY_BUTTON = Corriger
+TOOLTIP_CORRECT_ACTIVITY = Corriger la distance, la durée, le dénivelé ou le sport de l'activité sélectionnée sans modifier son fichier
+CORRECTION_TITLE = Corriger l'activité
+CORRECTION_VALUE = Valeur
+CORRECTION_RECORDED = Enregistré
+CORRECTION_REVERT = Rétablir
+TOOLTIP_CORRECTION_REVERT = Revenir aux valeurs enregistrées dans le fichier
+ELEVATION_DEVICE = Altitude de l'appareil
+ELEVATION_DEM = Altitude corrigée (MNT)
+TOOLTIP_ELEVATION_SOURCE = Dénivelé positif et négatif enregistrés par l'appareil ou recalculés à partir des tuiles d'altitude du répertoire configuré
+CONFIG_LOAD_ERROR = Fichier de configuration illisible ; réglages par défaut utilisés et positions GPS masquées
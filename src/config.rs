@@ -1,5 +1,11 @@
 // Module containing user preferences, static information and helper utilities.
 use crate::Error;
+use chrono::{NaiveDate, NaiveDateTime};
+use curro_all_core::data::Units;
+use curro_all_core::gear::{Gear, GearRule};
+use curro_all_core::metrics::{METRICS, Metric, find_metric};
+use curro_all_core::privacy::PrivacyZone;
+use curro_all_core::zones::{HrZoneSettings, PaceZoneSettings, ZoneSettings};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
@@ -14,14 +20,7 @@ pub const TESTER1: &str = "Gabriel A. Prevallet";
 pub const TESTER2: &str = "Jacob Thymann Nielsen";
 pub const TESTER3: &str = "Tony Castronovo";
 pub const SETTINGSFILE: &str = "curro-all.toml";
-
-//Unit of measure system.
-#[derive(Clone, PartialEq)]
-pub enum Units {
-    Metric,
-    US,
-    None,
-}
+pub const DATABASEFILE: &str = "curro-all.sqlite";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WindowConfig {
@@ -31,6 +30,306 @@ pub struct WindowConfig {
     pub left_frame_split: i32,
     pub right_frame_split: i32,
     pub units_index: u32, // toml won't serialize enums, we'll use the selected DropDown
+    // Library directory opened last, whose sidecar (notes, tags, manual
+    // activities, corrections) is shown.
+    #[serde(default)]
+    pub library: String,
+    // Offline map tiles: a {z}/{x}/{y}.png directory or an .mbtiles file.
+    #[serde(default)]
+    pub map_tiles: String,
+    // Heart rate zones come from the threshold heart rate when it is set
+    // (non-zero), otherwise from the maximum heart rate.
+    #[serde(default = "default_max_hr")]
+    pub max_hr: u32,
+    #[serde(default)]
+    pub threshold_hr: u32,
+    // Prefer the time in zone the watch recorded over our own computation.
+    #[serde(default = "default_true")]
+    pub use_device_hr_zones: bool,
+    // Threshold pace as "m:ss" per km and per mile; empty turns pace zones off.
+    #[serde(default = "default_threshold_pace_metric")]
+    pub threshold_pace_metric: String,
+    #[serde(default = "default_threshold_pace_us")]
+    pub threshold_pace_us: String,
+    // Functional threshold power in watts; 0 turns power zones off.
+    #[serde(default)]
+    pub ftp: u32,
+    // Columns of the chart grid; rows are added as the charts need.
+    #[serde(default = "default_chart_columns")]
+    pub chart_columns: u32,
+    // What the summary averages are weighted by: "distance", "duration" or
+    // nothing for every activity alike.
+    #[serde(default)]
+    pub summary_weighting: String,
+    // Weekly or monthly distance increase (percent) flagged in the volume
+    // report.
+    #[serde(default = "default_volume_jump_warning")]
+    pub volume_jump_warning: f64,
+    // Directory of SRTM .hgt elevation tiles; empty turns the elevation
+    // correction off.
+    #[serde(default)]
+    pub dem_directory: String,
+    // Where ascent and descent come from: "device" or "dem".
+    #[serde(default)]
+    pub elevation_source: String,
+    // Activity table columns the user hid, by name.
+    #[serde(default)]
+    pub hidden_columns: Vec<String>,
+    // Circles inside which GPS positions are never drawn or exported.
+    #[serde(default)]
+    pub privacy_zones: Vec<PrivacyZoneConfig>,
+    // The metric charts, in grid order (left to right, top to bottom).
+    #[serde(default = "default_charts")]
+    pub charts: Vec<ChartConfig>,
+    // Shoes, bikes and other items whose distance is tracked.
+    #[serde(default)]
+    pub gear: Vec<GearConfig>,
+    // Why the settings file could not be read, in which case the other
+    // fields are the defaults. Never saved.
+    #[serde(skip)]
+    pub load_error: Option<String>,
+}
+
+// How a metric is drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    // One bar per activity.
+    #[default]
+    Bar,
+    // One point per activity, joined.
+    Line,
+    // Running total over the range (running average for pace).
+    Cumulative,
+}
+
+// A metric chart as written in the settings file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartConfig {
+    // A metric name: distance, calories, pace, duration, ascent or descent.
+    pub metric: String,
+    #[serde(default)]
+    pub kind: ChartKind,
+    // "#rrggbb"; empty uses the metric's default color.
+    #[serde(default)]
+    pub color: String,
+    // Moving average over average_window activities, or days with
+    // average_by_days.
+    #[serde(default)]
+    pub show_average: bool,
+    #[serde(default = "default_average_window")]
+    pub average_window: u32,
+    #[serde(default)]
+    pub average_by_days: bool,
+    #[serde(default)]
+    pub show_trend: bool,
+    #[serde(default)]
+    pub trend: TrendKind,
+}
+
+// How the trend line of a chart is fitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrendKind {
+    #[default]
+    Linear,
+    Loess,
+}
+
+impl ChartConfig {
+    pub fn metric(&self) -> Option<&'static Metric> {
+        find_metric(&self.metric)
+    }
+
+    // Red, green and blue of the chart, the metric's own when the setting
+    // is empty or not a color.
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        parse_color(&self.color).or_else(|| self.metric().map(|m| m.color))
+    }
+}
+
+// A privacy zone as written in the settings file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PrivacyZoneConfig {
+    pub latitude: f64,
+    pub longitude: f64,
+    // meters
+    pub radius: f64,
+}
+
+// A gear item as written in the settings file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GearConfig {
+    pub name: String,
+    // Distance and moving time at which the item is worn out, 0 for none.
+    #[serde(default)]
+    pub retire_km: f64,
+    #[serde(default)]
+    pub retire_hours: f64,
+    // Kilometers covered before the first recorded activity.
+    #[serde(default)]
+    pub initial_km: f64,
+    // Start times ("YYYY-MM-DD HH:MM", UTC) of activities assigned by hand.
+    #[serde(default)]
+    pub activities: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<GearRuleConfig>,
+}
+
+// A gear rule as written in the settings file; empty fields match anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GearRuleConfig {
+    #[serde(default)]
+    pub sport: String,
+    #[serde(default)]
+    pub device: String,
+    // "YYYY-MM-DD", included.
+    #[serde(default)]
+    pub from: String,
+    #[serde(default)]
+    pub to: String,
+}
+
+fn default_max_hr() -> u32 {
+    190
+}
+
+fn default_chart_columns() -> u32 {
+    2
+}
+
+fn default_volume_jump_warning() -> f64 {
+    10.0
+}
+
+fn default_average_window() -> u32 {
+    5
+}
+
+// Every metric as a bar chart, in their usual order.
+fn default_charts() -> Vec<ChartConfig> {
+    METRICS
+        .iter()
+        .map(|m| ChartConfig {
+            metric: m.name.to_string(),
+            kind: ChartKind::Bar,
+            color: format!("#{:02x}{:02x}{:02x}", m.color.0, m.color.1, m.color.2),
+            show_average: false,
+            average_window: default_average_window(),
+            average_by_days: false,
+            show_trend: false,
+            trend: TrendKind::Linear,
+        })
+        .collect()
+}
+
+// Parse a "#rrggbb" color.
+fn parse_color(text: &str) -> Option<(u8, u8, u8)> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_threshold_pace_metric() -> String {
+    "5:00".to_string()
+}
+
+fn default_threshold_pace_us() -> String {
+    "8:00".to_string()
+}
+
+// Trimmed text, None when empty.
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+// Parse a date written as "YYYY-MM-DD".
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()
+}
+
+// Parse a pace written as "m:ss" into decimal minutes.
+fn parse_pace(text: &str) -> Option<f64> {
+    let (mins, secs) = text.trim().split_once(':')?;
+    let pace = mins.parse::<f64>().ok()? + secs.parse::<f64>().ok()? / 60.0;
+    (pace > 0.0).then_some(pace)
+}
+
+impl WindowConfig {
+    // Zone settings for the unit system shown, which picks the threshold pace.
+    pub fn zone_settings(&self, units: &Units) -> ZoneSettings {
+        let threshold_pace = match units {
+            Units::Metric => parse_pace(&self.threshold_pace_metric),
+            Units::US => parse_pace(&self.threshold_pace_us),
+            Units::None => None,
+        };
+        ZoneSettings {
+            hr: HrZoneSettings {
+                max_hr: self.max_hr as f64,
+                threshold_hr: (self.threshold_hr > 0).then_some(self.threshold_hr as f64),
+                prefer_device: self.use_device_hr_zones,
+            },
+            pace: threshold_pace.map(|threshold_pace| PaceZoneSettings {
+                threshold_pace,
+                units: units.clone(),
+            }),
+            ftp: (self.ftp > 0).then_some(self.ftp as f64),
+        }
+    }
+
+    // Gear items in meters and seconds. Unreadable dates are ignored.
+    pub fn gear(&self) -> Vec<Gear> {
+        let positive = |v: f64| (v > 0.0).then_some(v);
+        self.gear
+            .iter()
+            .map(|g| Gear {
+                name: g.name.clone(),
+                rules: g
+                    .rules
+                    .iter()
+                    .map(|r| GearRule {
+                        sport: non_empty(&r.sport),
+                        device: non_empty(&r.device),
+                        from: parse_date(&r.from),
+                        to: parse_date(&r.to),
+                    })
+                    .collect(),
+                activities: g
+                    .activities
+                    .iter()
+                    .filter_map(|t| NaiveDateTime::parse_from_str(t.trim(), "%Y-%m-%d %H:%M").ok())
+                    .map(|t| t.and_utc())
+                    .collect(),
+                initial_distance: g.initial_km * 1000.0,
+                retire_distance: positive(g.retire_km * 1000.0),
+                retire_time: positive(g.retire_hours * 3600.0),
+            })
+            .collect()
+    }
+
+    // Every position is hidden while the settings file cannot be read, so
+    // its zones are never ignored.
+    pub fn privacy_zones(&self) -> Vec<PrivacyZone> {
+        if self.load_error.is_some() {
+            return vec![PrivacyZone::everywhere()];
+        }
+        self.privacy_zones
+            .iter()
+            .map(|z| PrivacyZone {
+                lat: z.latitude,
+                lon: z.longitude,
+                radius: z.radius,
+            })
+            .collect()
+    }
 }
 
 impl Default for WindowConfig {
@@ -42,12 +341,34 @@ impl Default for WindowConfig {
             left_frame_split: 200,
             right_frame_split: 200,
             units_index: 0,
+            library: String::new(),
+            map_tiles: String::new(),
+            max_hr: default_max_hr(),
+            threshold_hr: 0,
+            use_device_hr_zones: true,
+            threshold_pace_metric: default_threshold_pace_metric(),
+            threshold_pace_us: default_threshold_pace_us(),
+            ftp: 0,
+            chart_columns: default_chart_columns(),
+            summary_weighting: String::new(),
+            volume_jump_warning: default_volume_jump_warning(),
+            dem_directory: String::new(),
+            elevation_source: String::new(),
+            hidden_columns: Vec::new(),
+            privacy_zones: Vec::new(),
+            charts: default_charts(),
+            gear: Vec::new(),
+            load_error: None,
         }
     }
 }
 
-/// Saves the WindowConfig struct to a TOML file.
+/// Saves the WindowConfig struct to a TOML file. A config standing in for
+/// an unreadable file is not saved, so the user's settings are not lost.
 pub fn save_config(config: &WindowConfig, path: &Path) -> std::io::Result<()> {
+    if let Some(e) = &config.load_error {
+        return Err(std::io::Error::other(e.clone()));
+    }
     // Use toml::to_string() to serialize the struct into a TOML string
     let toml_string = toml::to_string(config)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
@@ -67,21 +388,26 @@ fn deserialize_config(toml_string: &str) -> std::result::Result<WindowConfig, Bo
 }
 
 /// Loads the WindowConfig struct from a TOML file, using the dedicated
-/// deserialize_config function, or returns the default config on failure.
+/// deserialize_config function, or returns the default config on failure,
+/// with the reason in load_error.
 pub fn load_config(path: &Path) -> WindowConfig {
     if !path.exists() {
         return WindowConfig::default();
     }
 
+    let failed = |e: String| WindowConfig {
+        load_error: Some(e),
+        ..Default::default()
+    };
     // Read file content
     match fs::read_to_string(path) {
         Ok(toml_string) => {
             // Call the dedicated deserialization function
             match deserialize_config(&toml_string) {
                 Ok(config) => config,
-                Err(_e) => WindowConfig::default(),
+                Err(e) => failed(e.to_string()),
             }
         }
-        Err(_e) => WindowConfig::default(),
+        Err(e) => failed(e.to_string()),
     }
 }
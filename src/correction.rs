@@ -0,0 +1,194 @@
+// Editor of the corrections of a recorded activity: distance, duration,
+// ascent, descent and sport replacing the values of its file, which is left
+// untouched, with a revert to the recorded values.
+
+use crate::gui::format_duration;
+use crate::manual::parse_duration;
+use chrono::{DateTime, Utc};
+use curro_all_core::data::{SessionStats, Units, cvt_altitude, cvt_distance};
+use curro_all_core::i18n::tr;
+use curro_all_core::sidecar::Correction;
+use curro_all_core::source::ActivityInfo;
+use gtk4::glib::clone;
+use gtk4::prelude::*;
+use gtk4::{Button, Entry, Grid, Label, Orientation, SpinButton, Window};
+
+// Show the editor for the values recorded and the correction saved, if
+// any. on_save gets the new correction, empty when nothing differs from
+// the recording; on_revert drops it.
+pub fn show_correction_dialog(
+    parent: Option<&Window>,
+    units: &Units,
+    start: DateTime<Utc>,
+    recorded: (SessionStats, ActivityInfo),
+    correction: Option<Correction>,
+    on_save: impl Fn(Correction) + 'static,
+    on_revert: impl Fn() + 'static,
+) {
+    let (stats, info) = recorded;
+    let is_corrected = correction.is_some();
+    let mut current = stats;
+    let mut current_info = info.clone();
+    if let Some(correction) = &correction {
+        correction.apply(&mut current);
+        correction.apply_info(&mut current_info);
+    }
+    let grid = Grid::builder()
+        .column_spacing(12)
+        .row_spacing(8)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .build();
+    let (distance_key, ascent_key, descent_key) = match units {
+        Units::US => (
+            "LABEL_DISTANCE_MILES",
+            "LABEL_ASCENT_FEET",
+            "LABEL_DESCENT_FEET",
+        ),
+        _ => (
+            "LABEL_DISTANCE_KM",
+            "LABEL_ASCENT_METERS",
+            "LABEL_DESCENT_METERS",
+        ),
+    };
+    let distance_of = |meters: f64| cvt_distance(meters as f32, units) as f64;
+    let altitude_of = |meters: u16| cvt_altitude(meters as f32, units).round() as f64;
+    // Meters in one unit shown.
+    let meters_per_unit = 1.0 / cvt_distance(1.0, units) as f64;
+    let meters_per_altitude_unit = 1.0 / cvt_altitude(1.0, units) as f64;
+
+    let distance = SpinButton::with_range(0.0, 1000.0, 0.1);
+    distance.set_digits(2);
+    distance.set_value(distance_of(current.distance));
+    let duration = Entry::builder()
+        .text(format_duration(current.duration))
+        .placeholder_text("h:mm:ss")
+        .hexpand(true)
+        .build();
+    let ascent = SpinButton::with_range(0.0, 30000.0, 10.0);
+    ascent.set_value(altitude_of(current.ascent));
+    let descent = SpinButton::with_range(0.0, 30000.0, 10.0);
+    descent.set_value(altitude_of(current.descent));
+    let sport = Entry::builder()
+        .text(&current_info.sport)
+        .placeholder_text(tr("MANUAL_SPORT_PLACEHOLDER", None))
+        .build();
+    let recorded_distance = distance_of(stats.distance);
+    let (recorded_ascent, recorded_descent) =
+        (altitude_of(stats.ascent), altitude_of(stats.descent));
+    let header = |key: &str| {
+        Label::builder()
+            .label(format!("<b>{}</b>", tr(key, None)))
+            .use_markup(true)
+            .halign(gtk4::Align::Start)
+            .build()
+    };
+    grid.attach(&header("CORRECTION_VALUE"), 1, 0, 1, 1);
+    grid.attach(&header("CORRECTION_RECORDED"), 2, 0, 1, 1);
+    for (row, (key, widget, recorded)) in [
+        (
+            distance_key,
+            distance.upcast_ref::<gtk4::Widget>(),
+            format!("{:.2}", recorded_distance),
+        ),
+        (
+            "MANUAL_DURATION",
+            duration.upcast_ref(),
+            format_duration(stats.duration),
+        ),
+        (ascent_key, ascent.upcast_ref(), recorded_ascent.to_string()),
+        (
+            descent_key,
+            descent.upcast_ref(),
+            recorded_descent.to_string(),
+        ),
+        ("MANUAL_SPORT", sport.upcast_ref(), info.sport.clone()),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let row = row as i32 + 1;
+        let label = Label::builder()
+            .label(tr(key, None))
+            .halign(gtk4::Align::Start)
+            .build();
+        let recorded = Label::builder()
+            .label(recorded)
+            .halign(gtk4::Align::Start)
+            .css_classes(["dim-label"])
+            .build();
+        grid.attach(&label, 0, row, 1, 1);
+        grid.attach(widget, 1, row, 1, 1);
+        grid.attach(&recorded, 2, row, 1, 1);
+    }
+    let revert = Button::with_label(&tr("CORRECTION_REVERT", None));
+    revert.set_tooltip_text(Some(&tr("TOOLTIP_CORRECTION_REVERT", None)));
+    revert.set_visible(is_corrected);
+    let cancel = Button::with_label(&tr("CANCEL_BUTTON", None));
+    let save = Button::with_label(&tr("SAVE_BUTTON", None));
+    save.add_css_class("suggested-action");
+    let buttons = gtk4::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(10)
+        .halign(gtk4::Align::End)
+        .build();
+    buttons.append(&revert);
+    buttons.append(&cancel);
+    buttons.append(&save);
+    grid.attach(&buttons, 0, 6, 3, 1);
+
+    let window = Window::builder()
+        .title(format!(
+            "{} {}",
+            tr("CORRECTION_TITLE", None),
+            start.format("%Y-%m-%d %H:%M")
+        ))
+        .modal(true)
+        .default_width(460)
+        .child(&grid)
+        .build();
+    window.set_transient_for(parent);
+    cancel.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| window.close()
+    ));
+    revert.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| {
+            on_revert();
+            window.close();
+        }
+    ));
+    save.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| {
+            let Some(seconds) = parse_duration(&duration.text()) else {
+                duration.add_css_class("error");
+                return;
+            };
+            // Values shown as recorded, to the precision shown, are kept
+            // as recorded.
+            let altitude = |spin: &SpinButton, recorded: f64| {
+                (spin.value() != recorded)
+                    .then(|| (spin.value() * meters_per_altitude_unit).round() as u16)
+            };
+            let sport = sport.text().trim().to_lowercase();
+            on_save(Correction {
+                start,
+                distance: ((distance.value() - recorded_distance).abs() >= 0.005)
+                    .then(|| distance.value() * meters_per_unit),
+                duration: ((seconds - stats.duration).abs() >= 1.0).then_some(seconds),
+                ascent: altitude(&ascent, recorded_ascent),
+                descent: altitude(&descent, recorded_descent),
+                sport: (!sport.is_empty() && sport != info.sport).then_some(sport),
+            });
+            window.close();
+        }
+    ));
+    window.present();
+}
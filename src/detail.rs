@@ -0,0 +1,402 @@
+// Single activity detail window, opened from a row of the summary.
+
+use crate::gui::{format_duration, format_pace};
+use chrono::{DateTime, Utc};
+use curro_all_core::data::{Units, cvt_altitude, cvt_distance, cvt_pace, read_activity};
+use curro_all_core::i18n::tr;
+use curro_all_core::laps::{Lap, LapKind, lap_time, summarize_laps};
+use curro_all_core::records::Record;
+use gtk4::cairo::Context;
+use gtk4::glib::{self, clone};
+use gtk4::prelude::*;
+use gtk4::{DrawingArea, DropDown, Label, Notebook, Orientation, ScrolledWindow, Spinner, Window};
+use libadwaita::{ApplicationWindow, StyleManager};
+use plotters::prelude::*;
+use plotters::style::full_palette::{BROWN, GREY_200, GREY_400, GREY_600, GREY_800, PURPLE};
+use std::path::Path;
+use std::rc::Rc;
+
+// Height in pixels of each per-record chart.
+const RECORD_CHART_HEIGHT: i32 = 220;
+
+// One per-record metric: caption, unit and how to read it from a record.
+struct RecordMetric {
+    caption: String,
+    unit: String,
+    color: RGBColor,
+    value: fn(&Record, &Units) -> Option<f64>,
+}
+
+fn record_metrics(units: &Units) -> Vec<RecordMetric> {
+    let (pace_unit, alt_unit) = match units {
+        Units::Metric => (tr("UNIT_PACE_METRIC", None), tr("UNIT_METERS", None)),
+        _ => (tr("UNIT_PACE_US", None), tr("UNIT_FEET", None)),
+    };
+    vec![
+        RecordMetric {
+            caption: tr("GRAPH_CAPTION_PACE", None),
+            unit: pace_unit,
+            color: BROWN,
+            // Stopped samples would dwarf the rest of the pace line.
+            value: |r, u| {
+                r.speed
+                    .filter(|s| *s >= 1.0)
+                    .map(|s| cvt_pace(s as f32, u) as f64)
+            },
+        },
+        RecordMetric {
+            caption: tr("GRAPH_CAPTION_HR", None),
+            unit: "bpm".to_string(),
+            color: RED,
+            value: |r, _| r.heart_rate,
+        },
+        RecordMetric {
+            caption: tr("GRAPH_CAPTION_ELEVATION", None),
+            unit: alt_unit,
+            color: GREEN,
+            value: |r, u| r.altitude.map(|a| cvt_altitude(a as f32, u) as f64),
+        },
+        RecordMetric {
+            caption: tr("GRAPH_CAPTION_CADENCE", None),
+            unit: "rpm".to_string(),
+            color: BLUE,
+            value: |r, _| r.cadence,
+        },
+        RecordMetric {
+            caption: tr("GRAPH_CAPTION_POWER", None),
+            unit: "W".to_string(),
+            color: PURPLE,
+            value: |r, _| r.power,
+        },
+    ]
+}
+
+// X coordinate of a record: distance in the unit system, or elapsed minutes.
+fn record_x(record: &Record, by_distance: bool, units: &Units) -> Option<f64> {
+    if by_distance {
+        record
+            .distance
+            .map(|d| cvt_distance(d as f32, units) as f64)
+    } else {
+        Some(record.elapsed / 60.0)
+    }
+}
+
+// Translated name of a lap classification.
+fn lap_kind_label(kind: LapKind) -> String {
+    match kind {
+        LapKind::Work => tr("LAP_WORK", None),
+        LapKind::Recovery => tr("LAP_RECOVERY", None),
+        LapKind::WarmUp => tr("LAP_WARMUP", None),
+        LapKind::CoolDown => tr("LAP_COOLDOWN", None),
+        LapKind::Steady => tr("LAP_STEADY", None),
+    }
+}
+
+fn attach_cell(grid: &gtk4::Grid, text: &str, col: i32, row: i32, bold: bool) {
+    let label = Label::new(Some(text));
+    label.set_halign(gtk4::Align::Start);
+    label.set_selectable(true);
+    if bold {
+        label.set_markup(&format!("<b>{}</b>", text));
+    }
+    grid.attach(&label, col, row, 1, 1);
+}
+
+// Build the lap table of an activity.
+fn build_lap_page(laps: &[Lap], units: &Units) -> gtk4::Widget {
+    if laps.is_empty() {
+        return Label::new(Some(&tr("MESSAGE_NO_LAPS", None))).upcast();
+    }
+    let grid = gtk4::Grid::builder()
+        .column_spacing(12)
+        .row_spacing(6)
+        .margin_start(10)
+        .margin_end(10)
+        .margin_top(10)
+        .margin_bottom(10)
+        .build();
+    let (dist_label, alt_label, pace_label) = match units {
+        Units::Metric => (
+            tr("LABEL_DISTANCE_KM", None),
+            "m",
+            tr("LABEL_PACE_METRIC", None),
+        ),
+        _ => (
+            tr("LABEL_DISTANCE_MILES", None),
+            "ft",
+            tr("LABEL_PACE_US", None),
+        ),
+    };
+    let headers = [
+        tr("LABEL_LAP", None),
+        tr("LABEL_LAP_TYPE", None),
+        dist_label,
+        tr("LABEL_LAP_TIME", None),
+        pace_label,
+        tr("LABEL_HR_AVG", None),
+        tr("LABEL_HR_MAX", None),
+        format!("Asc({})", alt_label),
+        format!("Des({})", alt_label),
+    ];
+    for (col, text) in headers.iter().enumerate() {
+        attach_cell(&grid, text, col as i32, 0, true);
+    }
+    let optional = |v: Option<f64>| v.map(|v| format!("{:.0}", v)).unwrap_or_default();
+    for (row_idx, lap) in laps.iter().enumerate() {
+        let row = (row_idx + 1) as i32;
+        let cells = [
+            (lap.index + 1).to_string(),
+            lap_kind_label(lap.kind),
+            format!("{:.2}", cvt_distance(lap.distance as f32, units)),
+            format_duration(lap_time(lap)),
+            format_pace(cvt_pace(lap.avg_speed as f32, units) as f64),
+            optional(lap.avg_heart_rate.map(f64::from)),
+            optional(lap.max_heart_rate.map(f64::from)),
+            optional(lap.ascent.map(|v| cvt_altitude(v as f32, units) as f64)),
+            optional(lap.descent.map(|v| cvt_altitude(v as f32, units) as f64)),
+        ];
+        for (col, text) in cells.iter().enumerate() {
+            attach_cell(&grid, text, col as i32, row, false);
+        }
+    }
+
+    // Work and recovery totals of this activity.
+    let summary = summarize_laps(laps);
+    let mut row = laps.len() as i32 + 2;
+    for (kind, count, distance, time, speed) in [
+        (
+            LapKind::Work,
+            summary.work_count,
+            summary.work_distance,
+            summary.work_time,
+            summary.work_speed(),
+        ),
+        (
+            LapKind::Recovery,
+            summary.recovery_count,
+            summary.recovery_distance,
+            summary.recovery_time,
+            summary.recovery_speed(),
+        ),
+    ] {
+        if count == 0 {
+            continue;
+        }
+        let cells = [
+            format!("{} ({})", lap_kind_label(kind), count),
+            String::new(),
+            format!("{:.2}", cvt_distance(distance as f32, units)),
+            format_duration(time),
+            format_pace(cvt_pace(speed as f32, units) as f64),
+        ];
+        for (col, text) in cells.iter().enumerate() {
+            attach_cell(&grid, text, col as i32, row, true);
+        }
+        row += 1;
+    }
+    let scrolled_window = ScrolledWindow::builder()
+        .vexpand(true)
+        .hexpand(true)
+        .child(&grid)
+        .build();
+    scrolled_window.upcast()
+}
+
+// Draw one line chart per metric present in the records.
+fn draw_record_charts(
+    records: &[Record],
+    units: &Units,
+    by_distance: bool,
+    cr: &Context,
+    width: f64,
+    height: f64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let series: Vec<(RecordMetric, Vec<(f64, f64)>)> = record_metrics(units)
+        .into_iter()
+        .map(|metric| {
+            let points = records
+                .iter()
+                .filter_map(|r| Some((record_x(r, by_distance, units)?, (metric.value)(r, units)?)))
+                .collect::<Vec<_>>();
+            (metric, points)
+        })
+        .filter(|(_, points)| points.len() > 1)
+        .collect();
+    if series.is_empty() {
+        return Ok(());
+    }
+
+    let root =
+        plotters_cairo::CairoBackend::new(cr, (width as u32, height as u32))?.into_drawing_area();
+    let areas = root.split_evenly((series.len(), 1));
+    let is_dark = StyleManager::default().is_dark();
+    let (text_color, axis_color) = if is_dark {
+        (&GREY_200, GREY_400)
+    } else {
+        (&GREY_800, GREY_600)
+    };
+    let x_desc = if by_distance {
+        match units {
+            Units::Metric => tr("UNIT_KM", None),
+            _ => tr("UNIT_MILES", None),
+        }
+    } else {
+        tr("UNIT_MINUTES", None)
+    };
+
+    for ((metric, points), area) in series.iter().zip(areas.iter()) {
+        let x_max = points.iter().map(|(x, _)| *x).fold(f64::MIN, f64::max);
+        let x_min = points.iter().map(|(x, _)| *x).fold(f64::MAX, f64::min);
+        let y_max = points.iter().map(|(_, y)| *y).fold(f64::MIN, f64::max);
+        let y_min = points.iter().map(|(_, y)| *y).fold(f64::MAX, f64::min);
+        let y_pad = ((y_max - y_min) * 0.1).max(1.0);
+
+        let mut chart = ChartBuilder::on(area)
+            .caption(
+                &metric.caption,
+                ("sans-serif", 16, text_color).into_text_style(area),
+            )
+            .margin(10)
+            .x_label_area_size(35)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_min..x_max, (y_min - y_pad)..(y_max + y_pad))?;
+        chart
+            .configure_mesh()
+            .x_labels(10)
+            .y_labels(5)
+            .x_label_style(("sans-serif", 10, text_color).into_text_style(area))
+            .y_label_style(("sans-serif", 10, text_color).into_text_style(area))
+            .x_desc(x_desc.as_str())
+            .y_desc(metric.unit.as_str())
+            .axis_style(axis_color.mix(1.0))
+            .draw()?;
+        chart.draw_series(LineSeries::new(points.iter().cloned(), &metric.color))?;
+    }
+    root.present()?;
+    Ok(())
+}
+
+// Build the per-record charts with a choice of distance or time for the X axis.
+fn build_chart_page(records: Vec<Record>, units: &Units) -> gtk4::Widget {
+    if records.is_empty() {
+        return Label::new(Some(&tr("MESSAGE_NO_RECORDS", None))).upcast();
+    }
+    let records = Rc::new(records);
+    let units = units.clone();
+    let x_axis_widget = DropDown::from_strings(&[
+        &tr("LABEL_X_AXIS_DISTANCE", None),
+        &tr("LABEL_X_AXIS_TIME", None),
+    ]);
+    x_axis_widget.set_halign(gtk4::Align::Start);
+    x_axis_widget.set_margin_start(10);
+    let charted = record_metrics(&units)
+        .iter()
+        .filter(|m| records.iter().any(|r| (m.value)(r, &units).is_some()))
+        .count() as i32;
+    let da = DrawingArea::builder()
+        .hexpand(true)
+        .content_height(RECORD_CHART_HEIGHT * charted.max(1))
+        .build();
+    da.set_draw_func(clone!(
+        #[strong]
+        records,
+        #[strong]
+        x_axis_widget,
+        move |_drawing_area, cr, width, height| {
+            let by_distance = x_axis_widget.selected() == 0;
+            let _ = draw_record_charts(
+                &records,
+                &units,
+                by_distance,
+                cr,
+                width as f64,
+                height as f64,
+            );
+        }
+    ));
+    x_axis_widget.connect_selected_notify(clone!(
+        #[weak]
+        da,
+        move |_| da.queue_draw()
+    ));
+
+    let page = gtk4::Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .margin_top(6)
+        .build();
+    page.append(&x_axis_widget);
+    page.append(
+        &ScrolledWindow::builder()
+            .vexpand(true)
+            .hexpand(true)
+            .child(&da)
+            .build(),
+    );
+    page.upcast()
+}
+
+// Open a window with the details of one activity. The file is read once,
+// on a worker thread, and the pages added when it is.
+pub fn show_activity_detail(
+    parent: &ApplicationWindow,
+    units: &Units,
+    timestamp: DateTime<Utc>,
+    path: &Path,
+) {
+    let notebook = Notebook::builder().vexpand(true).hexpand(true).build();
+    let spinner = Spinner::builder()
+        .spinning(true)
+        .vexpand(true)
+        .width_request(32)
+        .height_request(32)
+        .build();
+    notebook.append_page(&spinner, None::<&Label>);
+    notebook.set_show_tabs(false);
+    let units = units.clone();
+    let file = path.to_path_buf();
+    glib::MainContext::default().spawn_local(clone!(
+        #[weak]
+        notebook,
+        async move {
+            let activity = gtk4::gio::spawn_blocking(move || read_activity(&file))
+                .await
+                .expect("Worker thread panicked")
+                .unwrap_or_default();
+            notebook.remove_page(Some(0));
+            notebook.set_show_tabs(true);
+            notebook.append_page(
+                &build_lap_page(&activity.laps, &units),
+                Some(&Label::new(Some(&tr("DETAIL_TAB_LAPS", None)))),
+            );
+            notebook.append_page(
+                &build_chart_page(activity.records, &units),
+                Some(&Label::new(Some(&tr("DETAIL_TAB_CHARTS", None)))),
+            );
+        }
+    ));
+
+    let content = gtk4::Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(6)
+        .build();
+    let file_label = Label::builder()
+        .label(path.to_string_lossy())
+        .halign(gtk4::Align::Start)
+        .margin_start(10)
+        .margin_top(6)
+        .selectable(true)
+        .build();
+    content.append(&file_label);
+    content.append(&notebook);
+
+    let window = Window::builder()
+        .transient_for(parent)
+        .title(timestamp.format("%Y-%m-%d %H:%M").to_string())
+        .default_width(800)
+        .default_height(700)
+        .child(&content)
+        .build();
+    window.present();
+}
@@ -1,8 +1,46 @@
 // User interface logic - setup, drawing, formatting.
 
-use crate::config::{ICON_NAME, PROGRAM_NAME, SETTINGSFILE, Units, load_config};
-use crate::data::{PlottableData, TimeBucket, convert_session_data, get_time_range};
-use crate::i18n::tr;
+use crate::config::{
+    ChartConfig, ChartKind, DATABASEFILE, ICON_NAME, PROGRAM_NAME, SETTINGSFILE, TrendKind,
+    WindowConfig, load_config, save_config,
+};
+use crate::correction::show_correction_dialog;
+use crate::manual::show_manual_dialog;
+use crate::map::{HEATMAP_EXPORT_SIZE, MapView};
+use crate::show_error_dialog;
+use crate::table::{ActivityRow, ActivityTable};
+use curro_all_core::calendar::{
+    CALENDAR_LEFT, CALENDAR_TOP, CalendarLayout, CalendarMetric, DayTotals, WEEK_START,
+    daily_totals,
+};
+use curro_all_core::data::{
+    PlottableData, SessionStats, TimeBucket, Units, convert_all_stats, cvt_distance, cvt_pace,
+    get_files_in_range, get_metric_vec, get_time_range, read_activity,
+};
+use curro_all_core::dem::ElevationSource;
+use curro_all_core::digest::{ActivityDigest, DigestCache, DigestSettings, digest_stats};
+use curro_all_core::fitness::{
+    ActivityFitness, FitnessPoint, RACES, RECENT_DAYS, fitness_timeline,
+};
+use curro_all_core::gear::{Gear, GearActivity, GearUsage, collect_gear_activities, gear_usage};
+use curro_all_core::i18n::tr;
+use curro_all_core::laps::{LapSummary, summarize_laps};
+use curro_all_core::map::TileSource;
+use curro_all_core::metrics::{Metric, cumulative};
+use curro_all_core::sidecar::{
+    ActivityMeta, ManualActivity, Sidecar, is_manual_path, library_sidecar,
+};
+use curro_all_core::source::ActivityInfo;
+use curro_all_core::statistics::Weighting;
+#[cfg(feature = "sqlite")]
+use curro_all_core::store::Store;
+use curro_all_core::trend::{
+    LinearTrend, linear_trend, loess, moving_average_by_count, moving_average_by_days,
+};
+use curro_all_core::volume::{Period, PeriodVolume, period_volumes, write_volume_csv};
+use curro_all_core::zones::{
+    ActivityZones, ZONE_COUNT, ZoneTimes, total_zones, zone_series, zones_by_week,
+};
 use dashmap::DashMap;
 use directories::BaseDirs;
 use gtk4::cairo::Context;
@@ -10,37 +48,240 @@ use gtk4::ffi::GTK_STYLE_PROVIDER_PRIORITY_APPLICATION;
 use gtk4::glib::clone;
 use gtk4::prelude::*;
 use gtk4::{
-    Button, DrawingArea, DropDown, Frame, HeaderBar, Image, Label, MenuButton, Orientation,
-    Popover, ScrolledWindow, Spinner, StringList, StringObject, gdk,
+    Button, DrawingArea, DropDown, Frame, HeaderBar, Image, Label, MenuButton, Notebook,
+    Orientation, Popover, ScrolledWindow, Spinner, StringList, StringObject, ToggleButton, gdk,
 };
 use libadwaita::prelude::*;
 use libadwaita::{Application, ApplicationWindow, StyleManager, WindowTitle};
 use plotters::prelude::*;
-use plotters::style::full_palette::{BROWN, CYAN, GREY_200, GREY_400, GREY_600, GREY_800};
+use plotters::style::full_palette::{GREY_200, GREY_400, GREY_600, GREY_800};
 use plotters_cairo::CairoBackend;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::Path;
 use std::rc::Rc;
+use std::sync::Arc;
 
-use chrono::{DateTime, Utc};
-use rayon::prelude::*;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
 use std::path::PathBuf;
 
 // Import types from our data module
-use crate::data::{SessionStats, extract_session_data, get_filtered_variants};
+use curro_all_core::data::get_filtered_variants;
 
 // #####################################################################
 // ##################### OVERALL UI FUNCTIONS ##########################
 // #####################################################################
 //
+// Colors of zones 1 to 5 in the zone charts.
+const HR_ZONE_COLORS: [RGBColor; 5] = [
+    RGBColor(150, 150, 150),
+    RGBColor(60, 130, 220),
+    RGBColor(60, 180, 80),
+    RGBColor(240, 160, 30),
+    RGBColor(220, 50, 40),
+];
+
+// Time a bar of the metric charts covers.
+const BAR_WIDTH: chrono::Duration = chrono::Duration::hours(12);
+// How far (pixels) from a bar the pointer still picks it.
+const BAR_HIT_SLACK: f64 = 4.0;
+// Zoom factor of one scroll step.
+const CHART_ZOOM_STEP: f64 = 1.25;
+// The charts never zoom in past this span.
+const MIN_CHART_SPAN: chrono::Duration = chrono::Duration::days(1);
+// Drags shorter than this (pixels) are clicks.
+const CLICK_DISTANCE: f64 = 3.0;
+
+// Color of the trend lines.
+const TREND_COLOR: RGBColor = RGBColor(230, 80, 20);
+
+// Index of the calendar and the fitness charts in the graph view dropdown.
+const CALENDAR_VIEW: u32 = 1;
+const FITNESS_VIEW: u32 = 2;
+
+// Colors of the VO2max chart and of the race predictions.
+const VO2MAX_COLOR: RGBColor = RGBColor(233, 30, 99);
+const RACE_COLORS: [RGBColor; RACES.len()] = [
+    RGBColor(33, 150, 243),
+    RGBColor(0, 150, 136),
+    RGBColor(255, 152, 0),
+    RGBColor(156, 39, 176),
+];
+
+// Calendar cell colors: days without training, then from light to heavy.
+const CALENDAR_EMPTY_LIGHT: RGBColor = RGBColor(235, 237, 240);
+const CALENDAR_EMPTY_DARK: RGBColor = RGBColor(50, 50, 50);
+const CALENDAR_LEVELS_LIGHT: [RGBColor; 4] = [
+    RGBColor(155, 233, 168),
+    RGBColor(64, 196, 99),
+    RGBColor(48, 161, 78),
+    RGBColor(33, 110, 57),
+];
+const CALENDAR_LEVELS_DARK: [RGBColor; 4] = [
+    RGBColor(14, 68, 41),
+    RGBColor(0, 109, 50),
+    RGBColor(38, 166, 65),
+    RGBColor(57, 211, 83),
+];
+
+// Short month names, January first.
+const MONTH_KEYS: [&str; 12] = [
+    "MONTH_JAN",
+    "MONTH_FEB",
+    "MONTH_MAR",
+    "MONTH_APR",
+    "MONTH_MAY",
+    "MONTH_JUN",
+    "MONTH_JUL",
+    "MONTH_AUG",
+    "MONTH_SEP",
+    "MONTH_OCT",
+    "MONTH_NOV",
+    "MONTH_DEC",
+];
+
+// Picks one kind of zone times (heart rate, pace or power) of an activity.
+type ZoneKind = fn(&ActivityZones) -> Option<ZoneTimes>;
+
+// The axis of a chart when a drag starts on it, and its seconds per pixel.
+type DragOrigin = (DateTime<Utc>, DateTime<Utc>, f64);
+
+// A metric chart of the configured layout, with the values it plots.
+pub struct MetricChart {
+    metric: &'static Metric,
+    // Position of the chart in the settings file.
+    index: usize,
+    settings: ChartConfig,
+    color: RGBColor,
+    plotvals: Vec<(DateTime<Utc>, f64)>,
+}
+
+impl MetricChart {
+    // None when the settings name a metric that does not exist.
+    fn new(index: usize, chart: &ChartConfig, stats: &[PlottableData]) -> Option<Self> {
+        let metric = chart.metric()?;
+        let (r, g, b) = chart.rgb()?;
+        let mut plotvals = get_metric_vec(stats, metric.value);
+        if chart.kind == ChartKind::Cumulative {
+            let totals = cumulative(&plotvals, metric.additive);
+            plotvals
+                .iter_mut()
+                .zip(totals)
+                .for_each(|((_, v), total)| *v = total);
+        }
+        Some(MetricChart {
+            metric,
+            index,
+            settings: chart.clone(),
+            color: RGBColor(r, g, b),
+            plotvals,
+        })
+    }
+}
+
+// Data of the zone charts below the metric graphs.
+struct ZoneCharts {
+    hr_weeks: Vec<(NaiveDate, ZoneTimes)>,
+    pace: Option<ZoneTimes>,
+    power: Option<ZoneTimes>,
+}
+
+impl ZoneCharts {
+    fn new(zones: &[(DateTime<Utc>, ActivityZones)]) -> Self {
+        let totals = |kind: ZoneKind| {
+            let series = zone_series(zones, kind);
+            (!series.is_empty()).then(|| total_zones(&series))
+        };
+        ZoneCharts {
+            hr_weeks: zones_by_week(&zone_series(zones, |z| z.hr)),
+            pace: totals(|z| z.pace),
+            power: totals(|z| z.power),
+        }
+    }
+
+    fn count(&self) -> usize {
+        usize::from(!self.hr_weeks.is_empty())
+            + usize::from(self.pace.is_some())
+            + usize::from(self.power.is_some())
+    }
+}
+
+// Inputs of the summary table, kept so that clicking a calendar day can
+// narrow it down without re-reading the whole range.
+#[derive(Default)]
+pub struct SummaryState {
+    stats: Vec<PlottableData>,
+    digests: Vec<(DateTime<Utc>, Arc<ActivityDigest>)>,
+    zones: Vec<(DateTime<Utc>, ActivityZones)>,
+    // The calendar day the summary is filtered to, if any.
+    day: Option<NaiveDate>,
+    // The activity clicked in a chart, highlighted in the summary.
+    activity: Option<DateTime<Utc>>,
+}
+
+// Where a metric chart was last drawn, to map pointer positions back to
+// times and activities.
+struct ChartFrame {
+    // Plotting area, in drawing area pixels.
+    x_px: (i32, i32),
+    y_px: (i32, i32),
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    plotvals: Vec<(DateTime<Utc>, f64)>,
+    metric: &'static Metric,
+    metric_name: String,
+    unit_label: String,
+    // Index of the chart in UserInterface::metric_charts.
+    chart: usize,
+}
+
+impl ChartFrame {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x_px.0 as f64
+            && x <= self.x_px.1 as f64
+            && y >= self.y_px.0 as f64
+            && y <= self.y_px.1 as f64
+    }
+
+    // Seconds of the time axis per pixel.
+    fn seconds_per_pixel(&self) -> f64 {
+        (self.end - self.start).num_seconds() as f64 / (self.x_px.1 - self.x_px.0).max(1) as f64
+    }
+
+    fn time_at(&self, x: f64) -> DateTime<Utc> {
+        let seconds = (x - self.x_px.0 as f64) * self.seconds_per_pixel();
+        self.start + chrono::Duration::seconds(seconds as i64)
+    }
+
+    // The bar under a horizontal position, or the nearest one a few pixels away.
+    fn bar_at(&self, x: f64) -> Option<(DateTime<Utc>, f64)> {
+        let time = self.time_at(x);
+        let slack = chrono::Duration::seconds((BAR_HIT_SLACK * self.seconds_per_pixel()) as i64);
+        self.plotvals
+            .iter()
+            .filter(|(date, _)| time >= *date - slack && time <= *date + BAR_WIDTH + slack)
+            .min_by_key(|(date, _)| (time - (*date + BAR_WIDTH / 2)).num_seconds().abs())
+            .copied()
+    }
+}
+
 // Widgets used for the graphical user interface.
 pub struct UserInterface {
     pub settings_file: String,
+    pub database_file: String,
+    // Titles, tags, notes and exertion the user added to the activities of
+    // the library opened; the configuration directory's before any is.
+    pub sidecar_file: RefCell<PathBuf>,
+    pub sidecar: RefCell<Sidecar>,
     pub win: ApplicationWindow,
     pub header_bar: HeaderBar,
     pub menu_button: gtk4::MenuButton,
     pub popover: gtk4::Popover,
     pub spinner: Spinner,
     pub time_widget: DropDown,
+    // Narrows the range to the activities with a tag.
+    pub tag_widget: DropDown,
     pub status_label: Label,
     pub menu_box: gtk4::Box,
     pub outer_box: gtk4::Box,
@@ -50,7 +291,28 @@ pub struct UserInterface {
     // pub text_view: TextView,
     // pub text_buffer: TextBuffer,
     pub main_grid: gtk4::Grid, // Replaces TextView
+    // One row per activity, in scrolled_window.
+    pub table: Rc<ActivityTable>,
     pub scrolled_window: ScrolledWindow,
+    // Filter bar, table and, pinned below, the aggregates.
+    pub summary_box: gtk4::Box,
+    pub footer_window: ScrolledWindow,
+    // Activities and weekly/monthly volume pages of the summary.
+    pub summary_notebook: Notebook,
+    pub volume_box: gtk4::Box,
+    pub volume_toolbar: gtk4::Box,
+    pub volume_period_widget: DropDown,
+    pub volume_export_btn: Button,
+    pub volume_grid: gtk4::Grid,
+    pub volume_window: ScrolledWindow,
+    // Gear page: usage table above the distance chart.
+    pub gear_box: gtk4::Box,
+    pub gear_grid: gtk4::Grid,
+    pub gear_da: DrawingArea,
+    gear_usage: Rc<RefCell<Vec<GearUsage>>>,
+    // The library activities gear is assigned from, and the fingerprint of
+    // the index they were read for.
+    gear_activities: RefCell<(Option<u64>, Vec<GearActivity>)>,
     pub frame_left: Frame,
     pub frame_right: Frame,
     pub left_frame_pane: gtk4::Paned,
@@ -63,13 +325,58 @@ pub struct UserInterface {
     pub about_label: String,
     pub about_btn: Button,
     pub da: DrawingArea,
+    pub graph_view_widget: DropDown,
+    pub calendar_metric_widget: DropDown,
+    pub calendar_reset_btn: Button,
+    // Device or DEM ascent and descent, shown when a DEM is configured.
+    pub elevation_widget: DropDown,
+    // What the views show about each activity, read on worker threads.
+    digests: Arc<DigestCache>,
+    // Bumped on each update of the views, so that a slower earlier one
+    // finishing last does not replace it.
+    update_generation: Cell<u64>,
+    pub summary_state: RefCell<SummaryState>,
+    // Zoomed and panned time axis of the charts, None for the whole range.
+    pub chart_axis: Cell<Option<(DateTime<Utc>, DateTime<Utc>)>>,
+    metric_charts: RefCell<Vec<MetricChart>>,
+    // Fitness estimates of the range, read with the rest of the range.
+    fitness: RefCell<Vec<FitnessPoint>>,
+    chart_frames: RefCell<Vec<ChartFrame>>,
+    pub map_frame: Frame,
+    pub map_box: gtk4::Box,
+    pub map_toolbar: gtk4::Box,
+    pub heatmap_toggle: ToggleButton,
+    pub privacy_toggle: ToggleButton,
+    pub heatmap_export_btn: Button,
+    pub map_da: DrawingArea,
+    pub map_view: Rc<RefCell<MapView>>,
     pub lookup: DashMap<DateTime<Utc>, PathBuf>,
 }
 
 // Instantiate the object holding the widgets (views).
 pub fn instantiate_ui(app: &Application) -> UserInterface {
-    let mut ui = UserInterface {
-        settings_file: String::from(SETTINGSFILE),
+    let mut settings_file = String::from(SETTINGSFILE);
+    let mut database_file = String::from(DATABASEFILE);
+    let mut library = PathBuf::new();
+    // query paths of user-invisible standard directories.
+    let base_dirs = BaseDirs::new();
+    if base_dirs.is_some() {
+        let config_dir = base_dirs.unwrap().config_dir().to_path_buf();
+        settings_file = config_dir.join(SETTINGSFILE).to_string_lossy().to_string();
+        database_file = config_dir.join(DATABASEFILE).to_string_lossy().to_string();
+        library = config_dir;
+    }
+    let opened = load_config(Path::new(&settings_file)).library;
+    if !opened.is_empty() {
+        library = PathBuf::from(opened);
+    }
+    let sidecar_file = library_sidecar(&library);
+    let ui = UserInterface {
+        table: ActivityTable::new(&settings_file),
+        settings_file,
+        database_file,
+        sidecar: RefCell::new(Sidecar::load(&sidecar_file).unwrap_or_default()),
+        sidecar_file: RefCell::new(sidecar_file),
         win: ApplicationWindow::builder()
             .application(app)
             .title(PROGRAM_NAME)
@@ -95,6 +402,12 @@ pub fn instantiate_ui(app: &Application) -> UserInterface {
             .width_request(100)
             .visible(false)
             .build(),
+        tag_widget: DropDown::builder()
+            .margin_top(5)
+            .margin_bottom(5)
+            .height_request(30)
+            .visible(false)
+            .build(),
         status_label: Label::new(Some("")),
         menu_box: gtk4::Box::builder()
             .orientation(Orientation::Vertical)
@@ -155,7 +468,59 @@ pub fn instantiate_ui(app: &Application) -> UserInterface {
             .resize_start_child(true)
             .resize_end_child(false)
             .build(),
-        scrolled_window: ScrolledWindow::builder().margin_top(5).build(),
+        scrolled_window: ScrolledWindow::builder()
+            .margin_top(5)
+            .vexpand(true)
+            .build(),
+        summary_box: gtk4::Box::builder()
+            .orientation(Orientation::Vertical)
+            .build(),
+        footer_window: ScrolledWindow::builder()
+            .propagate_natural_height(true)
+            .max_content_height(250)
+            .build(),
+        summary_notebook: Notebook::builder().vexpand(true).hexpand(true).build(),
+        volume_box: gtk4::Box::builder()
+            .orientation(Orientation::Vertical)
+            .build(),
+        volume_toolbar: gtk4::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(10)
+            .margin_start(5)
+            .margin_top(5)
+            .build(),
+        volume_period_widget: DropDown::builder()
+            .model(&StringList::new(&[
+                &tr("VOLUME_PERIOD_WEEK", None),
+                &tr("VOLUME_PERIOD_MONTH", None),
+            ]))
+            .build(),
+        volume_export_btn: Button::builder().label(tr("VOLUME_EXPORT", None)).build(),
+        volume_grid: gtk4::Grid::builder()
+            .column_spacing(12)
+            .row_spacing(6)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(10)
+            .build(),
+        volume_window: ScrolledWindow::builder().vexpand(true).build(),
+        gear_box: gtk4::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(10)
+            .build(),
+        gear_grid: gtk4::Grid::builder()
+            .column_spacing(12)
+            .row_spacing(6)
+            .margin_start(10)
+            .margin_end(10)
+            .margin_top(10)
+            .build(),
+        gear_da: DrawingArea::builder()
+            .height_request(200)
+            .vexpand(true)
+            .build(),
+        gear_usage: Rc::new(RefCell::new(Vec::new())),
+        gear_activities: RefCell::new((None, Vec::new())),
         da_window: ScrolledWindow::builder()
             .vexpand(true)
             .hexpand(true)
@@ -188,10 +553,80 @@ pub fn instantiate_ui(app: &Application) -> UserInterface {
             .width_request(400)
             .margin_end(10)
             .build(),
+        graph_view_widget: DropDown::builder()
+            .margin_top(5)
+            .margin_bottom(5)
+            .height_request(30)
+            .model(&StringList::new(&[
+                &tr("GRAPH_VIEW_CHARTS", None),
+                &tr("GRAPH_VIEW_CALENDAR", None),
+                &tr("GRAPH_VIEW_FITNESS", None),
+            ]))
+            .build(),
+        calendar_metric_widget: DropDown::builder()
+            .margin_top(5)
+            .margin_bottom(5)
+            .height_request(30)
+            .model(&StringList::new(&[
+                &tr("CALENDAR_METRIC_DISTANCE", None),
+                &tr("CALENDAR_METRIC_DURATION", None),
+                &tr("CALENDAR_METRIC_LOAD", None),
+            ]))
+            .visible(false)
+            .build(),
+        calendar_reset_btn: Button::builder()
+            .label(tr("CALENDAR_SHOW_ALL", None))
+            .margin_top(5)
+            .margin_bottom(5)
+            .visible(false)
+            .build(),
+        elevation_widget: DropDown::builder()
+            .margin_top(5)
+            .margin_bottom(5)
+            .height_request(30)
+            .model(&StringList::new(&[
+                &tr("ELEVATION_DEVICE", None),
+                &tr("ELEVATION_DEM", None),
+            ]))
+            .visible(false)
+            .build(),
+        digests: Arc::new(DigestCache::new()),
+        update_generation: Cell::new(0),
+        summary_state: RefCell::new(SummaryState::default()),
+        chart_axis: Cell::new(None),
+        metric_charts: RefCell::new(Vec::new()),
+        fitness: RefCell::new(Vec::new()),
+        chart_frames: RefCell::new(Vec::new()),
+        map_frame: Frame::builder().margin_top(5).build(),
+        map_box: gtk4::Box::builder()
+            .orientation(Orientation::Vertical)
+            .build(),
+        map_toolbar: gtk4::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(10)
+            .margin_start(5)
+            .margin_top(5)
+            .margin_bottom(5)
+            .build(),
+        heatmap_toggle: ToggleButton::builder()
+            .label(tr("HEATMAP_TOGGLE", None))
+            .build(),
+        privacy_toggle: ToggleButton::builder()
+            .label(tr("PRIVACY_PREVIEW_TOGGLE", None))
+            .build(),
+        heatmap_export_btn: Button::builder().label(tr("HEATMAP_EXPORT", None)).build(),
+        map_da: DrawingArea::builder()
+            .width_request(300)
+            .height_request(200)
+            .vexpand(true)
+            .hexpand(true)
+            .build(),
+        map_view: Rc::new(RefCell::new(MapView::new(Vec::new(), None, Vec::new()))),
         lookup: DashMap::new(),
     };
     let provider = gtk4::CssProvider::new();
-    let css_data = "textview { font: 14px monospace; font-weight: 500;}";
+    let css_data = "textview { font: 14px monospace; font-weight: 500;}
+        .selected-activity { background-color: alpha(@accent_bg_color, 0.3); }";
     provider.load_from_data(css_data);
     gtk4::style_context_add_provider_for_display(
         &gdk::Display::default().expect("Could not get default display."),
@@ -200,7 +635,37 @@ pub fn instantiate_ui(app: &Application) -> UserInterface {
     );
     ui.about_btn.set_label(&ui.about_label);
     ui.units_widget.set_model(Some(&ui.uom));
-    ui.scrolled_window.set_child(Some(&ui.main_grid));
+    ui.scrolled_window.set_child(Some(&ui.table.view));
+    let footer_box = gtk4::Box::new(Orientation::Vertical, 10);
+    footer_box.append(&ui.table.footer);
+    footer_box.append(&ui.main_grid);
+    ui.footer_window.set_child(Some(&footer_box));
+    ui.summary_box.append(&ui.table.filter_bar);
+    ui.summary_box.append(&ui.scrolled_window);
+    ui.summary_box.append(&ui.footer_window);
+    ui.volume_period_widget
+        .set_tooltip_text(Some(&tr("TOOLTIP_VOLUME_PERIOD", None)));
+    ui.volume_export_btn
+        .set_tooltip_text(Some(&tr("TOOLTIP_VOLUME_EXPORT", None)));
+    ui.volume_toolbar.append(&ui.volume_period_widget);
+    ui.volume_toolbar.append(&ui.volume_export_btn);
+    ui.volume_window.set_child(Some(&ui.volume_grid));
+    ui.volume_box.append(&ui.volume_toolbar);
+    ui.volume_box.append(&ui.volume_window);
+    ui.summary_notebook.append_page(
+        &ui.summary_box,
+        Some(&Label::new(Some(&tr("SUMMARY_TAB_ACTIVITIES", None)))),
+    );
+    ui.summary_notebook.append_page(
+        &ui.volume_box,
+        Some(&Label::new(Some(&tr("SUMMARY_TAB_VOLUME", None)))),
+    );
+    ui.gear_box.append(&ui.gear_grid);
+    ui.gear_box.append(&ui.gear_da);
+    ui.summary_notebook.append_page(
+        &ui.gear_box,
+        Some(&Label::new(Some(&tr("SUMMARY_TAB_GEAR", None)))),
+    );
     ui.scrolled_window
         .set_tooltip_text(Some(&tr("TOOLTIP_TEXT_VIEW", None)));
     ui.about_btn
@@ -238,26 +703,45 @@ pub fn instantiate_ui(app: &Application) -> UserInterface {
     ui.button_box.append(&ui.spinner);
     ui.button_box.append(&ui.status_label);
     ui.button_box.append(&ui.time_widget);
+    ui.tag_widget
+        .set_tooltip_text(Some(&tr("TOOLTIP_TAG_FILTER", None)));
+    refresh_tag_widget(&ui);
+    ui.button_box.append(&ui.tag_widget);
     ui.button_box.append(&ui.controls_box);
     ui.outer_box.append(&ui.button_box);
     ui.outer_box.append(&ui.main_pane);
     ui.controls_box.append(&ui.curr_time_label);
+    ui.graph_view_widget
+        .set_tooltip_text(Some(&tr("TOOLTIP_GRAPH_VIEW", None)));
+    ui.calendar_metric_widget
+        .set_tooltip_text(Some(&tr("TOOLTIP_CALENDAR_METRIC", None)));
+    ui.calendar_reset_btn
+        .set_tooltip_text(Some(&tr("TOOLTIP_CALENDAR_SHOW_ALL", None)));
+    ui.controls_box.append(&ui.graph_view_widget);
+    ui.controls_box.append(&ui.calendar_metric_widget);
+    ui.controls_box.append(&ui.calendar_reset_btn);
+    ui.elevation_widget
+        .set_tooltip_text(Some(&tr("TOOLTIP_ELEVATION_SOURCE", None)));
+    ui.controls_box.append(&ui.elevation_widget);
 
-    ui.frame_left
+    ui.map_frame
         .set_tooltip_text(Some(&tr("TOOLTIP_MAP_FRAME", None)));
+    ui.heatmap_toggle
+        .set_tooltip_text(Some(&tr("TOOLTIP_HEATMAP_TOGGLE", None)));
+    ui.heatmap_export_btn
+        .set_tooltip_text(Some(&tr("TOOLTIP_HEATMAP_EXPORT", None)));
+    ui.privacy_toggle
+        .set_tooltip_text(Some(&tr("TOOLTIP_PRIVACY_PREVIEW_TOGGLE", None)));
+    ui.map_toolbar.append(&ui.heatmap_toggle);
+    ui.map_toolbar.append(&ui.privacy_toggle);
+    ui.map_toolbar.append(&ui.heatmap_export_btn);
+    ui.map_box.append(&ui.map_toolbar);
+    ui.map_box.append(&ui.map_da);
+    connect_map_widgets(&ui);
     ui.frame_right
         .set_tooltip_text(Some(&tr("TOOLTIP_GRAPH_FRAME", None)));
-    // query paths of user-invisible standard directories.
-    let base_dirs = BaseDirs::new();
-    if base_dirs.is_some() {
-        ui.settings_file = base_dirs
-            .unwrap()
-            .config_dir()
-            .join(SETTINGSFILE)
-            .to_string_lossy()
-            .to_string();
-    }
     set_up_user_defaults(&ui);
+    index_manual_activities(&ui);
     return ui;
 }
 // After reading the fit file, display the additional views of the UI.
@@ -271,9 +755,11 @@ pub fn construct_views_from_data(
     // 2. Connect embedded widgets to their parents.
     ui.da_window.set_child(Some(&ui.da));
     ui.frame_right.set_child(Some(&ui.da_window));
-    ui.frame_left.set_child(Some(&ui.scrolled_window));
+    ui.frame_left.set_child(Some(&ui.summary_notebook));
+    ui.map_frame.set_child(Some(&ui.map_box));
     // 3. Configure the widget layout.
     ui.left_frame_pane.set_start_child(Some(&ui.frame_left));
+    ui.left_frame_pane.set_end_child(Some(&ui.map_frame));
     ui.right_frame_pane.set_start_child(Some(&ui.frame_right));
     // Main box contains all of the above plus the graphs.
     ui.main_pane.set_start_child(Some(&ui.left_frame_pane));
@@ -298,6 +784,7 @@ pub fn connect_interactive_widgets(
         move |_| {
             update_map_graph_and_summary_widgets(&ui, &data);
             ui.da.queue_draw();
+            ui.map_da.queue_draw();
         },
     ));
 }
@@ -346,63 +833,90 @@ pub fn get_selected_start_end(ui: &UserInterface) -> (DateTime<Utc>, DateTime<Ut
     return (Utc::now(), Utc::now());
 }
 
+// Describe the slope of a trend per month, e.g. "improving 0:04 min/km per
+// month" for pace, or "+12.5 kilometers per month".
+fn trend_summary(metric: &Metric, trend: &LinearTrend, unit_label: &str) -> String {
+    let per_month = trend.slope_per_month();
+    if metric.lower_is_better {
+        let direction = if per_month <= 0.0 {
+            tr("TREND_IMPROVING", None)
+        } else {
+            tr("TREND_DECLINING", None)
+        };
+        let amount = if metric.name == "pace" {
+            format_pace(per_month.abs())
+        } else {
+            format!("{:.1}", per_month.abs())
+        };
+        format!(
+            "{} {} {} {}",
+            direction,
+            amount,
+            unit_label,
+            tr("TREND_PER_MONTH", None)
+        )
+    } else {
+        format!(
+            "{:+.1} {} {}",
+            per_month,
+            unit_label,
+            tr("TREND_PER_MONTH", None)
+        )
+    }
+}
+
+// Return the time axis of the charts: the zoomed part of the selected range
+// if the user zoomed in, else the whole range.
+fn get_chart_time_axis(ui: &UserInterface) -> (DateTime<Utc>, DateTime<Utc>) {
+    ui.chart_axis
+        .get()
+        .unwrap_or_else(|| get_selected_start_end(ui))
+}
+
 // #####################################################################
 // ##################### GRAPH FUNCTIONS ###############################
 // #####################################################################
 //
 //
-// Perform this ONCE in main.rs
-pub fn collect_all_stats(results: &[(DateTime<Utc>, PathBuf)]) -> Vec<PlottableData> {
-    results
-        .into_par_iter()
-        .map(|(ts, path)| PlottableData {
-            timestamp: *ts,
-            stats: extract_session_data(path).unwrap_or_default(),
-        })
-        .collect()
-}
-
-pub fn convert_all_stats(raw_stats: &Vec<PlottableData>, ui: &UserInterface) -> Vec<PlottableData> {
-    let selected_units = get_unit_system(&ui.units_widget);
-    raw_stats
-        .into_par_iter()
-        .map(|plottable_data| PlottableData {
-            timestamp: plottable_data.timestamp,
-            stats: convert_session_data(&plottable_data.stats, &selected_units).unwrap_or_default(),
-        })
-        .collect()
-}
-
-// Convert the above structure to plottable vectors
-pub fn get_metric_vec(
-    all_data: &[PlottableData],
-    value_extractor: fn(&SessionStats) -> f64,
-) -> Vec<(DateTime<Utc>, f64)> {
-    let mut data: Vec<(DateTime<Utc>, f64)> = all_data
-        .iter()
-        .map(|item| (item.timestamp, value_extractor(&item.stats)))
-        .collect();
-
-    // Ensure chronological order for the LineSeries
-    data.sort_by_key(|(ts, _)| *ts);
-    data
-}
-
 /// Generates a bar chart for a specific metric.
 pub fn build_individual_graph(
     ui: &UserInterface,
     a: &plotters::drawing::DrawingArea<CairoBackend<'_>, plotters::coord::Shift>,
-    plotvals: Vec<(DateTime<Utc>, f64)>,
-    metric_name: &str,
-    unit_label: &str,
-    color: &RGBColor,
+    metric_chart: &MetricChart,
+    index: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if plotvals.is_empty() {
+    if metric_chart.plotvals.is_empty() {
         return Ok(());
     }
+    let metric_name = tr(metric_chart.metric.caption, None);
+    let unit_label = (metric_chart.metric.unit)(&get_unit_system(&ui.units_widget));
+    let color = &metric_chart.color;
+    let settings = &metric_chart.settings;
+    let kind = settings.kind;
+    // Overlays are computed over the whole range so zooming does not
+    // change them.
+    let average = settings.show_average.then(|| {
+        if settings.average_by_days {
+            moving_average_by_days(&metric_chart.plotvals, settings.average_window)
+        } else {
+            moving_average_by_count(&metric_chart.plotvals, settings.average_window as usize)
+        }
+    });
+    let linear = settings
+        .show_trend
+        .then(|| linear_trend(&metric_chart.plotvals))
+        .flatten();
+    let loess_curve = (settings.show_trend && settings.trend == TrendKind::Loess)
+        .then(|| loess(&metric_chart.plotvals));
+    let plotvals = metric_chart.plotvals.clone();
 
     let mut num_x_label = 16;
-    let (start_date, end_date) = get_selected_start_end(&ui);
+    let (start_date, end_date) = get_chart_time_axis(ui);
+    // Only the bars in the zoomed part of the axis are drawn.
+    let plotvals: Vec<(DateTime<Utc>, f64)> = plotvals
+        .into_iter()
+        .filter(|(date, _)| *date >= start_date && *date <= end_date)
+        .collect();
 
     // Logic for determining X-axis label density based on timeframe
     if let Some(selected_variant) = get_time_bucket(&ui) {
@@ -422,8 +936,16 @@ pub fn build_individual_graph(
         caption_style = ("sans-serif", 16, &GREY_200).into_text_style(a);
     }
 
+    let caption = match &linear {
+        Some(trend) => format!(
+            "{} ({})",
+            metric_name,
+            trend_summary(metric_chart.metric, trend, &unit_label)
+        ),
+        None => metric_name.clone(),
+    };
     let mut chart = ChartBuilder::on(&a)
-        .caption(format!("{}", metric_name), caption_style)
+        .caption(caption, caption_style)
         .margin(20)
         .x_label_area_size(40)
         .y_label_area_size(60)
@@ -449,273 +971,1355 @@ pub fn build_individual_graph(
         .y_labels(5)
         .y_label_style(axis_text_style.clone())
         .x_label_formatter(&|d| d.format("%m-%d").to_string())
-        .y_desc(unit_label)
+        .y_desc(&unit_label)
         .axis_style(axis_style)
         .draw()?;
 
-    // --- BAR GRAPH LOGIC START ---
-    // We use a Rectangle series to simulate bars.
-    // The width is calculated based on the timeframe to ensure bars don't overlap too much.
-    chart.draw_series(plotvals.iter().map(|(date, val)| {
-        let x0 = *date;
-        // Shift x1 slightly to create bar width (e.g., 1 day or a few hours)
-        let x1 = *date + chrono::Duration::hours(12);
-        let bar_style = color.filled();
-
-        // Optional: add a border to the bars
-        let rect = Rectangle::new([(x0, 0.0), (x1, *val)], bar_style);
-        rect
-    }))?;
-    // --- BAR GRAPH LOGIC END ---
+    let selected = ui.summary_state.borrow().activity;
+    let outline = if is_dark { &GREY_200 } else { &GREY_800 };
+    if kind == ChartKind::Bar {
+        // --- BAR GRAPH LOGIC START ---
+        // We use a Rectangle series to simulate bars.
+        // The width is calculated based on the timeframe to ensure bars don't overlap too much.
+        chart.draw_series(plotvals.iter().map(|(date, val)| {
+            let x0 = *date;
+            // Shift x1 slightly to create bar width (e.g., 1 day or a few hours)
+            let x1 = *date + BAR_WIDTH;
+            let bar_style = color.filled();
+
+            // Optional: add a border to the bars
+            let rect = Rectangle::new([(x0, 0.0), (x1, *val)], bar_style);
+            rect
+        }))?;
+        // Outline the activity selected by a click.
+        chart.draw_series(
+            plotvals
+                .iter()
+                .filter(|(date, _)| Some(*date) == selected)
+                .map(|(date, val)| {
+                    Rectangle::new(
+                        [(*date, 0.0), (*date + BAR_WIDTH, *val)],
+                        outline.stroke_width(2),
+                    )
+                }),
+        )?;
+        // --- BAR GRAPH LOGIC END ---
+    } else {
+        // Points sit in the middle of where the bars would be, so hovering
+        // and clicking work the same for both.
+        let points: Vec<(DateTime<Utc>, f64)> = plotvals
+            .iter()
+            .map(|(date, val)| (*date + BAR_WIDTH / 2, *val))
+            .collect();
+        chart.draw_series(LineSeries::new(points.clone(), color.stroke_width(2)))?;
+        chart.draw_series(points.iter().map(|p| Circle::new(*p, 3, color.filled())))?;
+        chart.draw_series(
+            points
+                .iter()
+                .filter(|(date, _)| Some(*date - BAR_WIDTH / 2) == selected)
+                .map(|p| Circle::new(*p, 6, outline.stroke_width(2))),
+        )?;
+    }
+
+    // Overlays, in the middle of the bars like the line charts.
+    let visible = |points: Vec<(DateTime<Utc>, f64)>| -> Vec<(DateTime<Utc>, f64)> {
+        points
+            .into_iter()
+            .filter(|(date, _)| *date >= start_date && *date <= end_date)
+            .map(|(date, v)| (date + BAR_WIDTH / 2, v))
+            .collect()
+    };
+    if let Some(average) = average {
+        chart.draw_series(LineSeries::new(visible(average), outline.stroke_width(2)))?;
+    }
+    let trend_points = match (&loess_curve, &linear) {
+        (Some(curve), _) => visible(curve.clone()),
+        (None, Some(trend)) => visible(
+            plotvals
+                .iter()
+                .map(|(date, _)| (*date, trend.value_at(*date)))
+                .collect(),
+        ),
+        (None, None) => Vec::new(),
+    };
+    if trend_points.len() > 1 {
+        chart.draw_series(DashedLineSeries::new(
+            trend_points,
+            8,
+            5,
+            TREND_COLOR.stroke_width(2),
+        ))?;
+    }
+
+    let (x_px, y_px) = chart.plotting_area().get_pixel_range();
+    ui.chart_frames.borrow_mut().push(ChartFrame {
+        x_px: (x_px.start, x_px.end),
+        y_px: (y_px.start, y_px.end),
+        start: start_date,
+        end: end_date,
+        plotvals,
+        metric: metric_chart.metric,
+        metric_name,
+        unit_label,
+        chart: index,
+    });
 
     Ok(())
 }
 
-// Use plotters.rs to draw a graph on the drawing area.
-fn draw_graphs(
+// Draw weekly time in zone as stacked bars, zone 1 at the bottom.
+fn build_zone_graph<const N: usize>(
     ui: &UserInterface,
-    distance_plotvals: &Vec<(DateTime<Utc>, f64)>,
-    calories_plotvals: &Vec<(DateTime<Utc>, f64)>,
-    ascent_plotvals: &Vec<(DateTime<Utc>, f64)>,
-    duration_plotvals: &Vec<(DateTime<Utc>, f64)>,
-    pace_plotvals: &Vec<(DateTime<Utc>, f64)>,
-    descent_plotvals: &Vec<(DateTime<Utc>, f64)>,
-    cr: &Context,
-    width: f64,
-    height: f64,
-) {
-    let selected_units = get_unit_system(&ui.units_widget);
-    let root = plotters_cairo::CairoBackend::new(&cr, (width as u32, height as u32))
-        .unwrap()
-        .into_drawing_area();
-    let areas = root.split_evenly((3, 2));
+    a: &plotters::drawing::DrawingArea<CairoBackend<'_>, plotters::coord::Shift>,
+    weeks: &[(NaiveDate, [f64; N])],
+    metric_name: &str,
+    colors: &[RGBColor; N],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (start_date, end_date) = get_chart_time_axis(ui);
+    let week_start = |week: &NaiveDate| week.and_time(NaiveTime::MIN).and_utc();
+    // Hours per week.
+    let max_val = weeks
+        .iter()
+        .map(|(_, times)| times.iter().sum::<f64>() / 3600.0)
+        .fold(0.0, f64::max)
+        * 1.1;
+    let is_dark = StyleManager::default().is_dark();
+    let (text_color, axis_color) = if is_dark {
+        (&GREY_200, GREY_400)
+    } else {
+        (&GREY_800, GREY_600)
+    };
 
-    match selected_units {
-        Units::Metric => {
-            build_individual_graph(
-                &ui,
-                &areas[0],
-                distance_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_DISTANCE", None),
-                &tr("UNIT_KM", None),
-                &GREEN,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[1],
-                calories_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_CALORIES", None),
-                "kcal",
-                &BLUE,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[2],
-                pace_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_PACE", None),
-                &tr("UNIT_PACE_METRIC", None),
-                &BROWN,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[3],
-                duration_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_DURATION", None),
-                "minutes",
-                &RED,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[4],
-                ascent_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_ASCENT", None),
-                &tr("UNIT_METERS", None),
-                &CYAN,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[5],
-                descent_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_DESCENT", None),
-                &tr("UNIT_METERS", None),
-                &YELLOW,
-            )
-            .unwrap();
-        }
-        Units::US => {
-            build_individual_graph(
-                &ui,
-                &areas[0],
-                distance_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_DISTANCE", None),
-                &tr("UNIT_MILES", None),
-                &GREEN,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[1],
-                calories_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_CALORIES", None),
-                "kcal",
-                &BLUE,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[2],
-                pace_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_PACE", None),
-                &tr("UNIT_PACE_US", None),
-                &BROWN,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[3],
-                duration_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_DURATION", None),
-                "minutes",
-                &RED,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[4],
-                ascent_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_ASCENT", None),
-                &tr("UNIT_FEET", None),
-                &CYAN,
-            )
-            .unwrap();
-            build_individual_graph(
-                &ui,
-                &areas[5],
-                descent_plotvals.to_vec(),
-                &tr("GRAPH_CAPTION_DESCENT", None),
-                &tr("UNIT_FEET", None),
-                &YELLOW,
-            )
-            .unwrap();
-        }
-        _ => {}
-    }
+    let mut chart = ChartBuilder::on(a)
+        .caption(
+            metric_name,
+            ("sans-serif", 16, text_color).into_text_style(a),
+        )
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(start_date..end_date, 0.0..max_val.max(1.0))?;
+    chart
+        .configure_mesh()
+        .x_labels(12)
+        .x_label_style(("sans-serif", 10, text_color).into_text_style(a))
+        .y_labels(5)
+        .y_label_style(("sans-serif", 10, text_color).into_text_style(a))
+        .x_label_formatter(&|d| d.format("%m-%d").to_string())
+        .y_desc(tr("UNIT_HOURS", None))
+        .axis_style(axis_color.mix(1.0))
+        .draw()?;
 
-    let _ = root.present();
+    for zone in 0..N {
+        let color = colors[zone];
+        chart
+            .draw_series(
+                weeks
+                    .iter()
+                    .filter(|(week, _)| {
+                        week_start(week) <= end_date
+                            && week_start(week) + chrono::Duration::days(6) >= start_date
+                    })
+                    .map(|(week, times)| {
+                        let below: f64 = times[..zone].iter().sum::<f64>() / 3600.0;
+                        let top = below + times[zone] / 3600.0;
+                        // Weeks cut by a zoomed axis are clipped to it.
+                        let x0 = week_start(week).max(start_date);
+                        let x1 = (week_start(week) + chrono::Duration::days(6)).min(end_date);
+                        Rectangle::new([(x0, below), (x1, top)], color.filled())
+                    }),
+            )?
+            .label(format!("Z{}", zone + 1))
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 10, y + 5)], color.filled()));
+    }
+    chart
+        .configure_series_labels()
+        .label_font(("sans-serif", 10, text_color).into_text_style(a))
+        .border_style(axis_color.mix(1.0))
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()?;
+    Ok(())
 }
 
-// Build the graphs.  Prepare the graphical data for the drawing area and
-// set-up the draw function callback.
-fn build_graphs(stats: &Vec<PlottableData>, ui: &Rc<UserInterface>) {
-    // Need to clone to use inside the closure.
-    let distance_plotvals: Vec<(DateTime<Utc>, f64)> =
-        get_metric_vec(&stats, |s| s.distance as f64);
-    let calories_plotvals: Vec<(DateTime<Utc>, f64)> =
-        get_metric_vec(&stats, |s| s.calories as f64);
-    let ascent_plotvals: Vec<(DateTime<Utc>, f64)> = get_metric_vec(&stats, |s| s.ascent as f64);
-    let duration_plotvals: Vec<(DateTime<Utc>, f64)> =
-        get_metric_vec(&stats, |s| s.duration as f64);
-    let pace_plotvals: Vec<(DateTime<Utc>, f64)> =
-        get_metric_vec(&stats, |s| s.enhanced_speed as f64);
-    let descent_plotvals: Vec<(DateTime<Utc>, f64)> = get_metric_vec(&stats, |s| s.descent as f64);
-    ui.da.set_draw_func(clone!(
-        #[strong]
-        ui,
-        move |_drawing_area, cr, width, height| {
-            draw_graphs(
-                &ui,
-                &distance_plotvals,
-                &calories_plotvals,
-                &ascent_plotvals,
-                &duration_plotvals,
-                &pace_plotvals,
-                &descent_plotvals,
-                cr,
-                width as f64,
-                height as f64,
-            );
-        }
-    ));
-}
+// Draw total time per zone as a histogram.
+fn build_zone_histogram(
+    a: &plotters::drawing::DrawingArea<CairoBackend<'_>, plotters::coord::Shift>,
+    totals: &ZoneTimes,
+    metric_name: &str,
+    colors: &[RGBColor; ZONE_COUNT],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hours = totals.map(|t| t / 3600.0);
+    let max_val = hours.iter().cloned().fold(0.0, f64::max) * 1.1;
+    let is_dark = StyleManager::default().is_dark();
+    let (text_color, axis_color) = if is_dark {
+        (&GREY_200, GREY_400)
+    } else {
+        (&GREY_800, GREY_600)
+    };
 
-// Update the views when supplied with data.
+    let mut chart = ChartBuilder::on(a)
+        .caption(
+            metric_name,
+            ("sans-serif", 16, text_color).into_text_style(a),
+        )
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            (0..ZONE_COUNT as u32).into_segmented(),
+            0.0..max_val.max(1.0),
+        )?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_label_style(("sans-serif", 10, text_color).into_text_style(a))
+        .y_labels(5)
+        .y_label_style(("sans-serif", 10, text_color).into_text_style(a))
+        .x_label_formatter(&|v| match v {
+            SegmentValue::CenterOf(zone) => format!("Z{}", zone + 1),
+            _ => String::new(),
+        })
+        .y_desc(tr("UNIT_HOURS", None))
+        .axis_style(axis_color.mix(1.0))
+        .draw()?;
+    chart.draw_series(
+        Histogram::vertical(&chart)
+            .margin(10)
+            .style_func(|v, _| match v {
+                SegmentValue::CenterOf(zone) | SegmentValue::Exact(zone) => {
+                    colors[*zone as usize % ZONE_COUNT].filled()
+                }
+                SegmentValue::Last => colors[ZONE_COUNT - 1].filled(),
+            })
+            .data(hours.iter().enumerate().map(|(zone, h)| (zone as u32, *h))),
+    )?;
+    Ok(())
+}
+
+// The calendar of the selected range in a drawing area of the given size.
+fn calendar_layout(ui: &UserInterface, width: f64, height: f64) -> CalendarLayout {
+    let (start, end) = get_selected_start_end(ui);
+    CalendarLayout::new(
+        start.date_naive(),
+        end.date_naive(),
+        WEEK_START,
+        width,
+        height,
+    )
+}
+
+// The metric the calendar cells are colored by.
+fn get_calendar_metric(ui: &UserInterface) -> CalendarMetric {
+    match ui.calendar_metric_widget.selected() {
+        1 => CalendarMetric::Duration,
+        2 => CalendarMetric::Load,
+        _ => CalendarMetric::Distance,
+    }
+}
+
+// Draw one cell per day of the range, darker the more training that day.
+// The day the summary is filtered to is outlined.
+fn build_calendar_graph(
+    ui: &UserInterface,
+    a: &plotters::drawing::DrawingArea<CairoBackend<'_>, plotters::coord::Shift>,
+    days: &BTreeMap<NaiveDate, DayTotals>,
+    width: f64,
+    height: f64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let layout = calendar_layout(ui, width, height);
+    let metric = get_calendar_metric(ui);
+    let is_dark = StyleManager::default().is_dark();
+    let (text_color, empty_color, levels) = if is_dark {
+        (&GREY_200, CALENDAR_EMPTY_DARK, &CALENDAR_LEVELS_DARK)
+    } else {
+        (&GREY_800, CALENDAR_EMPTY_LIGHT, &CALENDAR_LEVELS_LIGHT)
+    };
+    let unit = match (metric, get_unit_system(&ui.units_widget)) {
+        (CalendarMetric::Distance, Units::US) => tr("UNIT_MILES", None),
+        (CalendarMetric::Distance, _) => tr("UNIT_KM", None),
+        (CalendarMetric::Duration, _) => tr("UNIT_MINUTES", None),
+        (CalendarMetric::Load, _) => "TRIMP".to_string(),
+    };
+    a.draw(&Text::new(
+        format!("{} ({})", tr("GRAPH_CAPTION_CALENDAR", None), unit),
+        (CALENDAR_LEFT as i32, 10),
+        ("sans-serif", 16, text_color).into_text_style(a),
+    ))?;
+
+    let label_style = ("sans-serif", 10, text_color).into_text_style(a);
+    for (column, date) in layout.month_starts() {
+        a.draw(&Text::new(
+            tr(MONTH_KEYS[date.month0() as usize], None),
+            (
+                (CALENDAR_LEFT + column as f64 * layout.cell) as i32,
+                (CALENDAR_TOP - 14.0) as i32,
+            ),
+            label_style.clone(),
+        ))?;
+    }
+    for row in 0..7 {
+        let weekday = (layout.first_column + chrono::Duration::days(row as i64)).weekday();
+        let key = match weekday {
+            Weekday::Mon => "WEEKDAY_MON",
+            Weekday::Wed => "WEEKDAY_WED",
+            Weekday::Fri => "WEEKDAY_FRI",
+            _ => continue,
+        };
+        a.draw(&Text::new(
+            tr(key, None),
+            (5, layout.row_center(row) as i32 - 5),
+            label_style.clone(),
+        ))?;
+    }
+
+    let max_val = days.values().map(|d| d.value(metric)).fold(0.0, f64::max);
+    let selected = ui.summary_state.borrow().day;
+    for date in layout.days() {
+        let Some((x, y, side)) = layout.cell_rect(date) else {
+            continue;
+        };
+        let value = days.get(&date).map_or(0.0, |d| d.value(metric));
+        let color = if value > 0.0 && max_val > 0.0 {
+            let level = ((value / max_val) * levels.len() as f64).ceil() as usize;
+            levels[level.clamp(1, levels.len()) - 1]
+        } else {
+            empty_color
+        };
+        let corners = [(x as i32, y as i32), ((x + side) as i32, (y + side) as i32)];
+        a.draw(&Rectangle::new(corners, color.filled()))?;
+        if selected == Some(date) {
+            a.draw(&Rectangle::new(corners, text_color.stroke_width(2)))?;
+        }
+    }
+    Ok(())
+}
+
+// Line chart of one fitness series over the range, formatting values with
+// format_value.
+fn build_fitness_chart(
+    a: &plotters::drawing::DrawingArea<CairoBackend<'_>, plotters::coord::Shift>,
+    caption: &str,
+    values: &[(DateTime<Utc>, f64)],
+    (start, end): (DateTime<Utc>, DateTime<Utc>),
+    color: &RGBColor,
+    format_value: &dyn Fn(&f64) -> String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let is_dark = StyleManager::default().is_dark();
+    let text_color = if is_dark { &GREY_200 } else { &GREY_800 };
+    let (low, high) = values
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(low, high), (_, v)| {
+            (low.min(*v), high.max(*v))
+        });
+    // Leave room above and below so a flat series is visible.
+    let margin = ((high - low) * 0.1).max(high.abs() * 0.02).max(1.0);
+    let mut chart = ChartBuilder::on(a)
+        .caption(caption, ("sans-serif", 16, text_color).into_text_style(a))
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(start..end, (low - margin)..(high + margin))?;
+    let axis_text_style = ("sans-serif", 10, text_color).into_text_style(a);
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_style(axis_text_style.clone())
+        .y_labels(5)
+        .y_label_style(axis_text_style)
+        .x_label_formatter(&|d| d.format("%m-%d").to_string())
+        .y_label_formatter(format_value)
+        .axis_style(ShapeStyle {
+            color: if is_dark { GREY_400 } else { GREY_600 }.mix(1.0),
+            filled: false,
+            stroke_width: 2,
+        })
+        .draw()?;
+    chart.draw_series(LineSeries::new(values.to_vec(), color.stroke_width(2)))?;
+    chart.draw_series(values.iter().map(|p| Circle::new(*p, 3, color.filled())))?;
+    Ok(())
+}
+
+// VO2max over the range above the predicted race times, one chart each.
+fn build_fitness_graphs(
+    ui: &UserInterface,
+    a: &plotters::drawing::DrawingArea<CairoBackend<'_>, plotters::coord::Shift>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let points = ui.fitness.borrow();
+    if points.is_empty() {
+        let text_color = if StyleManager::default().is_dark() {
+            &GREY_200
+        } else {
+            &GREY_800
+        };
+        a.draw(&Text::new(
+            tr("FITNESS_NO_EFFORTS", None),
+            (20, 20),
+            ("sans-serif", 16, text_color).into_text_style(a),
+        ))?;
+        return Ok(());
+    }
+    let axis = get_selected_start_end(ui);
+    let (upper, lower) = a.split_vertically(a.dim_in_pixel().1 * 2 / 5);
+    let source = if points.last().is_some_and(|p| p.from_device) {
+        tr("FITNESS_FROM_DEVICE", None)
+    } else {
+        "VDOT".to_string()
+    };
+    let vo2max: Vec<(DateTime<Utc>, f64)> =
+        points.iter().map(|p| (p.timestamp, p.vo2max)).collect();
+    build_fitness_chart(
+        &upper,
+        &format!("{} ({})", tr("GRAPH_CAPTION_VO2MAX", None), source),
+        &vo2max,
+        axis,
+        &VO2MAX_COLOR,
+        &|v| format!("{:.0}", v),
+    )?;
+    for (i, (area, (key, _))) in lower.split_evenly((2, 2)).iter().zip(RACES).enumerate() {
+        let times: Vec<(DateTime<Utc>, f64)> = points
+            .iter()
+            .map(|p| (p.timestamp, p.predictions[i]))
+            .collect();
+        let latest = times.last().map_or(0.0, |(_, t)| *t);
+        build_fitness_chart(
+            area,
+            &format!("{} {}", tr(key, None), format_duration(latest)),
+            &times,
+            axis,
+            &RACE_COLORS[i],
+            &|v| format_duration(*v),
+        )?;
+    }
+    Ok(())
+}
+
+// Use plotters.rs to draw a graph on the drawing area.
+fn draw_graphs(
+    ui: &UserInterface,
+    chart_columns: usize,
+    zone_charts: &ZoneCharts,
+    days: &BTreeMap<NaiveDate, DayTotals>,
+    cr: &Context,
+    width: f64,
+    height: f64,
+) {
+    let root = plotters_cairo::CairoBackend::new(&cr, (width as u32, height as u32))
+        .unwrap()
+        .into_drawing_area();
+    ui.chart_frames.borrow_mut().clear();
+    if ui.graph_view_widget.selected() == CALENDAR_VIEW {
+        build_calendar_graph(ui, &root, days, width, height).unwrap();
+        let _ = root.present();
+        return;
+    }
+    if ui.graph_view_widget.selected() == FITNESS_VIEW {
+        build_fitness_graphs(ui, &root).unwrap();
+        let _ = root.present();
+        return;
+    }
+    // The zone charts take the bottom quarter when there is zone data.
+    let chart_count = zone_charts.count();
+    let (upper, lower) = if chart_count == 0 {
+        (root.clone(), None)
+    } else {
+        let (upper, lower) = root.split_vertically((height * 0.75) as i32);
+        (upper, Some(lower))
+    };
+    let metric_charts = ui.metric_charts.borrow();
+    let columns = chart_columns.max(1);
+    let rows = metric_charts.len().div_ceil(columns).max(1);
+    let areas = upper.split_evenly((rows, columns));
+    if let Some(lower) = &lower {
+        let mut zone_areas = lower.split_evenly((1, chart_count)).into_iter();
+        if !zone_charts.hr_weeks.is_empty() {
+            build_zone_graph(
+                ui,
+                &zone_areas.next().unwrap(),
+                &zone_charts.hr_weeks,
+                &tr("GRAPH_CAPTION_HR_ZONES", None),
+                &HR_ZONE_COLORS,
+            )
+            .unwrap();
+        }
+        for (totals, caption) in [
+            (zone_charts.pace, "GRAPH_CAPTION_PACE_ZONES"),
+            (zone_charts.power, "GRAPH_CAPTION_POWER_ZONES"),
+        ] {
+            if let Some(totals) = totals {
+                build_zone_histogram(
+                    &zone_areas.next().unwrap(),
+                    &totals,
+                    &tr(caption, None),
+                    &HR_ZONE_COLORS,
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    for (index, (chart, area)) in metric_charts.iter().zip(&areas).enumerate() {
+        build_individual_graph(ui, area, chart, index).unwrap();
+    }
+
+    let _ = root.present();
+}
+
+// Build the graphs.  Prepare the graphical data for the drawing area and
+// set-up the draw function callback.
+fn build_graphs(
+    stats: &[PlottableData],
+    config: &WindowConfig,
+    zone_charts: ZoneCharts,
+    days: BTreeMap<NaiveDate, DayTotals>,
+    ui: &Rc<UserInterface>,
+) {
+    *ui.metric_charts.borrow_mut() = config
+        .charts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, chart)| MetricChart::new(index, chart, stats))
+        .collect();
+    // Need to clone to use inside the closure.
+    let chart_columns = config.chart_columns as usize;
+    ui.da.set_draw_func(clone!(
+        #[strong]
+        ui,
+        move |_drawing_area, cr, width, height| {
+            draw_graphs(
+                &ui,
+                chart_columns,
+                &zone_charts,
+                &days,
+                cr,
+                width as f64,
+                height as f64,
+            );
+        }
+    ));
+}
+
+// Switch between the charts and the calendar, and narrow the summary to a
+// day clicked in the calendar. Connected once; the data the handlers use is
+// replaced whenever the range changes.
+pub fn connect_calendar_widgets(ui: &Rc<UserInterface>) {
+    ui.graph_view_widget.connect_selected_notify(clone!(
+        #[weak]
+        ui,
+        move |dropdown| {
+            ui.calendar_metric_widget
+                .set_visible(dropdown.selected() == CALENDAR_VIEW);
+            ui.da.queue_draw();
+        }
+    ));
+    ui.calendar_metric_widget.connect_selected_notify(clone!(
+        #[weak(rename_to = da)]
+        ui.da,
+        move |_| da.queue_draw()
+    ));
+    ui.calendar_reset_btn.connect_clicked(clone!(
+        #[weak]
+        ui,
+        move |_| {
+            ui.summary_state.borrow_mut().day = None;
+            refresh_summary(&ui);
+            ui.da.queue_draw();
+        }
+    ));
+    let gesture = gtk4::GestureClick::new();
+    gesture.connect_pressed(clone!(
+        #[weak]
+        ui,
+        move |_, _, x, y| {
+            if ui.graph_view_widget.selected() != CALENDAR_VIEW {
+                return;
+            }
+            let layout = calendar_layout(&ui, ui.da.width() as f64, ui.da.height() as f64);
+            let Some(date) = layout.date_at(x, y) else {
+                return;
+            };
+            {
+                let mut state = ui.summary_state.borrow_mut();
+                if !state
+                    .stats
+                    .iter()
+                    .any(|item| item.timestamp.date_naive() == date)
+                {
+                    return;
+                }
+                // Clicking the selected day again shows every day.
+                state.day = (state.day != Some(date)).then_some(date);
+            }
+            refresh_summary(&ui);
+            ui.da.queue_draw();
+        }
+    ));
+    ui.da.add_controller(gesture);
+}
+
+// Pointer interaction with the metric charts: hovering a bar shows its date
+// and value, clicking it selects the activity in the summary, scrolling
+// zooms the time axis around the pointer, dragging pans it and a double
+// click shows the whole range again. Connected once.
+pub fn connect_chart_widgets(ui: &Rc<UserInterface>) {
+    ui.da.set_has_tooltip(true);
+    ui.da.connect_query_tooltip(clone!(
+        #[weak]
+        ui,
+        #[upgrade_or]
+        false,
+        move |_, x, y, _, tooltip| {
+            let frames = ui.chart_frames.borrow();
+            let Some(frame) = frames.iter().find(|f| f.contains(x as f64, y as f64)) else {
+                return false;
+            };
+            let Some((date, value)) = frame.bar_at(x as f64) else {
+                return false;
+            };
+            let value = if frame.metric.name == "pace" {
+                format_pace(value)
+            } else {
+                format!("{:.1}", value)
+            };
+            tooltip.set_text(Some(&format!(
+                "{}\n{}: {} {}",
+                date.format("%Y-%m-%d %H:%M"),
+                frame.metric_name,
+                value,
+                frame.unit_label
+            )));
+            true
+        }
+    ));
+
+    let scroll = gtk4::EventControllerScroll::new(gtk4::EventControllerScrollFlags::VERTICAL);
+    let pointer = Rc::new(Cell::new((0.0, 0.0)));
+    scroll.connect_scroll(clone!(
+        #[weak]
+        ui,
+        #[strong]
+        pointer,
+        #[upgrade_or]
+        glib::Propagation::Proceed,
+        move |_, _, dy| {
+            let (x, y) = pointer.get();
+            let frames = ui.chart_frames.borrow();
+            let Some(frame) = frames.iter().find(|f| f.contains(x, y)) else {
+                return glib::Propagation::Proceed;
+            };
+            let factor = CHART_ZOOM_STEP.powf(dy);
+            let anchor = frame.time_at(x);
+            let scale = |t: DateTime<Utc>| {
+                anchor
+                    + chrono::Duration::seconds(((t - anchor).num_seconds() as f64 * factor) as i64)
+            };
+            let (start, end) = (scale(frame.start), scale(frame.end));
+            if end - start < MIN_CHART_SPAN {
+                return glib::Propagation::Stop;
+            }
+            set_chart_axis(&ui, start, end);
+            glib::Propagation::Stop
+        }
+    ));
+    ui.da.add_controller(scroll);
+    let motion = gtk4::EventControllerMotion::new();
+    motion.connect_motion(clone!(
+        #[strong]
+        pointer,
+        move |_, x, y| pointer.set((x, y))
+    ));
+    ui.da.add_controller(motion);
+
+    let drag_origin: Rc<Cell<Option<DragOrigin>>> = Rc::new(Cell::new(None));
+    let drag = gtk4::GestureDrag::new();
+    drag.connect_drag_begin(clone!(
+        #[weak]
+        ui,
+        #[strong]
+        drag_origin,
+        move |_, x, y| {
+            let frames = ui.chart_frames.borrow();
+            drag_origin.set(
+                frames
+                    .iter()
+                    .find(|f| f.contains(x, y))
+                    .map(|f| (f.start, f.end, f.seconds_per_pixel())),
+            );
+        }
+    ));
+    drag.connect_drag_update(clone!(
+        #[weak]
+        ui,
+        #[strong]
+        drag_origin,
+        move |_, dx, _| {
+            if let Some((start, end, seconds_per_pixel)) = drag_origin.get()
+                && dx.abs() >= CLICK_DISTANCE
+            {
+                let shift = chrono::Duration::seconds((-dx * seconds_per_pixel) as i64);
+                set_chart_axis(&ui, start + shift, end + shift);
+            }
+        }
+    ));
+    drag.connect_drag_end(clone!(
+        #[weak]
+        ui,
+        #[strong]
+        drag_origin,
+        move |gesture, dx, dy| {
+            if drag_origin.take().is_none() || dx.hypot(dy) >= CLICK_DISTANCE {
+                return;
+            }
+            let Some((x, y)) = gesture.start_point() else {
+                return;
+            };
+            let clicked = {
+                let frames = ui.chart_frames.borrow();
+                frames
+                    .iter()
+                    .find(|f| f.contains(x, y))
+                    .and_then(|f| f.bar_at(x))
+            };
+            if let Some((date, _)) = clicked {
+                select_activity(&ui, date);
+            }
+        }
+    ));
+    ui.da.add_controller(drag);
+
+    let double_click = gtk4::GestureClick::new();
+    double_click.connect_pressed(clone!(
+        #[weak]
+        ui,
+        move |_, n_press, _, _| {
+            if n_press == 2 && ui.chart_axis.get().is_some() {
+                ui.chart_axis.set(None);
+                ui.da.queue_draw();
+            }
+        }
+    ));
+    ui.da.add_controller(double_click);
+
+    let context_click = gtk4::GestureClick::builder()
+        .button(gdk::BUTTON_SECONDARY)
+        .build();
+    context_click.connect_pressed(clone!(
+        #[weak]
+        ui,
+        move |_, _, x, y| {
+            let chart = {
+                let frames = ui.chart_frames.borrow();
+                frames.iter().find(|f| f.contains(x, y)).map(|f| f.chart)
+            };
+            if let Some(chart) = chart {
+                show_overlay_menu(&ui, chart, x, y);
+            }
+        }
+    ));
+    ui.da.add_controller(context_click);
+}
+
+// Pop up the overlay toggles of a chart. Changes are saved to the settings
+// file at once.
+fn show_overlay_menu(ui: &Rc<UserInterface>, chart: usize, x: f64, y: f64) {
+    let Some(settings) = ui
+        .metric_charts
+        .borrow()
+        .get(chart)
+        .map(|c| c.settings.clone())
+    else {
+        return;
+    };
+    let menu_box = gtk4::Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(5)
+        .build();
+    let average_check = gtk4::CheckButton::builder()
+        .label(tr("OVERLAY_MOVING_AVERAGE", None))
+        .active(settings.show_average)
+        .build();
+    let trend_check = gtk4::CheckButton::builder()
+        .label(tr("OVERLAY_TREND", None))
+        .active(settings.show_trend)
+        .build();
+    menu_box.append(&average_check);
+    menu_box.append(&trend_check);
+    let popover = Popover::builder()
+        .child(&menu_box)
+        .pointing_to(&gdk::Rectangle::new(x as i32, y as i32, 1, 1))
+        .build();
+    popover.set_parent(&ui.da);
+    popover.connect_closed(|popover| popover.unparent());
+    let toggled = move |ui: &UserInterface, average: bool, trend: bool| {
+        let index = {
+            let mut charts = ui.metric_charts.borrow_mut();
+            let Some(metric_chart) = charts.get_mut(chart) else {
+                return;
+            };
+            metric_chart.settings.show_average = average;
+            metric_chart.settings.show_trend = trend;
+            metric_chart.index
+        };
+        let config_path = Path::new(&ui.settings_file);
+        let mut config = load_config(config_path);
+        if let Some(chart_config) = config.charts.get_mut(index) {
+            chart_config.show_average = average;
+            chart_config.show_trend = trend;
+            let _ = save_config(&config, config_path);
+        }
+        ui.da.queue_draw();
+    };
+    let toggled = Rc::new(toggled);
+    for check in [&average_check, &trend_check] {
+        check.connect_toggled(clone!(
+            #[weak]
+            ui,
+            #[weak]
+            average_check,
+            #[weak]
+            trend_check,
+            #[strong]
+            toggled,
+            move |_| toggled(&ui, average_check.is_active(), trend_check.is_active())
+        ));
+    }
+    popover.popup();
+}
+
+// Zoom or pan the charts to a part of the selected range. The axis never
+// leaves the range nor grows past it.
+fn set_chart_axis(ui: &UserInterface, start: DateTime<Utc>, end: DateTime<Utc>) {
+    let (range_start, range_end) = get_selected_start_end(ui);
+    let span = (end - start).min(range_end - range_start);
+    let start = start.max(range_start).min(range_end - span);
+    let axis = (start, start + span);
+    ui.chart_axis
+        .set((axis != (range_start, range_end)).then_some(axis));
+    ui.da.queue_draw();
+}
+
+// Select an activity clicked in a chart, or clear the selection when it was
+// already selected.
+fn select_activity(ui: &UserInterface, timestamp: DateTime<Utc>) {
+    {
+        let mut state = ui.summary_state.borrow_mut();
+        state.activity = (state.activity != Some(timestamp)).then_some(timestamp);
+        // A calendar day filter hiding the activity no longer applies.
+        if state.day.is_some_and(|day| day != timestamp.date_naive()) {
+            state.day = None;
+        }
+    }
+    refresh_summary(ui);
+    ui.da.queue_draw();
+}
+
+// Update the views when supplied with data. The files are read on a
+// worker thread; the views keep showing the previous range until then.
 fn update_map_graph_and_summary_widgets(
     ui: &Rc<UserInterface>,
     data: &Vec<(chrono::DateTime<chrono::Utc>, PathBuf)>,
 ) {
-    let stats = collect_all_stats(data);
+    let data = tagged(ui, data);
+    let config = load_config(Path::new(&ui.settings_file));
+    report_config_error(ui, &config);
+    let units = get_unit_system(&ui.units_widget);
+    let dem_directory = config.dem_directory.trim();
+    let settings = DigestSettings {
+        zones: config.zone_settings(&units),
+        dem_directory: (!dem_directory.is_empty()).then(|| PathBuf::from(dem_directory)),
+    };
+    // Fitness looks back RECENT_DAYS from each activity, so the first
+    // activities of the range need the weeks before it.
+    let (start, end) = get_selected_start_end(ui);
+    let fitness_data = get_files_in_range(&ui.lookup, start - Duration::days(RECENT_DAYS), end);
+    let generation = ui.update_generation.get() + 1;
+    ui.update_generation.set(generation);
+    ui.spinner.set_visible(true);
+    ui.spinner.start();
+    let cache = ui.digests.clone();
+    glib::MainContext::default().spawn_local(clone!(
+        #[strong]
+        ui,
+        async move {
+            let (digests, fitness) = gtk4::gio::spawn_blocking(move || {
+                let fitness: Vec<(DateTime<Utc>, ActivityFitness)> = cache
+                    .collect(&fitness_data, &settings)
+                    .iter()
+                    .map(|(timestamp, digest)| (*timestamp, digest.fitness))
+                    .collect();
+                let mut fitness = fitness_timeline(&fitness);
+                fitness.retain(|point| point.timestamp >= start);
+                let digests = cache.collect(&data, &settings);
+                (digests, fitness)
+            })
+            .await
+            .expect("Worker thread panicked");
+            if ui.update_generation.get() != generation {
+                return;
+            }
+            ui.spinner.stop();
+            ui.spinner.set_visible(false);
+            *ui.fitness.borrow_mut() = fitness;
+            show_digests(&ui, digests, &config, &units);
+        }
+    ));
+}
+
+// Fill the views with the activities of the range once they are read.
+fn show_digests(
+    ui: &Rc<UserInterface>,
+    digests: Vec<(DateTime<Utc>, Arc<ActivityDigest>)>,
+    config: &WindowConfig,
+    units: &Units,
+) {
+    let stats =
+        ui.sidecar
+            .borrow()
+            .corrected(dem_corrected(config, &digests, digest_stats(&digests)));
     // units conversion
-    let ui_stats = convert_all_stats(&stats, &ui);
-    build_graphs(&ui_stats, &ui);
-    build_summary(&ui_stats, &ui);
-    return;
+    let ui_stats = convert_all_stats(&stats, units);
+    let zones: Vec<(DateTime<Utc>, ActivityZones)> = digests
+        .iter()
+        .map(|(timestamp, digest)| (*timestamp, digest.zones))
+        .collect();
+    build_map(&digests, ui);
+    build_graphs(
+        &ui_stats,
+        config,
+        ZoneCharts::new(&zones),
+        daily_totals(&ui_stats, &zones),
+        ui,
+    );
+    // A new range or unit system shows every day again, unzoomed.
+    *ui.summary_state.borrow_mut() = SummaryState {
+        stats: ui_stats,
+        digests,
+        zones,
+        day: None,
+        activity: None,
+    };
+    ui.chart_axis.set(None);
+    refresh_summary(ui);
+    build_volume_table(ui);
+    build_gear_page(ui);
+    ui.da.queue_draw();
+    ui.map_da.queue_draw();
 }
 
-// #####################################################################
-// ##################### SUMMARY FUNCTIONS #############################
-// #####################################################################
-// Build a summary using the PlottableData struct
-fn build_summary(stat_collection: &Vec<PlottableData>, ui: &UserInterface) {
-    // 1. Calculate Aggregates
-    let count = stat_collection.len() as f64;
-    let mut max_vals = [f64::MIN; 6]; // Dist, Cal, Dur, Pace(Fast), Asc, Des
-    let mut min_vals = [f64::MAX; 6];
-    let mut sums = [0.0; 6];
-
-    for item in stat_collection {
-        let s = &item.stats;
-        let vals = [
-            s.distance,
-            s.calories as f64,
-            s.duration,
-            s.enhanced_speed,
-            s.ascent as f64,
-            s.descent as f64,
-        ];
-
-        for i in 0..6 {
-            max_vals[i] = max_vals[i].max(vals[i]);
-            // For pace, we often want the "fastest" (minimum number)
-            if vals[i] > 0.0 {
-                min_vals[i] = min_vals[i].min(vals[i]);
-            }
-            sums[i] += vals[i];
+// Tell the user the settings file could not be read: the defaults are
+// used, every GPS position is hidden and nothing is saved until it is fixed.
+pub fn report_config_error(ui: &UserInterface, config: &WindowConfig) {
+    // Parse errors go on to quote the file; the first line has the position.
+    if let Some(e) = &config.load_error {
+        let reason = e.lines().next().unwrap_or_default();
+        ui.status_label
+            .set_text(&format!("{}: {}", tr("CONFIG_LOAD_ERROR", None), reason));
+    }
+}
+
+// Replace the recorded ascent and descent with the DEM's when the user
+// chose it. Activities the DEM does not cover keep the device values.
+fn dem_corrected(
+    config: &WindowConfig,
+    digests: &[(DateTime<Utc>, Arc<ActivityDigest>)],
+    mut stats: Vec<PlottableData>,
+) -> Vec<PlottableData> {
+    if ElevationSource::from_name(&config.elevation_source) != ElevationSource::Dem {
+        return stats;
+    }
+    for (item, (_, digest)) in stats.iter_mut().zip(digests) {
+        if let Some((ascent, descent)) = digest.dem {
+            item.stats.ascent = ascent;
+            item.stats.descent = descent;
         }
     }
+    stats
+}
 
-    // 1. Clear existing children from the grid
-    let mut child = ui.main_grid.first_child();
-    while let Some(widget) = child {
-        child = widget.next_sibling();
-        ui.main_grid.remove(&widget);
+// Rebuild the summary table, limited to the calendar day picked, if any.
+fn refresh_summary(ui: &UserInterface) {
+    let state = ui.summary_state.borrow();
+    ui.calendar_reset_btn.set_visible(state.day.is_some());
+    let Some(day) = state.day else {
+        build_summary(
+            &state.stats,
+            &summarize_laps(state.digests.iter().flat_map(|(_, d)| &d.laps)),
+            &state.zones,
+            state.activity,
+            ui,
+        );
+        return;
+    };
+    let on_day = |timestamp: &DateTime<Utc>| timestamp.date_naive() == day;
+    let stats: Vec<PlottableData> = state
+        .stats
+        .iter()
+        .filter(|item| on_day(&item.timestamp))
+        .cloned()
+        .collect();
+    let lap_summary = summarize_laps(
+        state
+            .digests
+            .iter()
+            .filter(|(timestamp, _)| on_day(timestamp))
+            .flat_map(|(_, d)| &d.laps),
+    );
+    let zones: Vec<(DateTime<Utc>, ActivityZones)> = state
+        .zones
+        .iter()
+        .filter(|(timestamp, _)| on_day(timestamp))
+        .cloned()
+        .collect();
+    build_summary(&stats, &lap_summary, &zones, state.activity, ui);
+}
+
+// The tag picked to narrow the range, if any.
+fn selected_tag(ui: &UserInterface) -> Option<String> {
+    let index = ui.tag_widget.selected();
+    if index == 0 || index == gtk4::INVALID_LIST_POSITION {
+        return None;
     }
+    ui.tag_widget
+        .model()
+        .and_then(|model| model.item(index))
+        .and_downcast::<StringObject>()
+        .map(|item| item.string().to_string())
+}
 
-    if stat_collection.is_empty() {
+// The activities of the range with the tag picked, or all of them.
+fn tagged(ui: &UserInterface, data: &[(DateTime<Utc>, PathBuf)]) -> Vec<(DateTime<Utc>, PathBuf)> {
+    let Some(tag) = selected_tag(ui) else {
+        return data.to_vec();
+    };
+    let sidecar = ui.sidecar.borrow();
+    data.iter()
+        .filter(|(timestamp, _)| sidecar.has_tag(timestamp, &tag))
+        .cloned()
+        .collect()
+}
+
+// List the tags in use, keeping the one picked while it still exists; the
+// list is hidden until an activity is tagged.
+fn refresh_tag_widget(ui: &UserInterface) {
+    let tags = ui.sidecar.borrow().tags();
+    let selected = selected_tag(ui);
+    let mut labels = vec![tr("TAG_ALL", None)];
+    labels.extend(tags.iter().cloned());
+    let current: Vec<String> = ui
+        .tag_widget
+        .model()
+        .map(|model| {
+            (0..model.n_items())
+                .filter_map(|i| model.item(i).and_downcast::<StringObject>())
+                .map(|item| item.string().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    if current != labels {
+        let strings: Vec<&str> = labels.iter().map(|s| s.as_str()).collect();
+        ui.tag_widget.set_model(Some(&StringList::new(&strings)));
+        let position = selected
+            .and_then(|tag| tags.iter().position(|t| *t == tag))
+            .map_or(0, |i| i as u32 + 1);
+        ui.tag_widget.set_selected(position);
+    }
+    ui.tag_widget.set_visible(!tags.is_empty());
+}
+
+// Show the range picked again, e.g. after the tag filter changed.
+fn show_selected_range(ui: &Rc<UserInterface>) {
+    if ui.lookup.is_empty() {
         return;
     }
+    ui.time_widget.set_visible(true);
+    let (start, end) = get_selected_start_end(ui);
+    let data = get_files_in_range(&ui.lookup, start, end);
+    construct_views_from_data(ui, &data);
+    ui.da.queue_draw();
+    ui.map_da.queue_draw();
+}
 
-    // 2. Unit Logic (Keep your existing conversion logic)
-    let selected_units = get_unit_system(&ui.units_widget);
-    let (dist_label, alt_label, pace_label) = match selected_units {
-        Units::Metric => (
-            tr("LABEL_DISTANCE_KM", None),
-            "m",
-            tr("LABEL_PACE_METRIC", None),
-        ),
-        _ => (
-            tr("LABEL_DISTANCE_MILES", None),
-            "ft",
-            tr("LABEL_PACE_US", None),
-        ),
+// Change the sidecar and save it. It is read again first so an unreadable
+// file is reported rather than overwritten.
+fn update_sidecar(ui: &UserInterface, change: impl FnOnce(&mut Sidecar)) -> bool {
+    let sidecar_path = ui.sidecar_file.borrow().clone();
+    let saved = Sidecar::load(&sidecar_path).and_then(|mut sidecar| {
+        change(&mut sidecar);
+        sidecar.save(&sidecar_path)?;
+        Ok(sidecar)
+    });
+    match saved {
+        Ok(sidecar) => {
+            *ui.sidecar.borrow_mut() = sidecar;
+            true
+        }
+        Err(e) => {
+            ui.status_label
+                .set_text(&format!("{}: {}", tr("SIDECAR_SAVE_ERROR", None), e));
+            false
+        }
+    }
+}
+
+// Index the manual activities of the sidecar with the activity files.
+fn index_manual_activities(ui: &UserInterface) {
+    ui.lookup.retain(|_, path| !is_manual_path(path));
+    for (start, path) in ui.sidecar.borrow().manual_index(&ui.sidecar_file.borrow()) {
+        ui.lookup.entry(start).or_insert(path);
+    }
+    // Manual activities count towards gear; read the library again.
+    *ui.gear_activities.borrow_mut() = (None, Vec::new());
+}
+
+// Switch to the sidecar of a library directory just opened and remember
+// it for the next session. Activity files of other libraries stay indexed.
+pub fn open_library(ui: &UserInterface, library: &Path) {
+    let sidecar_file = library_sidecar(library);
+    *ui.sidecar.borrow_mut() = Sidecar::load(&sidecar_file).unwrap_or_default();
+    *ui.sidecar_file.borrow_mut() = sidecar_file;
+    index_manual_activities(ui);
+    refresh_tag_widget(ui);
+    let config_path = Path::new(&ui.settings_file);
+    let mut config = load_config(config_path);
+    config.library = library.to_string_lossy().to_string();
+    let _ = save_config(&config, config_path);
+}
+
+// Open the editor of a manual activity, a new one when start is None.
+fn edit_manual_activity(ui: &Rc<UserInterface>, start: Option<DateTime<Utc>>) {
+    let (activity, notes) = {
+        let sidecar = ui.sidecar.borrow();
+        let activity = start
+            .and_then(|start| sidecar.manual(&start).cloned())
+            .unwrap_or_else(|| ManualActivity {
+                start: DateTime::from_timestamp(Utc::now().timestamp() / 60 * 60, 0)
+                    .unwrap_or_default(),
+                ..Default::default()
+            });
+        let notes = sidecar.meta_or_default(&activity.start).notes;
+        (activity, notes)
     };
+    let previous = start;
+    let parent = ui.win.clone().upcast::<gtk4::Window>();
+    let weak_ui = Rc::downgrade(ui);
+    let delete_ui = Rc::downgrade(ui);
+    show_manual_dialog(
+        Some(&parent),
+        &get_unit_system(&ui.units_widget),
+        activity,
+        notes,
+        start.is_none(),
+        move |activity, notes| {
+            let Some(ui) = weak_ui.upgrade() else {
+                return;
+            };
+            // A recorded activity, or another manual one, may start then.
+            let taken = ui
+                .lookup
+                .get(&activity.start)
+                .is_some_and(|_| previous != Some(activity.start));
+            if taken {
+                ui.status_label.set_text(&tr("MANUAL_CONFLICT", None));
+                return;
+            }
+            let saved = update_sidecar(&ui, |sidecar| {
+                // Moving the activity keeps its title and tags.
+                let mut meta = previous
+                    .map(|p| sidecar.meta_or_default(&p))
+                    .unwrap_or_default();
+                if let Some(previous) = previous {
+                    sidecar.remove_manual(&previous);
+                }
+                meta.start = activity.start;
+                meta.notes = notes;
+                sidecar.set_meta(meta);
+                sidecar.set_manual(activity);
+            });
+            if saved {
+                index_manual_activities(&ui);
+                refresh_tag_widget(&ui);
+                show_selected_range(&ui);
+            }
+        },
+        move || {
+            let (Some(ui), Some(start)) = (delete_ui.upgrade(), previous) else {
+                return;
+            };
+            if update_sidecar(&ui, |sidecar| sidecar.remove_manual(&start)) {
+                index_manual_activities(&ui);
+                refresh_tag_widget(&ui);
+                show_selected_range(&ui);
+            }
+        },
+    );
+}
 
-    // 3. Helper to attach styled labels
-    let attach_label = |grid: &gtk4::Grid, text: &str, col, row, bold: bool| {
+// Open the editor of the corrections of a recorded activity. The recorded
+// totals come from the digests of the range shown, or are read on a worker
+// thread for an activity outside it.
+fn correct_activity(ui: &Rc<UserInterface>, start: DateTime<Utc>, path: &Path) {
+    let recorded = ui
+        .summary_state
+        .borrow()
+        .digests
+        .iter()
+        .find(|(timestamp, _)| *timestamp == start)
+        .map(|(_, digest)| (digest.stats, digest.info.clone()));
+    if let Some(recorded) = recorded {
+        show_correction(ui, start, recorded);
+        return;
+    }
+    let path = path.to_path_buf();
+    glib::MainContext::default().spawn_local(clone!(
+        #[strong]
+        ui,
+        async move {
+            let recorded = gtk4::gio::spawn_blocking(move || {
+                let activity = read_activity(&path).unwrap_or_default();
+                (activity.stats, activity.info)
+            })
+            .await
+            .expect("Worker thread panicked");
+            show_correction(&ui, start, recorded);
+        }
+    ));
+}
+
+fn show_correction(
+    ui: &Rc<UserInterface>,
+    start: DateTime<Utc>,
+    recorded: (SessionStats, ActivityInfo),
+) {
+    let correction = ui.sidecar.borrow().correction(&start).cloned();
+    let parent = ui.win.clone().upcast::<gtk4::Window>();
+    let save_ui = Rc::downgrade(ui);
+    let revert_ui = Rc::downgrade(ui);
+    show_correction_dialog(
+        Some(&parent),
+        &get_unit_system(&ui.units_widget),
+        start,
+        recorded,
+        correction,
+        move |correction| {
+            if let Some(ui) = save_ui.upgrade()
+                && update_sidecar(&ui, |sidecar| sidecar.set_correction(correction))
+            {
+                show_selected_range(&ui);
+            }
+        },
+        move || {
+            if let Some(ui) = revert_ui.upgrade()
+                && update_sidecar(&ui, |sidecar| sidecar.revert(&start))
+            {
+                show_selected_range(&ui);
+            }
+        },
+    );
+}
+
+// Add and edit manual activities, and correct recorded ones, from the
+// summary table.
+pub fn connect_manual_widgets(ui: &Rc<UserInterface>) {
+    let weak_ui = Rc::downgrade(ui);
+    ui.table.connect_manual(move |start| {
+        if let Some(ui) = weak_ui.upgrade() {
+            edit_manual_activity(&ui, start);
+        }
+    });
+    let weak_ui = Rc::downgrade(ui);
+    ui.table.connect_correct(move |start, path| {
+        if let Some(ui) = weak_ui.upgrade() {
+            correct_activity(&ui, start, &path);
+        }
+    });
+}
+
+// Save what the user added to an activity.
+fn save_activity_meta(ui: &UserInterface, meta: &ActivityMeta) {
+    update_sidecar(ui, |sidecar| sidecar.set_meta(meta.clone()));
+}
+
+// Narrow the range to a tag, and save the metadata edited in the table.
+pub fn connect_tag_widgets(ui: &Rc<UserInterface>) {
+    ui.tag_widget.connect_selected_notify(clone!(
+        #[weak]
+        ui,
+        move |_| show_selected_range(&ui)
+    ));
+    let weak_ui = Rc::downgrade(ui);
+    ui.table.connect_edited(move |meta| {
+        let Some(ui) = weak_ui.upgrade() else {
+            return;
+        };
+        save_activity_meta(&ui, &meta);
+        ui.table
+            .set_meta(ui.sidecar.borrow().meta_or_default(&meta.start));
+        let filtering = selected_tag(&ui).is_some();
+        refresh_tag_widget(&ui);
+        if filtering {
+            show_selected_range(&ui);
+        }
+    });
+}
+
+// Offer the DEM elevation when a directory of tiles is configured, and
+// redraw the range with the source chosen.
+pub fn connect_elevation_widget(ui: &Rc<UserInterface>) {
+    let config = load_config(Path::new(&ui.settings_file));
+    ui.elevation_widget
+        .set_visible(!config.dem_directory.trim().is_empty());
+    if ElevationSource::from_name(&config.elevation_source) == ElevationSource::Dem {
+        ui.elevation_widget.set_selected(1);
+    }
+    ui.elevation_widget.connect_selected_notify(clone!(
+        #[weak]
+        ui,
+        move |widget| {
+            let source = match widget.selected() {
+                1 => ElevationSource::Dem,
+                _ => ElevationSource::Device,
+            };
+            let config_path = Path::new(&ui.settings_file);
+            let mut config = load_config(config_path);
+            config.elevation_source = source.name().to_string();
+            let _ = save_config(&config, config_path);
+            show_selected_range(&ui);
+        }
+    ));
+}
+
+fn get_volume_period(ui: &UserInterface) -> Period {
+    match ui.volume_period_widget.selected() {
+        1 => Period::Month,
+        _ => Period::Week,
+    }
+}
+
+// Volume of the range per week or month, for the volume page and export.
+fn volume_report(ui: &UserInterface) -> Vec<PeriodVolume> {
+    let (start, end) = get_selected_start_end(ui);
+    period_volumes(
+        &ui.summary_state.borrow().stats,
+        get_volume_period(ui),
+        start.date_naive(),
+        end.date_naive(),
+    )
+}
+
+// Rebuild the volume page: one row per period with its change from the
+// previous one, flagged when above the configured increase.
+fn build_volume_table(ui: &UserInterface) {
+    let grid = &ui.volume_grid;
+    let mut child = grid.first_child();
+    while let Some(widget) = child {
+        child = widget.next_sibling();
+        grid.remove(&widget);
+    }
+    let max_increase = load_config(Path::new(&ui.settings_file)).volume_jump_warning;
+    let (dist_label, alt_label) = match get_unit_system(&ui.units_widget) {
+        Units::Metric => (tr("LABEL_DISTANCE_KM", None), "m"),
+        _ => (tr("LABEL_DISTANCE_MILES", None), "ft"),
+    };
+    let attach_label = |text: &str, col, row, bold: bool| {
         let label = Label::new(Some(text));
         label.set_halign(gtk4::Align::Start);
         label.set_selectable(true);
@@ -723,179 +2327,625 @@ fn build_summary(stat_collection: &Vec<PlottableData>, ui: &UserInterface) {
             label.set_markup(&format!("<b>{}</b>", text));
         }
         grid.attach(&label, col, row, 1, 1);
+        label
     };
-
-    // 4. Create Headers (Row 0)
     let headers = [
-        tr("LABEL_DATE_TIME", None),
+        tr("VOLUME_PERIOD", None),
+        tr("ACTIVITY_COUNT", None),
         dist_label,
-        "Calories".to_string(),
-        tr("LABEL_DURATION", None),
-        pace_label,
+        tr("GRAPH_CAPTION_DURATION", None),
         format!("Asc({})", alt_label),
-        format!("Des({})", alt_label),
+        tr("VOLUME_LONGEST", None),
+        tr("VOLUME_CHANGE", None),
     ];
+    for (col, text) in headers.iter().enumerate() {
+        attach_label(text, col as i32, 0, true);
+    }
+    for (i, volume) in volume_report(ui).iter().enumerate() {
+        let row = i as i32 + 1;
+        attach_label(&volume.start.format("%Y-%m-%d").to_string(), 0, row, false);
+        attach_label(&volume.count.to_string(), 1, row, false);
+        attach_label(&format!("{:.2}", volume.distance), 2, row, false);
+        attach_label(&format_duration(volume.duration * 60.0), 3, row, false);
+        attach_label(&format!("{:.0}", volume.ascent), 4, row, false);
+        attach_label(&format!("{:.2}", volume.longest), 5, row, false);
+        if let Some(change) = volume.change {
+            let label = attach_label(&format!("{:+.0}%", change), 6, row, false);
+            if volume.is_jump(max_increase) {
+                label.add_css_class("warning");
+                label.set_tooltip_text(Some(&format!(
+                    "{} {:.0}%",
+                    tr("VOLUME_JUMP_WARNING", None),
+                    max_increase
+                )));
+            }
+        }
+    }
+}
 
+// Switch the volume report between weeks and months and export it as CSV.
+pub fn connect_volume_widgets(ui: &Rc<UserInterface>) {
+    ui.volume_period_widget.connect_selected_notify(clone!(
+        #[weak]
+        ui,
+        move |_| build_volume_table(&ui)
+    ));
+    ui.volume_export_btn.connect_clicked(clone!(
+        #[weak]
+        ui,
+        move |_| {
+            let chooser = gtk4::FileChooserNative::new(
+                Some(&tr("VOLUME_EXPORT", None)),
+                Some(&ui.win),
+                gtk4::FileChooserAction::Save,
+                Some(&tr("SAVE_BUTTON", None)),
+                Some(&tr("CANCEL_BUTTON", None)),
+            );
+            chooser.set_current_name("volume.csv");
+            chooser.connect_response(clone!(
+                #[weak]
+                ui,
+                move |chooser, response| {
+                    if response == gtk4::ResponseType::Accept
+                        && let Some(path) = chooser.file().and_then(|f| f.path())
+                    {
+                        let max_increase =
+                            load_config(Path::new(&ui.settings_file)).volume_jump_warning;
+                        let result = std::fs::File::create(&path).and_then(|mut file| {
+                            write_volume_csv(&volume_report(&ui), max_increase, &mut file)
+                        });
+                        if let Err(e) = result {
+                            show_error_dialog(&ui.win, e.to_string());
+                        }
+                    }
+                    chooser.destroy();
+                }
+            ));
+            chooser.show();
+        }
+    ));
+}
+
+// Sport, device and totals of every activity of the library, from the
+// activity database for the files it holds unchanged; the others are read.
+// Manual activities are never in the database. Runs on a worker thread.
+fn library_activities(
+    lookup: &[(DateTime<Utc>, PathBuf)],
+    database_file: &Path,
+) -> Vec<GearActivity> {
+    let (manual, files): (Vec<(DateTime<Utc>, PathBuf)>, Vec<_>) = lookup
+        .iter()
+        .cloned()
+        .partition(|(_, path)| is_manual_path(path));
+    let mut activities = Vec::new();
+    #[cfg(not(feature = "sqlite"))]
+    let _ = database_file;
+    #[cfg(feature = "sqlite")]
+    let files = {
+        let paths: Vec<PathBuf> = files.iter().map(|(_, path)| path.clone()).collect();
+        let records = Store::open(database_file)
+            .and_then(|store| store.current_records(&paths))
+            .unwrap_or_default();
+        let stored: std::collections::HashSet<PathBuf> =
+            records.iter().map(|r| r.path.clone()).collect();
+        activities.extend(records.into_iter().map(|r| GearActivity {
+            timestamp: r.timestamp,
+            info: r.info,
+            stats: r.stats,
+        }));
+        files
+            .into_iter()
+            .filter(|(_, path)| !stored.contains(path))
+            .collect::<Vec<_>>()
+    };
+    activities.extend(collect_gear_activities(&files));
+    activities.extend(collect_gear_activities(&manual));
+    activities
+}
+
+// Rebuild the gear page from the whole library, whatever the range, and
+// report worn out gear in the status bar.
+fn build_gear_page(ui: &Rc<UserInterface>) {
+    let gear = load_config(Path::new(&ui.settings_file)).gear();
+    if gear.is_empty() {
+        let grid = &ui.gear_grid;
+        let mut child = grid.first_child();
+        while let Some(widget) = child {
+            child = widget.next_sibling();
+            grid.remove(&widget);
+        }
+        let hint = Label::builder()
+            .label(tr("GEAR_NONE", None))
+            .wrap(true)
+            .halign(gtk4::Align::Start)
+            .build();
+        grid.attach(&hint, 0, 0, 1, 1);
+        ui.gear_usage.borrow_mut().clear();
+        ui.gear_da.queue_draw();
+        return;
+    }
+    let fingerprint = lookup_fingerprint(&ui.lookup);
+    if ui.gear_activities.borrow().0 == Some(fingerprint) {
+        show_gear_usage(ui, &gear);
+        return;
+    }
+    // The whole library is read; do it on a worker thread.
+    let lookup: Vec<(DateTime<Utc>, PathBuf)> = ui
+        .lookup
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().clone()))
+        .collect();
+    let database_file = PathBuf::from(&ui.database_file);
+    glib::MainContext::default().spawn_local(clone!(
+        #[strong]
+        ui,
+        async move {
+            let activities =
+                gtk4::gio::spawn_blocking(move || library_activities(&lookup, &database_file))
+                    .await
+                    .expect("Worker thread panicked");
+            // A later change of the library is read by its own update.
+            if lookup_fingerprint(&ui.lookup) != fingerprint {
+                return;
+            }
+            *ui.gear_activities.borrow_mut() = (Some(fingerprint), activities);
+            show_gear_usage(&ui, &gear);
+        }
+    ));
+}
+
+// Fingerprint of the activities indexed, to tell when the library changed.
+fn lookup_fingerprint(lookup: &DashMap<DateTime<Utc>, PathBuf>) -> u64 {
+    let mut entries: Vec<(DateTime<Utc>, PathBuf)> = lookup
+        .iter()
+        .map(|entry| (*entry.key(), entry.value().clone()))
+        .collect();
+    entries.sort();
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Fill the gear table with the use of each item over the library.
+fn show_gear_usage(ui: &UserInterface, gear: &[Gear]) {
+    let grid = &ui.gear_grid;
+    let mut child = grid.first_child();
+    while let Some(widget) = child {
+        child = widget.next_sibling();
+        grid.remove(&widget);
+    }
+    // Corrections apply to gear too; the library is kept as recorded.
+    let mut activities = ui.gear_activities.borrow().1.clone();
+    for activity in &mut activities {
+        if let Some(correction) = ui.sidecar.borrow().correction(&activity.timestamp) {
+            correction.apply(&mut activity.stats);
+            correction.apply_info(&mut activity.info);
+        }
+    }
+    let usage = gear_usage(gear, &activities);
+
+    let units = get_unit_system(&ui.units_widget);
+    let distance_label = match units {
+        Units::Metric => tr("LABEL_DISTANCE_KM", None),
+        _ => tr("LABEL_DISTANCE_MILES", None),
+    };
+    let attach_label = |text: &str, col, row, bold: bool| {
+        let label = Label::new(Some(text));
+        label.set_halign(gtk4::Align::Start);
+        label.set_selectable(true);
+        if bold {
+            label.set_markup(&format!("<b>{}</b>", text));
+        }
+        grid.attach(&label, col, row, 1, 1);
+        label
+    };
+    let headers = [
+        tr("GEAR_NAME", None),
+        tr("ACTIVITY_COUNT", None),
+        distance_label,
+        tr("GRAPH_CAPTION_MOVING_TIME", None),
+        tr("GEAR_RETIRE_AT", None),
+        tr("GEAR_USED", None),
+    ];
     for (col, text) in headers.iter().enumerate() {
-        attach_label(&ui.main_grid, text, col as i32, 0, true);
+        attach_label(text, col as i32, 0, true);
+    }
+    let distance = |meters: f64| cvt_distance(meters as f32, &units) as f64;
+    let mut warnings = Vec::new();
+    for (i, item) in usage.iter().enumerate() {
+        let row = i as i32 + 1;
+        attach_label(&item.name, 0, row, false);
+        attach_label(&item.count.to_string(), 1, row, false);
+        attach_label(&format!("{:.1}", distance(item.distance)), 2, row, false);
+        attach_label(&format_duration(item.time), 3, row, false);
+        let retire_at = [
+            item.retire_distance.map(|d| format!("{:.0}", distance(d))),
+            item.retire_time.map(format_duration),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" / ");
+        attach_label(&retire_at, 4, row, false);
+        if let Some(share) = item.share_used() {
+            let label = attach_label(&format!("{:.0}%", share * 100.0), 5, row, false);
+            if item.is_worn_out() {
+                label.add_css_class("error");
+                label.set_tooltip_text(Some(&tr("GEAR_WORN_OUT", None)));
+            } else if item.needs_attention() {
+                label.add_css_class("warning");
+                label.set_tooltip_text(Some(&tr("GEAR_NEARLY_WORN_OUT", None)));
+            }
+        }
+        if item.needs_attention() {
+            warnings.push(item.name.clone());
+        }
+    }
+    if !warnings.is_empty() {
+        ui.status_label.set_text(&format!(
+            "{}: {}",
+            tr("GEAR_STATUS_WARNING", None),
+            warnings.join(", ")
+        ));
+    }
+    *ui.gear_usage.borrow_mut() = usage;
+    ui.gear_da.queue_draw();
+}
+
+// Distance of each gear item over time, with its retirement distance.
+fn build_gear_graph(
+    a: &plotters::drawing::DrawingArea<CairoBackend<'_>, plotters::coord::Shift>,
+    usage: &[GearUsage],
+    units: &Units,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(start), Some(end)) = (
+        usage
+            .iter()
+            .filter_map(|g| g.history.first())
+            .map(|p| p.0)
+            .min(),
+        usage
+            .iter()
+            .filter_map(|g| g.history.last())
+            .map(|p| p.0)
+            .max(),
+    ) else {
+        return Ok(());
+    };
+    let end = end.max(start + chrono::Duration::days(1));
+    let distance = |meters: f64| cvt_distance(meters as f32, units) as f64;
+    let top = usage
+        .iter()
+        .map(|g| g.distance.max(g.retire_distance.unwrap_or_default()))
+        .fold(0.0, f64::max);
+    let is_dark = StyleManager::default().is_dark();
+    let text_color = if is_dark { &GREY_200 } else { &GREY_800 };
+    let unit_label = match units {
+        Units::US => tr("UNIT_MILES", None),
+        _ => tr("UNIT_KM", None),
+    };
+    let mut chart = ChartBuilder::on(a)
+        .caption(
+            tr("GRAPH_CAPTION_GEAR", None),
+            ("sans-serif", 16, text_color).into_text_style(a),
+        )
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(start..end, 0.0..distance(top) * 1.1)?;
+    let axis_text_style = ("sans-serif", 10, text_color).into_text_style(a);
+    chart
+        .configure_mesh()
+        .x_labels(8)
+        .x_label_style(axis_text_style.clone())
+        .y_labels(5)
+        .y_label_style(axis_text_style)
+        .x_label_formatter(&|d| d.format("%Y-%m").to_string())
+        .y_desc(&unit_label)
+        .axis_style(ShapeStyle {
+            color: if is_dark { GREY_400 } else { GREY_600 }.mix(1.0),
+            filled: false,
+            stroke_width: 2,
+        })
+        .draw()?;
+    for (i, item) in usage.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let points: Vec<(DateTime<Utc>, f64)> = item
+            .history
+            .iter()
+            .map(|(t, d)| (*t, distance(*d)))
+            .collect();
+        chart
+            .draw_series(LineSeries::new(points, color.stroke_width(2)))?
+            .label(item.name.clone())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        if let Some(retire) = item.retire_distance {
+            chart.draw_series(DashedLineSeries::new(
+                [(start, distance(retire)), (end, distance(retire))],
+                6,
+                4,
+                color.stroke_width(1),
+            ))?;
+        }
     }
+    chart
+        .configure_series_labels()
+        .label_font(("sans-serif", 12, text_color).into_text_style(a))
+        .border_style(text_color)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()?;
+    Ok(())
+}
 
-    // 5. Populate Data Rows
-    let mut sorted_data = stat_collection.clone();
-    sorted_data.sort_by_key(|item| item.timestamp);
+// Draw the gear chart from the usage last computed.
+pub fn connect_gear_widgets(ui: &Rc<UserInterface>) {
+    let usage = ui.gear_usage.clone();
+    ui.gear_da.set_draw_func(clone!(
+        #[weak]
+        ui,
+        move |_, cr, width, height| {
+            let root = CairoBackend::new(cr, (width as u32, height as u32))
+                .unwrap()
+                .into_drawing_area();
+            let _ = build_gear_graph(&root, &usage.borrow(), &get_unit_system(&ui.units_widget));
+            let _ = root.present();
+        }
+    ));
+}
 
-    let pace_formatter = |x: f64| {
-        let mins = x.trunc();
-        let secs = x.fract() * 60.0;
-        format!("{:02.0}:{:02.0}", mins, secs)
+// #####################################################################
+// ##################### MAP FUNCTIONS #################################
+// #####################################################################
+//
+// Overlay the GPS tracks of the range on the map, over the configured tiles.
+fn build_map(digests: &[(DateTime<Utc>, Arc<ActivityDigest>)], ui: &UserInterface) {
+    let config = load_config(Path::new(&ui.settings_file));
+    let tiles = if config.map_tiles.is_empty() {
+        None
+    } else {
+        TileSource::open(Path::new(&config.map_tiles))
     };
+    let tracks = digests
+        .iter()
+        .filter(|(_, digest)| !digest.track.is_empty())
+        .map(|(timestamp, digest)| (*timestamp, digest.track.clone()))
+        .collect();
+    *ui.map_view.borrow_mut() = MapView::new(tracks, tiles, config.privacy_zones());
+    ui.map_da.queue_draw();
+}
 
-    for (row_idx, item) in sorted_data.iter().enumerate() {
-        let row = (row_idx + 1) as i32; // Offset by 1 for header
+// Draw the map view and handle the heatmap and privacy preview toggles and
+// the export button. The
+// view itself is replaced by build_map whenever the range changes.
+fn connect_map_widgets(ui: &UserInterface) {
+    let map_view = ui.map_view.clone();
+    let heatmap_toggle = ui.heatmap_toggle.clone();
+    let privacy_toggle = ui.privacy_toggle.clone();
+    ui.map_da
+        .set_draw_func(move |_drawing_area, cr, width, height| {
+            map_view.borrow().draw(
+                cr,
+                width as f64,
+                height as f64,
+                heatmap_toggle.is_active(),
+                privacy_toggle.is_active(),
+            );
+        });
+    for toggle in [&ui.heatmap_toggle, &ui.privacy_toggle] {
+        toggle.connect_toggled(clone!(
+            #[weak(rename_to = map_da)]
+            ui.map_da,
+            move |_| map_da.queue_draw()
+        ));
+    }
+    let map_view = ui.map_view.clone();
+    ui.heatmap_export_btn.connect_clicked(clone!(
+        #[weak(rename_to = win)]
+        ui.win,
+        move |_| {
+            let chooser = gtk4::FileChooserNative::new(
+                Some(&tr("HEATMAP_EXPORT", None)),
+                Some(&win),
+                gtk4::FileChooserAction::Save,
+                Some(&tr("SAVE_BUTTON", None)),
+                Some(&tr("CANCEL_BUTTON", None)),
+            );
+            chooser.set_current_name("heatmap.png");
+            chooser.connect_response(clone!(
+                #[strong]
+                map_view,
+                move |chooser, response| {
+                    if response == gtk4::ResponseType::Accept
+                        && let Some(path) = chooser.file().and_then(|f| f.path())
+                        && let Err(e) = map_view.borrow().export_heatmap_png(
+                            &path,
+                            HEATMAP_EXPORT_SIZE.0,
+                            HEATMAP_EXPORT_SIZE.1,
+                        )
+                    {
+                        show_error_dialog(&win, e.to_string());
+                    }
+                    chooser.destroy();
+                }
+            ));
+            chooser.show();
+        }
+    ));
+}
 
+// #####################################################################
+// ##################### SUMMARY FUNCTIONS #############################
+// #####################################################################
+// Build a summary using the PlottableData struct
+fn build_summary(
+    stat_collection: &[PlottableData],
+    lap_summary: &LapSummary,
+    zones: &[(DateTime<Utc>, ActivityZones)],
+    selected: Option<DateTime<Utc>>,
+    ui: &UserInterface,
+) {
+    // 1. Clear existing children from the grid
+    let mut child = ui.main_grid.first_child();
+    while let Some(widget) = child {
+        child = widget.next_sibling();
+        ui.main_grid.remove(&widget);
+    }
+
+    // 2. One table row per activity; the table sorts, filters and sums them.
+    let selected_units = get_unit_system(&ui.units_widget);
+    let rows = stat_collection
+        .iter()
+        .map(|item| ActivityRow {
+            timestamp: item.timestamp,
+            path: ui
+                .lookup
+                .get(&item.timestamp)
+                .map(|entry| entry.value().clone()),
+            stats: item.stats,
+            zones: zones
+                .iter()
+                .find(|(timestamp, _)| *timestamp == item.timestamp)
+                .map(|(_, z)| *z)
+                .unwrap_or_default(),
+            meta: ui.sidecar.borrow().meta_or_default(&item.timestamp),
+            corrected: ui.sidecar.borrow().is_corrected(&item.timestamp),
+        })
+        .collect();
+    let weighting =
+        Weighting::from_name(&load_config(Path::new(&ui.settings_file)).summary_weighting);
+    ui.table.set_rows(rows, &selected_units, weighting);
+    // Highlight the activity selected in the charts.
+    ui.table.select(selected);
+    if stat_collection.is_empty() {
+        return;
+    }
+
+    // 3. Helper to attach styled labels
+    let attach_label = |grid: &gtk4::Grid, text: &str, col, row, bold: bool| {
+        let label = Label::new(Some(text));
+        label.set_halign(gtk4::Align::Start);
+        label.set_selectable(true);
+        if bold {
+            label.set_markup(&format!("<b>{}</b>", text));
+        }
+        grid.attach(&label, col, row, 1, 1);
+    };
+
+    // --- Append interval rows: totals and pace of work and recovery laps ---
+    let mut lap_row = 0;
+    for (key, count, distance, time, speed) in [
+        (
+            "SUMMARY_WORK_INTERVALS",
+            lap_summary.work_count,
+            lap_summary.work_distance,
+            lap_summary.work_time,
+            lap_summary.work_speed(),
+        ),
+        (
+            "SUMMARY_RECOVERY",
+            lap_summary.recovery_count,
+            lap_summary.recovery_distance,
+            lap_summary.recovery_time,
+            lap_summary.recovery_speed(),
+        ),
+    ] {
+        if count == 0 {
+            continue;
+        }
         attach_label(
             &ui.main_grid,
-            &item.timestamp.format("%Y-%m-%d").to_string(),
+            &format!("{} ({})", tr(key, None), count),
             0,
-            row,
-            false,
+            lap_row,
+            true,
         );
         attach_label(
             &ui.main_grid,
-            &format!("{:.2}", item.stats.distance),
+            &format!("{:.2}", cvt_distance(distance as f32, &selected_units)),
             1,
-            row,
-            false,
+            lap_row,
+            true,
         );
         attach_label(
             &ui.main_grid,
-            &item.stats.calories.to_string(),
-            2,
-            row,
-            false,
-        );
-        attach_label(
-            &ui.main_grid,
-            &format!("{:.1}", item.stats.duration),
+            &format!("{:.1}", time / 60.0),
             3,
-            row,
-            false,
+            lap_row,
+            true,
         );
         attach_label(
             &ui.main_grid,
-            &pace_formatter(item.stats.enhanced_speed),
+            &format_pace(cvt_pace(speed as f32, &selected_units) as f64),
             4,
-            row,
-            false,
-        );
-        attach_label(
-            &ui.main_grid,
-            &format!("{:.0}", item.stats.ascent),
-            5,
-            row,
-            false,
+            lap_row,
+            true,
         );
+        lap_row += 1;
+    }
+
+    // --- Append time in heart rate zone: minutes and share of the total ---
+    let hr_zone_totals = total_zones(&zone_series(zones, |z| z.hr));
+    let zone_total: f64 = hr_zone_totals.iter().sum();
+    if zone_total > 0.0 {
+        let zone_row = lap_row + 1;
         attach_label(
             &ui.main_grid,
-            &format!("{:.0}", item.stats.descent),
-            6,
-            row,
-            false,
+            &tr("SUMMARY_HR_ZONES", None),
+            0,
+            zone_row,
+            true,
         );
-    }
-    // ... [Previous code for populating session data rows] ...
-    let last_data_row = sorted_data.len() as i32;
-
-    // 3. Append Aggregate Rows
-    // for (i, (en, fr, es)) in curro-all_labels.iter().enumerate() {
-    for i in 0..3 {
-        let row = last_data_row + 4 + i as i32;
-
-        // Choose label based on current locale or a setting
-        // For this example, we'll use a placeholder logic
-        let mut row_title: String = "".to_string();
-        match i {
-            0 => row_title = tr("MAXIMUM", None),
-            1 => row_title = tr("MINIMUM", None),
-            2 => row_title = tr("AVERAGE", None),
-            _ => (),
-        }
-        // Title Cell
-        let title_label = Label::builder()
-            .label(&format!("<b>{}</b>", row_title))
-            .use_markup(true)
-            .halign(gtk4::Align::Start)
-            .build();
-        ui.main_grid.attach(&title_label, 0, row, 1, 1);
-
-        // Value Cells
-        for col in 1..7 {
-            let val = match i {
-                0 => {
-                    if col == 4 {
-                        min_vals[col - 1]
-                    } else {
-                        max_vals[col - 1]
-                    }
-                } // Pace Max is actually the min number
-                1 => {
-                    if col == 4 {
-                        max_vals[col - 1]
-                    } else {
-                        min_vals[col - 1]
-                    }
-                }
-                _ => sums[col - 1] / count,
-            };
-
-            let text = if col == 4 {
-                pace_formatter(val)
-            } else {
-                format!("{:.2}", val)
-            };
-            let val_label = Label::builder()
-                .label(&format!("<b>{}</b>", text))
-                .use_markup(true)
-                .selectable(true)
-                .halign(gtk4::Align::Start)
-                .build();
-            ui.main_grid.attach(&val_label, col as i32, row, 1, 1);
-        }
-    }
-    // --- Append Totals Row ---
-    let totals_row = last_data_row + 4 + 3; // Positioned after Max, Min, and Avg
-
-    // Row Title: "Total" (Using your assumed translation key)
-    let total_row_title = "Total";
-    let totals_title = Label::builder()
-        .label(&format!("<b>{}</b>", total_row_title))
-        .use_markup(true)
-        .halign(gtk4::Align::Start)
-        .build();
-    ui.main_grid.attach(&totals_title, 0, totals_row, 1, 1);
-
-    for col in 1..7 {
-        // Skip the Pace column (Index 4 in the grid corresponds to sums[3])
-        if col == 4 {
-            continue;
+        for (zone, seconds) in hr_zone_totals.iter().enumerate() {
+            let col = zone as i32 + 1;
+            attach_label(
+                &ui.main_grid,
+                &format!("Z{}", zone + 1),
+                col,
+                zone_row,
+                true,
+            );
+            attach_label(
+                &ui.main_grid,
+                &format!("{:.0} min", seconds / 60.0),
+                col,
+                zone_row + 1,
+                false,
+            );
+            attach_label(
+                &ui.main_grid,
+                &format!("{:.0}%", seconds / zone_total * 100.0),
+                col,
+                zone_row + 2,
+                false,
+            );
         }
+    }
+}
 
-        let total_val = sums[col - 1];
+// Format zone times as the percentage in each zone, e.g. "10/55/25/8/2".
+pub fn format_zone_shares(times: &ZoneTimes) -> String {
+    let total: f64 = times.iter().sum();
+    times
+        .iter()
+        .map(|t| format!("{:.0}", t / total * 100.0))
+        .collect::<Vec<_>>()
+        .join("/")
+}
 
-        // Formatting: Use whole numbers for Calories and Elevation, 2 decimals for others
-        let text = if col == 2 || col >= 5 {
-            format!("{:.0}", total_val)
-        } else {
-            format!("{:.2}", total_val)
-        };
+// Format a pace in decimal minutes as mm:ss.
+pub fn format_pace(x: f64) -> String {
+    let mins = x.trunc();
+    let secs = x.fract() * 60.0;
+    format!("{:02.0}:{:02.0}", mins, secs)
+}
 
-        let total_label = Label::builder()
-            .label(&format!("<b>{}</b>", text))
-            .use_markup(true)
-            .selectable(true)
-            .halign(gtk4::Align::Start)
-            .build();
-        ui.main_grid
-            .attach(&total_label, col as i32, totals_row, 1, 1);
+// Format a time in seconds as m:ss, or h:mm:ss when an hour or longer.
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (hours, mins, secs) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, mins, secs)
+    } else {
+        format!("{}:{:02}", mins, secs)
     }
 }
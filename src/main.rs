@@ -24,22 +24,30 @@
  */
 #![windows_subsystem = "windows"]
 mod config;
-mod data;
+mod correction;
+mod detail;
 mod gui;
-mod i18n;
+mod manual;
+mod map;
+mod table;
 
 use crate::config::{
     APP_ID, AUTHOR, COPYRIGHT, ICON_NAME, PROGRAM_NAME, TESTER1, TESTER2, TESTER3, WindowConfig,
-    save_config,
+    load_config, save_config,
 };
 use crate::gio::spawn_blocking;
 use crate::gui::{
-    UserInterface, connect_interactive_widgets, construct_views_from_data, instantiate_ui,
+    UserInterface, connect_calendar_widgets, connect_chart_widgets, connect_elevation_widget,
+    connect_gear_widgets, connect_interactive_widgets, connect_manual_widgets, connect_tag_widgets,
+    connect_volume_widgets, construct_views_from_data, get_selected_start_end, instantiate_ui,
+    open_library, report_config_error,
 };
-use crate::i18n::tr;
-use data::{
+use curro_all_core::data::{
     TimeBucket, get_files_in_range, get_filtered_variants, get_time_range, process_fit_directory,
 };
+use curro_all_core::i18n::tr;
+#[cfg(feature = "sqlite")]
+use curro_all_core::store::Store;
 use gtk4::glib::clone;
 use gtk4::prelude::*;
 use gtk4::{ButtonsType, License, MessageDialog, MessageType, gio};
@@ -103,6 +111,47 @@ fn tie_it_all_together(
     connect_interactive_widgets(&ui, &data);
 }
 
+// Save the index to the activity database so it is available next session.
+// Only new or changed files are parsed. Runs on a worker thread.
+#[cfg(feature = "sqlite")]
+fn save_to_store(
+    database_file: &Path,
+    lookup: &dashmap::DashMap<chrono::DateTime<chrono::Utc>, PathBuf>,
+) {
+    if let Ok(mut store) = Store::open(database_file) {
+        let _ = store.sync_index(lookup);
+    }
+}
+
+// Restore the index of the library opened last session and show its views.
+#[cfg(feature = "sqlite")]
+fn load_from_store(ui: &Rc<UserInterface>) {
+    let library = load_config(Path::new(&ui.settings_file)).library;
+    if library.is_empty() {
+        return;
+    }
+    let Ok(mut store) = Store::open(Path::new(&ui.database_file)) else {
+        return;
+    };
+    let _ = store.prune_missing();
+    let Ok(lookup) = store.load_index(Path::new(&library)) else {
+        return;
+    };
+    if lookup.is_empty() {
+        return;
+    }
+    for entry in lookup.iter() {
+        ui.lookup.insert(*entry.key(), entry.value().clone());
+    }
+    ui.time_widget.set_visible(true);
+    let index = ui.time_widget.selected() as usize;
+    if let Some(selected_variant) = get_filtered_variants().get(index) {
+        let (start, end) = get_time_range(*selected_variant);
+        let result = get_files_in_range(&ui.lookup, start, end);
+        tie_it_all_together(&result, ui);
+    }
+}
+
 // Wrapper for build_gui to handle no files from command line.
 fn build_gui_no_files(app: &Application) {
     build_gui(&app, &[], "");
@@ -118,7 +167,24 @@ fn build_gui(app: &Application, _files: &[gtk4::gio::File], _: &str) {
     // different closures ("button-clicked" and "native window response").
     let ui_rc = Rc::new(ui_original);
     let ui1 = Rc::clone(&ui_rc);
+    connect_calendar_widgets(&ui_rc);
+    connect_chart_widgets(&ui_rc);
+    connect_volume_widgets(&ui_rc);
+    connect_gear_widgets(&ui_rc);
+    connect_tag_widgets(&ui_rc);
+    connect_manual_widgets(&ui_rc);
+    connect_elevation_widget(&ui_rc);
+    report_config_error(&ui_rc, &load_config(Path::new(&ui_rc.settings_file)));
     ui_rc.win.present();
+    #[cfg(feature = "sqlite")]
+    load_from_store(&ui_rc);
+    // Manual activities are shown even before any folder is opened.
+    if !ui_rc.time_widget.is_visible() && !ui_rc.lookup.is_empty() {
+        ui_rc.time_widget.set_visible(true);
+        let (start, end) = get_selected_start_end(&ui_rc);
+        let result = get_files_in_range(&ui_rc.lookup, start, end);
+        tie_it_all_together(&result, &ui_rc);
+    }
 
     // Handle callbacks for btn and about_btn.
     let open_action = gio::SimpleAction::new("open", None);
@@ -142,6 +208,7 @@ fn build_gui(app: &Application, _files: &[gtk4::gio::File], _: &str) {
                     if response == gtk4::ResponseType::Accept {
                         if let Some(file) = chooser.file() {
                             if let Some(path) = file.path() {
+                                let library = path.clone();
                                 let target_dir = path;
                                 let ui_async = ui1.clone();
 
@@ -152,6 +219,8 @@ fn build_gui(app: &Application, _files: &[gtk4::gio::File], _: &str) {
                                     .status_label
                                     .set_text(&tr("STATUS_PROCESSING", None));
 
+                                #[cfg(feature = "sqlite")]
+                                let database_file = PathBuf::from(&ui_async.database_file);
                                 glib::MainContext::default().spawn_local(async move {
                                     let lookup =
                                         spawn_blocking(move || process_fit_directory(&target_dir))
@@ -162,6 +231,7 @@ fn build_gui(app: &Application, _files: &[gtk4::gio::File], _: &str) {
                                             .lookup
                                             .insert(entry.key().clone(), entry.value().clone());
                                     }
+                                    open_library(&ui_async, &library);
                                     // Reset Spinner
                                     ui_async.spinner.stop();
                                     ui_async.spinner.set_visible(false);
@@ -172,6 +242,13 @@ fn build_gui(app: &Application, _files: &[gtk4::gio::File], _: &str) {
                                     let (start, end) = get_time_range(selected_variant.clone());
                                     let result = get_files_in_range(&ui_async.lookup, start, end);
                                     tie_it_all_together(&result, &ui_async);
+                                    // Store new files for the next session once the
+                                    // views are shown, without holding them up.
+                                    #[cfg(feature = "sqlite")]
+                                    let _ = spawn_blocking(move || {
+                                        save_to_store(&database_file, &lookup)
+                                    })
+                                    .await;
                                 });
                             }
                         }
@@ -277,7 +354,13 @@ fn build_gui(app: &Application, _files: &[gtk4::gio::File], _: &str) {
                 right_frame_split: ui1.right_frame_pane.position(),
                 left_frame_split: ui1.left_frame_pane.position(),
                 units_index: ui1.units_widget.selected(),
+                // Settings only edited in the file are kept as they are.
+                ..load_config(config_path)
             };
+            // An unreadable settings file is left as it is for the user to fix.
+            if current_config.load_error.is_some() {
+                return glib::signal::Propagation::Proceed;
+            }
             match save_config(&current_config, config_path) {
                 Ok(_) => glib::signal::Propagation::Proceed,
                 Err(e) => {
@@ -0,0 +1,203 @@
+// Editor of a manual activity: a workout without a file (gym session,
+// treadmill run with a flat watch battery, old paper log) entered by hand.
+
+use crate::gui::format_duration;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use curro_all_core::data::{Units, cvt_altitude, cvt_distance};
+use curro_all_core::i18n::tr;
+use curro_all_core::sidecar::ManualActivity;
+use gtk4::glib::clone;
+use gtk4::prelude::*;
+use gtk4::{Button, Entry, Grid, Label, Orientation, ScrolledWindow, SpinButton, TextView, Window};
+
+// How start times are typed, in local time.
+const START_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+// Parse a duration written as minutes, m:ss or h:mm:ss into seconds.
+pub fn parse_duration(text: &str) -> Option<f64> {
+    let parts: Vec<f64> = text
+        .trim()
+        .split(':')
+        .map(|p| p.trim().parse::<f64>().ok())
+        .collect::<Option<_>>()?;
+    let seconds = match parts[..] {
+        [mins] => mins * 60.0,
+        [mins, secs] => mins * 60.0 + secs,
+        [hours, mins, secs] => hours * 3600.0 + mins * 60.0 + secs,
+        _ => return None,
+    };
+    (seconds >= 0.0).then_some(seconds)
+}
+
+fn parse_start(text: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(text.trim(), START_FORMAT).ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+// Show the editor. A new activity has no delete button. on_save gets the
+// activity (meters and seconds) and its notes.
+pub fn show_manual_dialog(
+    parent: Option<&Window>,
+    units: &Units,
+    activity: ManualActivity,
+    notes: String,
+    is_new: bool,
+    on_save: impl Fn(ManualActivity, String) + 'static,
+    on_delete: impl Fn() + 'static,
+) {
+    let grid = Grid::builder()
+        .column_spacing(12)
+        .row_spacing(8)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .build();
+    let (distance_key, altitude_key) = match units {
+        Units::US => ("LABEL_DISTANCE_MILES", "LABEL_ASCENT_FEET"),
+        _ => ("LABEL_DISTANCE_KM", "LABEL_ASCENT_METERS"),
+    };
+    // Meters in one unit shown.
+    let meters_per_unit = 1.0 / cvt_distance(1.0, units) as f64;
+    let meters_per_altitude_unit = 1.0 / cvt_altitude(1.0, units) as f64;
+
+    let start = Entry::builder()
+        .text(
+            activity
+                .start
+                .with_timezone(&Local)
+                .format(START_FORMAT)
+                .to_string(),
+        )
+        .placeholder_text(START_FORMAT)
+        .hexpand(true)
+        .build();
+    let sport = Entry::builder()
+        .text(&activity.sport)
+        .placeholder_text(tr("MANUAL_SPORT_PLACEHOLDER", None))
+        .build();
+    let distance = SpinButton::with_range(0.0, 1000.0, 0.1);
+    distance.set_digits(2);
+    distance.set_value(activity.distance / meters_per_unit);
+    let duration = Entry::builder()
+        .text(format_duration(activity.duration))
+        .placeholder_text("h:mm:ss")
+        .build();
+    let ascent = SpinButton::with_range(0.0, 30000.0, 10.0);
+    ascent.set_value((activity.ascent as f64 / meters_per_altitude_unit).round());
+    let calories = SpinButton::with_range(0.0, 20000.0, 10.0);
+    calories.set_value(activity.calories as f64);
+    let notes_view = TextView::builder()
+        .wrap_mode(gtk4::WrapMode::WordChar)
+        .build();
+    notes_view.buffer().set_text(&notes);
+    let notes_window = ScrolledWindow::builder()
+        .min_content_height(100)
+        .vexpand(true)
+        .child(&notes_view)
+        .build();
+    for (row, (key, widget)) in [
+        ("MANUAL_START", start.upcast_ref::<gtk4::Widget>()),
+        ("MANUAL_SPORT", sport.upcast_ref()),
+        (distance_key, distance.upcast_ref()),
+        ("MANUAL_DURATION", duration.upcast_ref()),
+        (altitude_key, ascent.upcast_ref()),
+        ("LABEL_CALORIES", calories.upcast_ref()),
+        ("LABEL_NOTES", notes_window.upcast_ref()),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let label = Label::builder()
+            .label(tr(key, None))
+            .halign(gtk4::Align::Start)
+            .valign(gtk4::Align::Start)
+            .build();
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(widget, 1, row as i32, 1, 1);
+    }
+    let delete = Button::with_label(&tr("DELETE_BUTTON", None));
+    delete.add_css_class("destructive-action");
+    delete.set_visible(!is_new);
+    let cancel = Button::with_label(&tr("CANCEL_BUTTON", None));
+    let save = Button::with_label(&tr("SAVE_BUTTON", None));
+    save.add_css_class("suggested-action");
+    let buttons = gtk4::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(10)
+        .halign(gtk4::Align::End)
+        .build();
+    buttons.append(&delete);
+    buttons.append(&cancel);
+    buttons.append(&save);
+    grid.attach(&buttons, 0, 7, 2, 1);
+
+    let title = if is_new {
+        "MANUAL_ADD_TITLE"
+    } else {
+        "MANUAL_EDIT_TITLE"
+    };
+    let window = Window::builder()
+        .title(tr(title, None))
+        .modal(true)
+        .default_width(420)
+        .default_height(460)
+        .child(&grid)
+        .build();
+    window.set_transient_for(parent);
+    cancel.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| window.close()
+    ));
+    delete.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| {
+            on_delete();
+            window.close();
+        }
+    ));
+    save.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| {
+            // Mark what cannot be read and keep the editor open.
+            let parsed_start = parse_start(&start.text());
+            let parsed_duration = parse_duration(&duration.text());
+            for (entry, ok) in [
+                (&start, parsed_start.is_some()),
+                (&duration, parsed_duration.is_some()),
+            ] {
+                if ok {
+                    entry.remove_css_class("error");
+                } else {
+                    entry.add_css_class("error");
+                }
+            }
+            let (Some(start), Some(duration)) = (parsed_start, parsed_duration) else {
+                return;
+            };
+            let buffer = notes_view.buffer();
+            on_save(
+                ManualActivity {
+                    start,
+                    sport: sport.text().trim().to_lowercase(),
+                    distance: distance.value() * meters_per_unit,
+                    duration,
+                    ascent: (ascent.value() * meters_per_altitude_unit).round() as u16,
+                    calories: calories.value() as u16,
+                },
+                buffer
+                    .text(&buffer.start_iter(), &buffer.end_iter(), false)
+                    .trim()
+                    .to_string(),
+            );
+            window.close();
+        }
+    ));
+    window.present();
+}
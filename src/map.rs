@@ -0,0 +1,231 @@
+// Route map view: the GPS tracks of the selected range drawn with cairo over
+// optional offline map tiles.
+
+use chrono::{DateTime, Utc};
+use curro_all_core::heatmap::Heatmap;
+use curro_all_core::i18n::tr;
+use curro_all_core::map::{Bounds, TILE_SIZE, TileSource, Track, Viewport};
+use curro_all_core::privacy::{PrivacyZone, clip_track, hidden_points};
+use gtk4::cairo::{Context, Format, ImageSurface};
+use gtk4::gdk::prelude::GdkCairoContextExt;
+use gtk4::gdk_pixbuf::{Colorspace, Pixbuf};
+use gtk4::glib;
+use libadwaita::StyleManager;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::Path;
+
+// Share of the view left empty around the tracks.
+const MAP_PADDING: f64 = 0.05;
+
+// Size in pixels of an exported heatmap image.
+pub const HEATMAP_EXPORT_SIZE: (i32, i32) = (1600, 1200);
+
+// Track colors, cycled when several activities are overlaid.
+const TRACK_COLORS: [(f64, f64, f64); 6] = [
+    (0.85, 0.15, 0.15),
+    (0.10, 0.45, 0.85),
+    (0.15, 0.65, 0.25),
+    (0.60, 0.25, 0.75),
+    (0.95, 0.55, 0.10),
+    (0.05, 0.65, 0.70),
+];
+
+pub struct MapView {
+    // Per activity, the parts of its track outside the privacy zones.
+    tracks: Vec<(DateTime<Utc>, Vec<Track>)>,
+    // Per activity, the positions hidden by the privacy zones.
+    hidden: Vec<Track>,
+    zones: Vec<PrivacyZone>,
+    tiles: Option<TileSource>,
+    // Decoded tiles, kept across redraws (resizing the pane redraws often).
+    tile_cache: RefCell<HashMap<(u32, u32, u32), Option<Pixbuf>>>,
+    // The heatmap of the last viewport drawn.
+    heat_cache: RefCell<Option<(Viewport, Pixbuf)>>,
+}
+
+impl MapView {
+    pub fn new(
+        tracks: Vec<(DateTime<Utc>, Track)>,
+        tiles: Option<TileSource>,
+        zones: Vec<PrivacyZone>,
+    ) -> Self {
+        MapView {
+            hidden: tracks
+                .iter()
+                .map(|(_, t)| hidden_points(t, &zones))
+                .collect(),
+            tracks: tracks
+                .iter()
+                .map(|(timestamp, t)| (*timestamp, clip_track(t, &zones)))
+                .collect(),
+            zones,
+            tiles,
+            tile_cache: RefCell::new(HashMap::new()),
+            heat_cache: RefCell::new(None),
+        }
+    }
+
+    // Rasterize the tracks into a heatmap image for the viewport.
+    fn heatmap(&self, viewport: &Viewport) -> Pixbuf {
+        let mut cache = self.heat_cache.borrow_mut();
+        if let Some((cached, pixbuf)) = cache.as_ref()
+            && cached == viewport
+        {
+            return pixbuf.clone();
+        }
+        let heatmap = Heatmap::from_tracks(
+            self.tracks
+                .iter()
+                .flat_map(|(_, parts)| parts)
+                .collect::<Vec<_>>(),
+            viewport,
+        );
+        let pixbuf = Pixbuf::from_bytes(
+            &glib::Bytes::from_owned(heatmap.to_rgba()),
+            Colorspace::Rgb,
+            true,
+            8,
+            heatmap.width as i32,
+            heatmap.height as i32,
+            heatmap.width as i32 * 4,
+        );
+        *cache = Some((*viewport, pixbuf.clone()));
+        pixbuf
+    }
+
+    // Render the heatmap, over the background and tiles, to a PNG file.
+    pub fn export_heatmap_png(
+        &self,
+        path: &Path,
+        width: i32,
+        height: i32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let surface = ImageSurface::create(Format::ARgb32, width, height)?;
+        {
+            let cr = Context::new(&surface)?;
+            self.draw(&cr, width as f64, height as f64, true, false);
+        }
+        surface.flush();
+        let pixbuf = gtk4::gdk::pixbuf_get_from_surface(&surface, 0, 0, width, height)
+            .ok_or("Could not read the rendered heatmap")?;
+        pixbuf.savev(path, "png", &[])?;
+        Ok(())
+    }
+
+    fn tile(&self, zoom: u32, x: u32, y: u32) -> Option<Pixbuf> {
+        let tiles = self.tiles.as_ref()?;
+        self.tile_cache
+            .borrow_mut()
+            .entry((zoom, x, y))
+            .or_insert_with(|| {
+                let bytes = tiles.tile(zoom, x, y)?;
+                Pixbuf::from_read(Cursor::new(bytes)).ok()
+            })
+            .clone()
+    }
+
+    // Draw the tiles covering the view, then every track, or their heatmap,
+    // on top. The preview adds the privacy zones and what they hide.
+    pub fn draw(&self, cr: &Context, width: f64, height: f64, heatmap: bool, preview: bool) {
+        let is_dark = StyleManager::default().is_dark();
+        if is_dark {
+            cr.set_source_rgb(0.15, 0.15, 0.15);
+        } else {
+            cr.set_source_rgb(0.93, 0.93, 0.91);
+        }
+        let _ = cr.paint();
+
+        // Only visible positions count, so the framing gives nothing away.
+        let Some(bounds) = Bounds::of_tracks(self.tracks.iter().flat_map(|(_, parts)| parts))
+        else {
+            if is_dark {
+                cr.set_source_rgb(0.8, 0.8, 0.8);
+            } else {
+                cr.set_source_rgb(0.3, 0.3, 0.3);
+            }
+            cr.set_font_size(14.0);
+            cr.move_to(20.0, 30.0);
+            let _ = cr.show_text(&tr("MESSAGE_NO_GPS", None));
+            return;
+        };
+
+        let viewport = Viewport::fit(&bounds, width, height, MAP_PADDING);
+        let scale = viewport.scale();
+        let (center_x, center_y) = (viewport.center_x, viewport.center_y);
+        let zoom = viewport.zoom;
+
+        // Tiles overlapping the view.
+        if self.tiles.is_some() {
+            let tile_count = 1i64 << zoom;
+            let first_x = ((center_x * scale - width / 2.0) / TILE_SIZE).floor() as i64;
+            let first_y = ((center_y * scale - height / 2.0) / TILE_SIZE).floor() as i64;
+            let last_x = ((center_x * scale + width / 2.0) / TILE_SIZE).floor() as i64;
+            let last_y = ((center_y * scale + height / 2.0) / TILE_SIZE).floor() as i64;
+            for ty in first_y.max(0)..=last_y.min(tile_count - 1) {
+                for tx in first_x.max(0)..=last_x.min(tile_count - 1) {
+                    if let Some(pixbuf) = self.tile(zoom, tx as u32, ty as u32) {
+                        let (sx, sy) = viewport.world_to_pixel(
+                            tx as f64 * TILE_SIZE / scale,
+                            ty as f64 * TILE_SIZE / scale,
+                        );
+                        cr.set_source_pixbuf(&pixbuf, sx, sy);
+                        let _ = cr.paint();
+                    }
+                }
+            }
+        }
+
+        if heatmap {
+            cr.set_source_pixbuf(&self.heatmap(&viewport), 0.0, 0.0);
+            let _ = cr.paint();
+        } else {
+            cr.set_line_width(2.5);
+            cr.set_line_join(gtk4::cairo::LineJoin::Round);
+            cr.set_line_cap(gtk4::cairo::LineCap::Round);
+            for (i, (_, parts)) in self.tracks.iter().enumerate() {
+                let (r, g, b) = TRACK_COLORS[i % TRACK_COLORS.len()];
+                cr.set_source_rgba(r, g, b, 0.85);
+                for part in parts {
+                    for (j, &(lat, lon)) in part.iter().enumerate() {
+                        let (sx, sy) = viewport.to_pixel(lat, lon);
+                        if j == 0 {
+                            cr.move_to(sx, sy);
+                        } else {
+                            cr.line_to(sx, sy);
+                        }
+                    }
+                    let _ = cr.stroke();
+                }
+            }
+        }
+        if preview {
+            self.draw_privacy_preview(cr, &viewport);
+        }
+    }
+
+    // Outline each privacy zone and dot the positions it hides.
+    fn draw_privacy_preview(&self, cr: &Context, viewport: &Viewport) {
+        for zone in self.zones.iter().filter(|z| z.radius.is_finite()) {
+            let (cx, cy) = viewport.to_pixel(zone.lat, zone.lon);
+            let radius = viewport.meters_to_pixels(zone.radius, zone.lat);
+            cr.new_path();
+            cr.arc(cx, cy, radius, 0.0, 2.0 * std::f64::consts::PI);
+            cr.set_source_rgba(0.5, 0.5, 0.5, 0.25);
+            let _ = cr.fill_preserve();
+            cr.set_source_rgba(0.3, 0.3, 0.3, 0.9);
+            cr.set_line_width(1.5);
+            cr.set_dash(&[6.0, 4.0], 0.0);
+            let _ = cr.stroke();
+            cr.set_dash(&[], 0.0);
+        }
+        cr.set_source_rgba(0.4, 0.4, 0.4, 0.8);
+        for &(lat, lon) in self.hidden.iter().flatten() {
+            let (sx, sy) = viewport.to_pixel(lat, lon);
+            cr.new_path();
+            cr.arc(sx, sy, 1.5, 0.0, 2.0 * std::f64::consts::PI);
+            let _ = cr.fill();
+        }
+    }
+}
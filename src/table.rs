@@ -0,0 +1,811 @@
+// Activity table of the summary: one row per activity in a virtualized
+// ColumnView, with click-to-sort columns, a filter bar, columns the user may
+// hide, the statistics of the rows shown in a footer and an editor for the
+// title, tags, notes and perceived exertion of an activity. Manual
+// activities are marked by their date, which opens their own editor, and so
+// are activities whose recorded values were corrected.
+
+use crate::config::{load_config, save_config};
+use crate::detail::show_activity_detail;
+use crate::gui::{format_pace, format_zone_shares};
+use chrono::{DateTime, Utc};
+use curro_all_core::data::{PlottableData, SessionStats, Units};
+use curro_all_core::filter::ActivityFilter;
+use curro_all_core::i18n::tr;
+use curro_all_core::metrics::{Metric, find_metric};
+use curro_all_core::sidecar::{ActivityMeta, MAX_RPE, is_manual_path, parse_tags};
+use curro_all_core::statistics::{Statistics, Weighting, range_statistics};
+use curro_all_core::zones::{ActivityZones, ZoneTimes};
+use gtk4::glib::{BoxedAnyObject, clone};
+use gtk4::prelude::*;
+use gtk4::{
+    Button, CheckButton, ColumnView, ColumnViewColumn, CustomFilter, CustomSorter, Entry,
+    FilterChange, FilterListModel, Label, ListItem, MenuButton, Orientation, Popover,
+    ScrolledWindow, SignalListItemFactory, SingleSelection, SortListModel, SortType, SpinButton,
+    TextView, Window, gio,
+};
+use libadwaita::ApplicationWindow;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// One activity of the table, its stats in the units shown.
+pub struct ActivityRow {
+    pub timestamp: DateTime<Utc>,
+    pub path: Option<PathBuf>,
+    pub stats: SessionStats,
+    pub zones: ActivityZones,
+    // Title, tags, notes and exertion from the library sidecar.
+    pub meta: ActivityMeta,
+    // Whether the stats include corrections of the recorded values.
+    pub corrected: bool,
+}
+
+// Called with an activity's edited metadata.
+type EditedCallback = Rc<dyn Fn(ActivityMeta)>;
+// Called to add a manual activity (None) or edit the one starting then.
+type ManualCallback = Rc<RefCell<Option<Rc<dyn Fn(Option<DateTime<Utc>>)>>>>;
+// Called to correct the recorded values of an activity.
+type CorrectCallback = Rc<dyn Fn(DateTime<Utc>, PathBuf)>;
+
+impl ActivityRow {
+    pub fn is_manual(&self) -> bool {
+        self.path.as_deref().is_some_and(is_manual_path)
+    }
+}
+
+// A column after the date: its settings name, how to show a row and the
+// number it sorts by, then by its text.
+struct TableColumn {
+    id: &'static str,
+    text: fn(&ActivityRow) -> String,
+    value: fn(&ActivityRow) -> f64,
+}
+
+// Share of the time above zone 3, to sort zone breakdowns by intensity.
+fn hard_share(times: Option<ZoneTimes>) -> f64 {
+    times.map_or(-1.0, |t| {
+        let total: f64 = t.iter().sum();
+        (t[3] + t[4]) / total.max(1.0)
+    })
+}
+
+const COLUMNS: [TableColumn; 12] = [
+    TableColumn {
+        id: "title",
+        text: |r| r.meta.title.clone(),
+        value: |_| 0.0,
+    },
+    TableColumn {
+        id: "distance",
+        text: |r| format!("{:.2}", r.stats.distance),
+        value: |r| r.stats.distance,
+    },
+    TableColumn {
+        id: "calories",
+        text: |r| r.stats.calories.to_string(),
+        value: |r| r.stats.calories as f64,
+    },
+    TableColumn {
+        id: "duration",
+        text: |r| format!("{:.1}", r.stats.duration),
+        value: |r| r.stats.duration,
+    },
+    TableColumn {
+        id: "moving_time",
+        text: |r| format!("{:.1}", r.stats.moving_time),
+        value: |r| r.stats.moving_time,
+    },
+    TableColumn {
+        id: "pace",
+        text: |r| format_pace(r.stats.enhanced_speed),
+        value: |r| r.stats.enhanced_speed,
+    },
+    TableColumn {
+        id: "ascent",
+        text: |r| format!("{:.0}", r.stats.ascent),
+        value: |r| r.stats.ascent as f64,
+    },
+    TableColumn {
+        id: "descent",
+        text: |r| format!("{:.0}", r.stats.descent),
+        value: |r| r.stats.descent as f64,
+    },
+    TableColumn {
+        id: "pace_zones",
+        text: |r| {
+            r.zones
+                .pace
+                .as_ref()
+                .map_or_else(String::new, format_zone_shares)
+        },
+        value: |r| hard_share(r.zones.pace),
+    },
+    TableColumn {
+        id: "power_zones",
+        text: |r| {
+            r.zones
+                .power
+                .as_ref()
+                .map_or_else(String::new, format_zone_shares)
+        },
+        value: |r| hard_share(r.zones.power),
+    },
+    TableColumn {
+        id: "rpe",
+        text: |r| r.meta.rpe.map_or_else(String::new, |rpe| rpe.to_string()),
+        value: |r| r.meta.rpe.map_or(-1.0, f64::from),
+    },
+    TableColumn {
+        id: "tags",
+        text: |r| r.meta.tags.join(", "),
+        value: |_| 0.0,
+    },
+];
+
+// Column titles, date first, for the unit system shown.
+fn column_titles(units: &Units) -> [String; COLUMNS.len() + 1] {
+    let (dist_label, alt_label, pace_label) = match units {
+        Units::Metric => (
+            tr("LABEL_DISTANCE_KM", None),
+            "m",
+            tr("LABEL_PACE_METRIC", None),
+        ),
+        _ => (
+            tr("LABEL_DISTANCE_MILES", None),
+            "ft",
+            tr("LABEL_PACE_US", None),
+        ),
+    };
+    [
+        tr("LABEL_DATE_TIME", None),
+        tr("LABEL_TITLE", None),
+        dist_label,
+        "Calories".to_string(),
+        tr("LABEL_DURATION", None),
+        tr("LABEL_MOVING_TIME", None),
+        pace_label,
+        format!("Asc({})", alt_label),
+        format!("Des({})", alt_label),
+        tr("LABEL_PACE_ZONES", None),
+        tr("LABEL_POWER_ZONES", None),
+        tr("LABEL_RPE", None),
+        tr("LABEL_TAGS", None),
+    ]
+}
+
+fn row_of(object: &gtk4::glib::Object) -> std::cell::Ref<'_, ActivityRow> {
+    object
+        .downcast_ref::<BoxedAnyObject>()
+        .expect("The table holds activity rows")
+        .borrow::<ActivityRow>()
+}
+
+pub struct ActivityTable {
+    pub view: ColumnView,
+    pub filter_bar: gtk4::Box,
+    // Statistics of the rows passing the filter.
+    pub footer: gtk4::Grid,
+    store: gio::ListStore,
+    selection: SingleSelection,
+    // Unit system of the rows.
+    units: Rc<RefCell<Units>>,
+    // What the footer averages are weighted by.
+    weighting: Cell<Weighting>,
+    // The date column, then one per entry of COLUMNS.
+    columns: Vec<ColumnViewColumn>,
+    // Settings names of the columns the user hid.
+    hidden: Rc<RefCell<Vec<String>>>,
+    // Whether each column has anything to show (zone columns may not).
+    has_data: RefCell<Vec<bool>>,
+    column_checks: Vec<CheckButton>,
+    // Edits the metadata of the selected activity.
+    edit_button: Button,
+    edited: RefCell<Option<EditedCallback>>,
+    manual: ManualCallback,
+    // Corrects the values of the selected activity.
+    correct_button: Button,
+    correct: RefCell<Option<CorrectCallback>>,
+}
+
+impl ActivityTable {
+    pub fn new(settings_file: &str) -> Rc<Self> {
+        let units = Rc::new(RefCell::new(Units::Metric));
+        let store = gio::ListStore::new::<BoxedAnyObject>();
+        let manual: ManualCallback = Rc::new(RefCell::new(None));
+        let active_filter = Rc::new(RefCell::new(ActivityFilter::default()));
+        let filter = CustomFilter::new(clone!(
+            #[strong]
+            active_filter,
+            move |object| {
+                let row = row_of(object);
+                active_filter
+                    .borrow()
+                    .matches(&row.timestamp, &row.stats, &row.meta.search_text())
+            }
+        ));
+        let filter_model = FilterListModel::new(Some(store.clone()), Some(filter.clone()));
+        let view = ColumnView::builder()
+            .show_column_separators(true)
+            .show_row_separators(true)
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        let sort_model = SortListModel::new(Some(filter_model.clone()), view.sorter());
+        let selection = SingleSelection::builder()
+            .model(&sort_model)
+            .autoselect(false)
+            .can_unselect(true)
+            .build();
+        view.set_model(Some(&selection));
+
+        // The date opens the lap detail of the activity, or the editor of a
+        // manual activity.
+        let date_factory = SignalListItemFactory::new();
+        date_factory.connect_setup(clone!(
+            #[strong]
+            units,
+            #[strong]
+            manual,
+            move |_, item| {
+                let item = item.downcast_ref::<ListItem>().unwrap().clone();
+                let button = Button::builder()
+                    .tooltip_text(tr("TOOLTIP_DATE_BUTTON", None))
+                    .halign(gtk4::Align::Start)
+                    .has_frame(false)
+                    .build();
+                button.connect_clicked(clone!(
+                    #[weak]
+                    item,
+                    #[strong]
+                    units,
+                    #[strong]
+                    manual,
+                    move |button| {
+                        let win = button.root().and_downcast::<ApplicationWindow>();
+                        if let (Some(win), Some(object)) = (win, item.item()) {
+                            let row = row_of(&object);
+                            if row.is_manual() {
+                                let callback = manual.borrow().clone();
+                                let timestamp = row.timestamp;
+                                drop(row);
+                                if let Some(callback) = callback {
+                                    callback(Some(timestamp));
+                                }
+                            } else if let Some(path) = &row.path {
+                                show_activity_detail(&win, &units.borrow(), row.timestamp, path);
+                            }
+                        }
+                    }
+                ));
+                item.set_child(Some(&button));
+            }
+        ));
+        date_factory.connect_bind(|_, item| {
+            let item = item.downcast_ref::<ListItem>().unwrap();
+            if let (Some(object), Some(button)) =
+                (item.item(), item.child().and_downcast::<Button>())
+            {
+                let row = row_of(&object);
+                let date = row.timestamp.format("%Y-%m-%d").to_string();
+                if row.is_manual() {
+                    button.set_label(&format!("{} {}", date, tr("MANUAL_MARK", None)));
+                    button.set_tooltip_text(Some(&tr("TOOLTIP_MANUAL_ACTIVITY", None)));
+                } else if row.corrected {
+                    button.set_label(&format!("{} {}", date, tr("EDITED_MARK", None)));
+                    button.set_tooltip_text(Some(&tr("TOOLTIP_EDITED_ACTIVITY", None)));
+                } else {
+                    button.set_label(&date);
+                    button.set_tooltip_text(Some(&tr("TOOLTIP_DATE_BUTTON", None)));
+                }
+            }
+        });
+        let date_column = ColumnViewColumn::builder().factory(&date_factory).build();
+        date_column.set_sorter(Some(&CustomSorter::new(|a, b| {
+            row_of(a).timestamp.cmp(&row_of(b).timestamp).into()
+        })));
+        view.append_column(&date_column);
+        let mut columns = vec![date_column.clone()];
+
+        for column in &COLUMNS {
+            let text = column.text;
+            let value = column.value;
+            let factory = SignalListItemFactory::new();
+            factory.connect_setup(|_, item| {
+                let item = item.downcast_ref::<ListItem>().unwrap();
+                item.set_child(Some(&Label::builder().halign(gtk4::Align::Start).build()));
+            });
+            factory.connect_bind(move |_, item| {
+                let item = item.downcast_ref::<ListItem>().unwrap();
+                if let (Some(object), Some(label)) =
+                    (item.item(), item.child().and_downcast::<Label>())
+                {
+                    label.set_text(&text(&row_of(&object)));
+                }
+            });
+            let view_column = ColumnViewColumn::builder()
+                .factory(&factory)
+                .resizable(true)
+                .build();
+            view_column.set_sorter(Some(&CustomSorter::new(move |a, b| {
+                let (a, b) = (row_of(a), row_of(b));
+                value(&a)
+                    .partial_cmp(&value(&b))
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| text(&a).to_lowercase().cmp(&text(&b).to_lowercase()))
+                    .into()
+            })));
+            view.append_column(&view_column);
+            columns.push(view_column);
+        }
+        view.sort_by_column(Some(&date_column), SortType::Ascending);
+
+        // Filter bar: the filter text and the column chooser.
+        let entry = Entry::builder()
+            .placeholder_text(tr("FILTER_PLACEHOLDER", None))
+            .tooltip_text(tr("TOOLTIP_FILTER", None))
+            .hexpand(true)
+            .build();
+        entry.connect_changed(clone!(
+            #[strong]
+            active_filter,
+            #[weak]
+            filter,
+            move |entry| match ActivityFilter::parse(&entry.text()) {
+                Ok(parsed) => {
+                    entry.remove_css_class("error");
+                    entry.set_tooltip_text(Some(&tr("TOOLTIP_FILTER", None)));
+                    *active_filter.borrow_mut() = parsed;
+                    filter.changed(FilterChange::Different);
+                }
+                Err(term) => {
+                    entry.add_css_class("error");
+                    entry.set_tooltip_text(Some(&format!(
+                        "{}: {}",
+                        tr("FILTER_ERROR", None),
+                        term
+                    )));
+                }
+            }
+        ));
+        let columns_box = gtk4::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(5)
+            .build();
+        let hidden = Rc::new(RefCell::new(
+            load_config(Path::new(settings_file)).hidden_columns,
+        ));
+        let mut column_checks = Vec::new();
+        for column in &COLUMNS {
+            let check = CheckButton::builder()
+                .active(!hidden.borrow().iter().any(|h| h == column.id))
+                .build();
+            columns_box.append(&check);
+            column_checks.push(check);
+        }
+        let columns_button = MenuButton::builder()
+            .label(tr("COLUMNS_BUTTON", None))
+            .tooltip_text(tr("TOOLTIP_COLUMNS_BUTTON", None))
+            .popover(&Popover::builder().child(&columns_box).build())
+            .build();
+        let filter_bar = gtk4::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(10)
+            .margin_top(5)
+            .margin_start(5)
+            .margin_end(5)
+            .build();
+        let edit_button = Button::builder()
+            .label(tr("EDIT_ACTIVITY_BUTTON", None))
+            .tooltip_text(tr("TOOLTIP_EDIT_ACTIVITY", None))
+            .sensitive(false)
+            .build();
+        let add_button = Button::builder()
+            .icon_name("list-add-symbolic")
+            .tooltip_text(tr("TOOLTIP_MANUAL_ADD", None))
+            .build();
+        add_button.connect_clicked(clone!(
+            #[strong]
+            manual,
+            move |_| {
+                let callback = manual.borrow().clone();
+                if let Some(callback) = callback {
+                    callback(None);
+                }
+            }
+        ));
+        let correct_button = Button::builder()
+            .label(tr("CORRECT_ACTIVITY_BUTTON", None))
+            .tooltip_text(tr("TOOLTIP_CORRECT_ACTIVITY", None))
+            .sensitive(false)
+            .build();
+        filter_bar.append(&entry);
+        filter_bar.append(&add_button);
+        filter_bar.append(&edit_button);
+        filter_bar.append(&correct_button);
+        filter_bar.append(&columns_button);
+
+        let table = Rc::new(ActivityTable {
+            view,
+            filter_bar,
+            footer: gtk4::Grid::builder()
+                .column_spacing(12)
+                .row_spacing(6)
+                .margin_start(10)
+                .margin_end(10)
+                .margin_top(10)
+                .build(),
+            store,
+            selection,
+            units,
+            weighting: Cell::new(Weighting::None),
+            columns,
+            hidden,
+            has_data: RefCell::new(vec![true; COLUMNS.len()]),
+            column_checks,
+            edit_button,
+            edited: RefCell::new(None),
+            manual,
+            correct_button,
+            correct: RefCell::new(None),
+        });
+        let settings_file = settings_file.to_string();
+        for (check, column) in table.column_checks.iter().zip(&COLUMNS) {
+            let id = column.id;
+            let settings_file = settings_file.clone();
+            check.connect_toggled(clone!(
+                #[weak]
+                table,
+                move |check| {
+                    {
+                        let mut hidden = table.hidden.borrow_mut();
+                        hidden.retain(|h| h != id);
+                        if !check.is_active() {
+                            hidden.push(id.to_string());
+                        }
+                        let config_path = Path::new(&settings_file);
+                        let mut config = load_config(config_path);
+                        config.hidden_columns = hidden.clone();
+                        let _ = save_config(&config, config_path);
+                    }
+                    table.update_column_visibility();
+                }
+            ));
+        }
+        // Double-click or Enter on a row, or the edit button, opens the
+        // editor of its metadata.
+        table.view.connect_activate(clone!(
+            #[weak]
+            table,
+            move |_, position| table.edit_row(position)
+        ));
+        table.edit_button.connect_clicked(clone!(
+            #[weak]
+            table,
+            move |_| table.edit_row(table.selection.selected())
+        ));
+        table.selection.connect_selected_notify(clone!(
+            #[weak]
+            table,
+            move |selection| {
+                let selected = selection.selected() != gtk4::INVALID_LIST_POSITION;
+                table.edit_button.set_sensitive(selected);
+                table.correct_button.set_sensitive(selected);
+            }
+        ));
+        table.correct_button.connect_clicked(clone!(
+            #[weak]
+            table,
+            move |_| table.correct_row(table.selection.selected())
+        ));
+        // The footer follows the filter.
+        sort_model.connect_items_changed(clone!(
+            #[weak]
+            table,
+            move |_, _, _, _| table.build_footer()
+        ));
+        table
+    }
+
+    // Replace the rows, e.g. for a new range or unit system.
+    pub fn set_rows(&self, rows: Vec<ActivityRow>, units: &Units, weighting: Weighting) {
+        *self.units.borrow_mut() = units.clone();
+        self.weighting.set(weighting);
+        let titles = column_titles(units);
+        for ((column, title), check) in self
+            .columns
+            .iter()
+            .zip(&titles)
+            .zip(std::iter::once(None).chain(self.column_checks.iter().map(Some)))
+        {
+            column.set_title(Some(title));
+            if let Some(check) = check {
+                check.set_label(Some(title));
+            }
+        }
+        *self.has_data.borrow_mut() = COLUMNS
+            .iter()
+            .map(|c| rows.iter().any(|r| !(c.text)(r).is_empty()))
+            .collect();
+        self.update_column_visibility();
+        let objects: Vec<BoxedAnyObject> = rows.into_iter().map(BoxedAnyObject::new).collect();
+        self.store.remove_all();
+        self.store.extend_from_slice(&objects);
+        self.build_footer();
+    }
+
+    // Select the row of an activity, or none.
+    pub fn select(&self, timestamp: Option<DateTime<Utc>>) {
+        let position = timestamp.and_then(|timestamp| {
+            (0..self.selection.n_items()).find(|&i| {
+                self.selection
+                    .item(i)
+                    .is_some_and(|object| row_of(&object).timestamp == timestamp)
+            })
+        });
+        self.selection
+            .set_selected(position.unwrap_or(gtk4::INVALID_LIST_POSITION));
+    }
+
+    // Call f with the metadata of an activity once edited and saved.
+    pub fn connect_edited(&self, f: impl Fn(ActivityMeta) + 'static) {
+        *self.edited.borrow_mut() = Some(Rc::new(f));
+    }
+
+    // Call f to add a manual activity (None) or edit one (its start time).
+    pub fn connect_manual(&self, f: impl Fn(Option<DateTime<Utc>>) + 'static) {
+        *self.manual.borrow_mut() = Some(Rc::new(f));
+    }
+
+    // Call f to correct the recorded values of an activity.
+    pub fn connect_correct(&self, f: impl Fn(DateTime<Utc>, PathBuf) + 'static) {
+        *self.correct.borrow_mut() = Some(Rc::new(f));
+    }
+
+    // Open the correction editor of the row at a position of the view; a
+    // manual activity is edited directly instead.
+    fn correct_row(&self, position: u32) {
+        let Some(object) = self.selection.item(position) else {
+            return;
+        };
+        let (timestamp, path, manual) = {
+            let row = row_of(&object);
+            (row.timestamp, row.path.clone(), row.is_manual())
+        };
+        if manual {
+            let callback = self.manual.borrow().clone();
+            if let Some(callback) = callback {
+                callback(Some(timestamp));
+            }
+            return;
+        }
+        let callback = self.correct.borrow().clone();
+        if let (Some(callback), Some(path)) = (callback, path) {
+            callback(timestamp, path);
+        }
+    }
+
+    // Show new metadata on the row of its activity.
+    pub fn set_meta(&self, meta: ActivityMeta) {
+        let position = (0..self.store.n_items()).find(|&i| {
+            self.store
+                .item(i)
+                .is_some_and(|object| row_of(&object).timestamp == meta.start)
+        });
+        let Some(position) = position else {
+            return;
+        };
+        if let Some(object) = self.store.item(position).and_downcast::<BoxedAnyObject>() {
+            object.borrow_mut::<ActivityRow>().meta = meta;
+            // Rebind the row and re-run the filter and the sort.
+            self.store.items_changed(position, 1, 1);
+        }
+    }
+
+    // Open the metadata editor of the row at a position of the view.
+    fn edit_row(self: &Rc<Self>, position: u32) {
+        let Some(object) = self.selection.item(position) else {
+            return;
+        };
+        let meta = row_of(&object).meta.clone();
+        let parent = self.view.root().and_downcast::<Window>();
+        let table = Rc::downgrade(self);
+        edit_meta_dialog(parent.as_ref(), meta, move |meta| {
+            let Some(table) = table.upgrade() else {
+                return;
+            };
+            let edited = table.edited.borrow().clone();
+            if let Some(edited) = edited {
+                edited(meta);
+            }
+        });
+    }
+
+    // The stats of the rows passing the filter.
+    fn visible_stats(&self) -> Vec<PlottableData> {
+        (0..self.selection.n_items())
+            .filter_map(|i| self.selection.item(i))
+            .map(|object| {
+                let row = row_of(&object);
+                PlottableData {
+                    timestamp: row.timestamp,
+                    stats: row.stats,
+                }
+            })
+            .collect()
+    }
+
+    fn update_column_visibility(&self) {
+        let hidden = self.hidden.borrow();
+        let has_data = self.has_data.borrow();
+        for ((column, def), data) in self.columns[1..].iter().zip(&COLUMNS).zip(has_data.iter()) {
+            column.set_visible(*data && !hidden.iter().any(|h| h == def.id));
+        }
+    }
+
+    // Rebuild the statistics rows from the rows shown.
+    fn build_footer(&self) {
+        let stat_collection = self.visible_stats();
+        let grid = &self.footer;
+        let mut child = grid.first_child();
+        while let Some(widget) = child {
+            child = widget.next_sibling();
+            grid.remove(&widget);
+        }
+        if stat_collection.is_empty() {
+            return;
+        }
+        let statistics = range_statistics(&stat_collection, self.weighting.get());
+        let attach_label = |text: &str, col: i32, row: i32| {
+            let label = Label::builder()
+                .label(format!("<b>{}</b>", text))
+                .use_markup(true)
+                .selectable(true)
+                .halign(gtk4::Align::Start)
+                .build();
+            grid.attach(&label, col, row, 1, 1);
+        };
+
+        let titles = column_titles(&self.units.borrow());
+        for (col, text) in titles.iter().enumerate().skip(1) {
+            if find_metric(COLUMNS[col - 1].id).is_some() {
+                attach_label(text, col as i32, 0);
+            }
+        }
+        attach_label(&tr("ACTIVITY_COUNT", None), 0, 1);
+        attach_label(&statistics.count.to_string(), 1, 1);
+
+        // The maximum of pace is its best, i.e. lowest, value.
+        type Row = fn(&Statistics, &Metric) -> Option<f64>;
+        let rows: [(&str, Row); 8] = [
+            ("TOTAL", |s, m| m.additive.then_some(s.total)),
+            ("AVERAGE", |s, _| Some(s.mean)),
+            ("MEDIAN", |s, _| Some(s.median)),
+            ("STANDARD_DEVIATION", |s, _| Some(s.std_dev)),
+            ("PERCENTILE_10", |s, _| Some(s.p10)),
+            ("PERCENTILE_90", |s, _| Some(s.p90)),
+            ("MINIMUM", |s, m| Some(s.worst(m))),
+            ("MAXIMUM", |s, m| Some(s.best(m))),
+        ];
+        for (i, (key, value)) in rows.iter().enumerate() {
+            let row = 2 + i as i32;
+            attach_label(&tr(key, None), 0, row);
+            for (col, column) in COLUMNS.iter().enumerate() {
+                let Some(metric) = find_metric(column.id) else {
+                    continue;
+                };
+                let Some(val) = statistics.get(metric.name).and_then(|s| value(s, metric)) else {
+                    continue;
+                };
+                let text = match metric.name {
+                    "pace" => format_pace(val),
+                    // Totals of whole numbers stay whole.
+                    "calories" | "ascent" | "descent" if *key == "TOTAL" => format!("{:.0}", val),
+                    _ => format!("{:.2}", val),
+                };
+                attach_label(&text, col as i32 + 1, row);
+            }
+        }
+    }
+}
+
+// Editor of the title, tags, exertion and notes of an activity; on_save is
+// called with the edited metadata.
+fn edit_meta_dialog(
+    parent: Option<&Window>,
+    meta: ActivityMeta,
+    on_save: impl Fn(ActivityMeta) + 'static,
+) {
+    let grid = gtk4::Grid::builder()
+        .column_spacing(12)
+        .row_spacing(8)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .build();
+    let title = Entry::builder().text(&meta.title).hexpand(true).build();
+    let tags = Entry::builder()
+        .text(meta.tags.join(", "))
+        .placeholder_text(tr("TAGS_PLACEHOLDER", None))
+        .build();
+    let rpe = SpinButton::with_range(0.0, MAX_RPE as f64, 1.0);
+    rpe.set_value(meta.rpe.unwrap_or(0) as f64);
+    rpe.set_tooltip_text(Some(&tr("TOOLTIP_RPE", None)));
+    let notes = TextView::builder()
+        .wrap_mode(gtk4::WrapMode::WordChar)
+        .build();
+    notes.buffer().set_text(&meta.notes);
+    let notes_window = ScrolledWindow::builder()
+        .min_content_height(120)
+        .vexpand(true)
+        .child(&notes)
+        .build();
+    for (row, (key, widget)) in [
+        ("LABEL_TITLE", title.upcast_ref::<gtk4::Widget>()),
+        ("LABEL_TAGS", tags.upcast_ref()),
+        ("LABEL_RPE", rpe.upcast_ref()),
+        ("LABEL_NOTES", notes_window.upcast_ref()),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let label = Label::builder()
+            .label(tr(key, None))
+            .halign(gtk4::Align::Start)
+            .valign(gtk4::Align::Start)
+            .build();
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(widget, 1, row as i32, 1, 1);
+    }
+    let cancel = Button::with_label(&tr("CANCEL_BUTTON", None));
+    let save = Button::with_label(&tr("SAVE_BUTTON", None));
+    save.add_css_class("suggested-action");
+    let buttons = gtk4::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(10)
+        .halign(gtk4::Align::End)
+        .build();
+    buttons.append(&cancel);
+    buttons.append(&save);
+    grid.attach(&buttons, 0, 4, 2, 1);
+
+    let window = Window::builder()
+        .title(format!(
+            "{} {}",
+            tr("EDIT_ACTIVITY_TITLE", None),
+            meta.start.format("%Y-%m-%d %H:%M")
+        ))
+        .modal(true)
+        .default_width(420)
+        .default_height(360)
+        .child(&grid)
+        .build();
+    window.set_transient_for(parent);
+    cancel.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| window.close()
+    ));
+    save.connect_clicked(clone!(
+        #[weak]
+        window,
+        move |_| {
+            let buffer = notes.buffer();
+            let rpe = rpe.value_as_int() as u8;
+            on_save(ActivityMeta {
+                title: title.text().trim().to_string(),
+                notes: buffer
+                    .text(&buffer.start_iter(), &buffer.end_iter(), false)
+                    .trim()
+                    .to_string(),
+                tags: parse_tags(&tags.text()),
+                rpe: (rpe > 0).then_some(rpe),
+                ..meta.clone()
+            });
+            window.close();
+        }
+    ));
+    window.present();
+}